@@ -58,6 +58,18 @@ disks-title = Disk load
 disks-title-combined = Disk load in bytes per second
 disks-title-write = Disk write in bytes per second
 disks-title-read = Disk read in bytes per second
+disks-title-iops-combined = Disk operations per second
+disks-title-iops-write = Disk write operations per second
+disks-title-iops-read = Disk read operations per second
+disks-title-latency-combined = Disk latency per operation
+disks-title-latency-write = Disk write latency per operation
+disks-title-latency-read = Disk read latency per operation
+disks-title-utilization = Disk utilization of the busiest drive
+disks-metric = Metric
+disks-metric-throughput = Throughput
+disks-metric-iops = IOPS
+disks-metric-latency = Latency
+disks-metric-utilization = Utilization
 enable-disks-combined = Combine disk Write and Read
 choose-sysmon = System Monitor
 memory-as-percentage = As percentage
@@ -71,6 +83,26 @@ gpu-title = Graphics
 gpu-title-usage = GPU load
 gpu-title-temperature = GPU Temperature
 gpu-title-vram = VRAM load
+gpu-title-processes = Top processes
+gpu-metric-power = Power
+gpu-metric-core-clock = Core clock
+gpu-metric-memory-clock = Memory clock
+gpu-metric-fan = Fan
+gpu-metric-memory-busy = Memory controller load
+gpu-metric-gtt = GTT memory
+gpu-metric-temp-junction = Junction temperature
+gpu-metric-temp-memory = Memory temperature
+gpu-metric-encoder = Video encoder
+gpu-metric-decoder = Video decoder
+gpu-metric-pcie-rx = PCIe receive
+gpu-metric-pcie-tx = PCIe transmit
+gpu-throttle = Clock throttling
+gpu-throttle-warning = Show warning icon when throttled
+gpu-throttle-none = None
+gpu-throttle-power = Power limit
+gpu-throttle-thermal = Thermal limit
+gpu-throttle-hw-slowdown = Hardware slowdown
+gpu-throttle-power-brake = External power brake
 sensor-gpu = GPU
 sensor-vram = VRAM
 settings-disable-on-battery = Disable monitoring while on battery to allow the GPU to sleep if possible
@@ -98,4 +130,103 @@ graph-memory-allocated = Allocated.
 graph-ring-unused = Unused.
 graph-cpu-load = Load.
 graph-cpu-idle = Idle.
-allocated-explanation = Allocated = total minus free. Includes system cache and buffers, which improve performance and are resized/released as needed.
\ No newline at end of file
+allocated-explanation = Allocated = total minus free. Includes system cache and buffers, which improve performance and are resized/released as needed.
+sensor-filesystems = Filesystems
+settings-filesystems = Filesystems
+filesystems-title = Filesystem space
+filesystems-mounts = Mount points
+filesystems-predict-full = Show time until full
+sensor-disk-temperature = Drive Temperature
+settings-disk-temperature = Drive Temperature
+disk-temperature-title = Drive Temperature
+disk-temperature-drives = Drives
+settings-storage-health = Storage Health
+storage-health-title = Storage Health
+storage-health-warning = Show warning in panel
+storage-health-devices = Arrays and filesystems
+storage-health-none = No RAID arrays, btrfs or zfs filesystems found
+storage-health-ok = OK
+storage-health-problems = { $count } problems
+storage-health-inactive = inactive
+storage-health-degraded = degraded, { $up } of { $total } devices
+storage-health-failed = failed: { $devices }
+storage-health-btrfs-errors = errors: write { $write }, read { $read }, flush { $flush }, corruption { $corruption }, generation { $generation }
+storage-health-btrfs-short = { $errors } errors
+sensor-gpu-combined = All GPUs
+settings-gpu-combined = All GPUs
+gpu-combined-heading = All GPUs combined
+gpu-combined-title = { $count } GPUs
+gpu-combined-mode = Combine usage as
+gpu-combined-average = Average
+gpu-combined-max = Maximum
+gpu-combined-vram = Show total VRAM label
+gpu-combined-temp = Show hottest temperature label
+graph-gpu-usage = Usage.
+graph-gpu-vram = VRAM.
+graph-gpu-1 = First GPU.
+graph-gpu-2 = Second GPU.
+graph-gpu-3 = Third GPU.
+gpu-title-power = Power state
+gpu-power-state = Runtime power management
+gpu-power-indicator = Show power state icon in panel
+gpu-power-holders = Device open by
+gpu-power-no-holders = No visible processes
+gpu-power-wake-ups = Recent wake-ups
+gpu-power-active = Active
+gpu-power-suspending = Suspending
+gpu-power-suspended = Suspended
+gpu-power-resuming = Resuming
+gpu-power-always-on = Always on, runtime suspend disabled
+cpu-temp-zone = Read from the thermal zone { $zone }.
+cpu-temp-source = Temperature source
+cpu-temp-automatic = Automatic
+cpu-temp-zones = Thermal zones
+alert-enable = Notify when a threshold is crossed
+alert-warning = Warning threshold ({ $unit })
+alert-critical = Critical threshold ({ $unit })
+alert-hysteresis = Clear below threshold by ({ $unit })
+alert-duration = Seconds above threshold before notifying
+alert-warning-title = { $name } warning
+alert-critical-title = { $name } critical
+alert-recovered-title = { $name } back to normal
+alert-raised-body = Now at { $value }, the threshold is { $threshold }.
+alert-recovered-body = Now at { $value }.
+color-stops-enable = Color chart and label by value
+color-stop-from = From ({ $unit })
+color-stop-add = Add color
+color-green = Green
+color-yellow = Yellow
+color-amber = Amber
+color-orange = Orange
+color-red = Red
+color-blue = Blue
+color-purple = Purple
+color-grey = Grey
+alert-command = Command to run
+alert-command-placeholder = Run with sh, sees MINIMON_SENSOR, MINIMON_VALUE and MINIMON_STATE
+alert-command-cooldown = Seconds before a new alert runs the command again
+alert-command-log = Alert commands
+alert-command-running = Running
+alert-command-exited = Exit code { $code }
+alert-command-killed = Killed
+alert-command-failed = Failed: { $error }
+chart-window = Chart time window
+chart-window-live = Live
+chart-window-minutes = { $minutes } minutes
+chart-window-hours = { $hours ->
+    [one] 1 hour
+   *[other] { $hours } hours
+}
+chart-window-days = { $days ->
+    [one] 1 day
+   *[other] { $days } days
+}
+export-history = Export history
+export-csv = CSV
+export-json = JSON Lines
+export-saved = Saved to { $path }
+export-failed = Export failed: { $error }
+export-no-directory = No download directory
+prometheus-enable = Serve metrics for Prometheus on port
+prometheus-serving = Serving http://127.0.0.1:{ $port }/metrics
+prometheus-failed = Can't serve metrics: { $error }
@@ -0,0 +1,294 @@
+//! User commands run on alert events, with the sensor item, its value and the alert
+//! state passed in MINIMON_* environment variables.
+
+use cosmic::{
+    Element,
+    iced::widget::column,
+    widget::{self, Column, settings},
+};
+use log::{info, warn};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::{app::Message, fl};
+
+// Number of invocations kept for the popup
+const MAX_LOG: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandStatus {
+    Running,
+    // None if killed by a signal
+    Exited(Option<i32>),
+    Failed(String),
+}
+
+impl std::fmt::Display for CommandStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandStatus::Running => write!(f, "{}", fl!("alert-command-running")),
+            CommandStatus::Exited(Some(code)) => {
+                write!(f, "{}", fl!("alert-command-exited", code = *code))
+            }
+            CommandStatus::Exited(None) => write!(f, "{}", fl!("alert-command-killed")),
+            CommandStatus::Failed(e) => {
+                write!(f, "{}", fl!("alert-command-failed", error = e.as_str()))
+            }
+        }
+    }
+}
+
+/// One invocation of an alert command
+#[derive(Debug, Clone)]
+pub struct CommandRun {
+    id: u64,
+    pub time: chrono::DateTime<chrono::Local>,
+    pub name: String,
+    // warning, critical or normal
+    pub state: &'static str,
+    pub value: String,
+    pub status: CommandStatus,
+}
+
+/// What an alert event passes to the command
+pub struct CommandEvent<'a> {
+    pub key: &'a str,
+    pub name: &'a str,
+    pub state: &'static str,
+    pub recovered: bool,
+    pub value: f64,
+    pub unit: &'a str,
+    pub threshold: Option<u64>,
+}
+
+impl CommandEvent<'_> {
+    fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("MINIMON_KEY", self.key.to_string()),
+            ("MINIMON_SENSOR", self.name.to_string()),
+            ("MINIMON_STATE", self.state.to_string()),
+            ("MINIMON_VALUE", format!("{:.1}", self.value)),
+            ("MINIMON_UNIT", self.unit.to_string()),
+            (
+                "MINIMON_THRESHOLD",
+                self.threshold.map(|t| t.to_string()).unwrap_or_default(),
+            ),
+        ]
+    }
+}
+
+#[derive(Default)]
+pub struct CommandRunner {
+    // When the command of each key and level last ran for a raised alert
+    last_raised: HashMap<(String, &'static str), Instant>,
+    // Keys whose command ran for the current alert, so it runs on recovery too
+    raised: HashSet<String>,
+    children: Vec<(u64, Child)>,
+    log: VecDeque<CommandRun>,
+    next_id: u64,
+}
+
+impl CommandRunner {
+    // Raised alerts run the command at most once per cooldown and level, so an escalation
+    // to critical always runs it. A recovery runs it only if the alert it ends did,
+    // so a paused job is always resumed.
+    fn should_run(&mut self, event: &CommandEvent, cooldown: u32, now: Instant) -> bool {
+        if event.recovered {
+            return self.raised.remove(event.key);
+        }
+        let cooldown = Duration::from_secs(u64::from(cooldown));
+        let level = (event.key.to_string(), event.state);
+        if self
+            .last_raised
+            .get(&level)
+            .is_some_and(|&t| now.duration_since(t) < cooldown)
+        {
+            return false;
+        }
+        self.last_raised.insert(level, now);
+        self.raised.insert(event.key.to_string());
+        true
+    }
+
+    /// Run `command` with `sh -c` for the event, unless it is in its cooldown
+    pub fn run(&mut self, command: &str, cooldown: u32, event: &CommandEvent, now: Instant) {
+        if command.trim().is_empty() || !self.should_run(event, cooldown, now) {
+            return;
+        }
+
+        let env = event.env();
+        // Inside a flatpak the command runs on the host, which doesn't inherit our environment
+        let mut cmd = if std::env::var("FLATPAK_ID").is_ok() {
+            let mut cmd = Command::new("flatpak-spawn");
+            cmd.arg("--host");
+            cmd.args(env.iter().map(|(k, v)| format!("--env={k}={v}")));
+            cmd.args(["sh", "-c", command]);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", command]).envs(env);
+            cmd
+        };
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let status = match cmd.spawn() {
+            Ok(child) => {
+                info!("Alert command for {} started: {command}", event.key);
+                self.children.push((id, child));
+                CommandStatus::Running
+            }
+            Err(e) => {
+                warn!("Alert command for {} failed to start: {e}", event.key);
+                CommandStatus::Failed(e.to_string())
+            }
+        };
+
+        if self.log.len() == MAX_LOG {
+            self.log.pop_front();
+        }
+        self.log.push_back(CommandRun {
+            id,
+            time: chrono::Local::now(),
+            name: event.name.to_string(),
+            state: event.state,
+            value: format!("{:.0}{}", event.value, event.unit),
+            status,
+        });
+    }
+
+    /// Collect the exit status of finished commands, never blocks
+    pub fn poll(&mut self) {
+        let log = &mut self.log;
+        self.children.retain_mut(|(id, child)| {
+            let status = match child.try_wait() {
+                Ok(Some(status)) => CommandStatus::Exited(status.code()),
+                Ok(None) => return true,
+                Err(e) => CommandStatus::Failed(e.to_string()),
+            };
+            info!("Alert command {id} finished: {status:?}");
+            if let Some(run) = log.iter_mut().find(|r| r.id == *id) {
+                run.status = status;
+            }
+            false
+        });
+    }
+
+    /// Past invocations, oldest first
+    pub fn log(&self) -> impl DoubleEndedIterator<Item = &CommandRun> {
+        self.log.iter()
+    }
+
+    /// The invocation log for the popup, None before the first command ran
+    pub fn log_ui<'a>(&self) -> Option<Element<'a, Message>> {
+        if self.log.is_empty() {
+            return None;
+        }
+        let spacing = cosmic::theme::spacing();
+
+        let mut runs = Column::new().spacing(spacing.space_xxs);
+        for run in self.log().rev() {
+            runs = runs.push(settings::item(
+                format!(
+                    "{} {} {} {}",
+                    run.time.format("%H:%M:%S"),
+                    run.name,
+                    run.state,
+                    run.value
+                ),
+                widget::text::body(run.status.to_string()),
+            ));
+        }
+
+        Some(
+            column!(widget::text::heading(fl!("alert-command-log")), runs)
+                .spacing(spacing.space_xs)
+                .into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(recovered: bool) -> CommandEvent<'static> {
+        CommandEvent {
+            key: "gpu:1:temp",
+            name: "GPU: Temperature",
+            state: if recovered { "normal" } else { "critical" },
+            recovered,
+            value: 92.0,
+            unit: "°C",
+            threshold: (!recovered).then_some(90),
+        }
+    }
+
+    fn warning() -> CommandEvent<'static> {
+        CommandEvent {
+            state: "warning",
+            value: 82.0,
+            threshold: Some(80),
+            ..event(false)
+        }
+    }
+
+    #[test]
+    fn cooldown_limits_raised() {
+        let mut runner = CommandRunner::default();
+        let start = Instant::now();
+
+        assert!(runner.should_run(&event(false), 60, start));
+        assert!(runner.should_run(&event(true), 60, start + Duration::from_secs(10)));
+        assert!(!runner.should_run(&event(false), 60, start + Duration::from_secs(20)));
+        // Its alert didn't run the command, so neither does the recovery
+        assert!(!runner.should_run(&event(true), 60, start + Duration::from_secs(30)));
+        assert!(runner.should_run(&event(false), 60, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn escalation_skips_cooldown() {
+        let mut runner = CommandRunner::default();
+        let start = Instant::now();
+
+        assert!(runner.should_run(&warning(), 60, start));
+        assert!(runner.should_run(&event(false), 60, start + Duration::from_secs(10)));
+        assert!(!runner.should_run(&warning(), 60, start + Duration::from_secs(20)));
+        assert!(!runner.should_run(&event(false), 60, start + Duration::from_secs(30)));
+        assert!(runner.should_run(&event(true), 60, start + Duration::from_secs(40)));
+    }
+
+    #[test]
+    fn command_gets_environment() {
+        let dir = std::env::temp_dir().join(format!("minimon-command-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("env");
+
+        let mut runner = CommandRunner::default();
+        let command = format!(
+            r#"printf '%s|%s|%s|%s' "$MINIMON_SENSOR" "$MINIMON_STATE" "$MINIMON_VALUE" "$MINIMON_THRESHOLD" > '{}'"#,
+            out.display()
+        );
+        runner.run(&command, 0, &event(false), Instant::now());
+
+        for _ in 0..100 {
+            runner.poll();
+            if runner.children.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        let run = runner.log().next().unwrap();
+        assert_eq!(run.status, CommandStatus::Exited(Some(0)));
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "GPU: Temperature|critical|92.0|90"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
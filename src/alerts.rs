@@ -0,0 +1,590 @@
+//! Warning and critical thresholds of the sensor items, reported as desktop notifications.
+
+use cosmic::{
+    Element,
+    iced::widget::row,
+    iced_widget::Column,
+    widget::{self, settings, toggler},
+};
+use log::info;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::{
+    alert_commands::{CommandEvent, CommandRunner},
+    app::Message,
+    config::{AlertConfig, DeviceKind},
+    fl,
+    notifications::{BackgroundNotifier, Notification, Urgency},
+};
+
+// A level that tripped is not notified again within this time, so a value
+// flapping around a threshold doesn't flood the notification list
+const REPEAT_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertLevel {
+    #[default]
+    Normal,
+    Warning,
+    Critical,
+}
+
+impl AlertLevel {
+    fn threshold(self, config: &AlertConfig) -> Option<f64> {
+        match self {
+            AlertLevel::Normal => None,
+            AlertLevel::Warning => config.warning.map(|t| t as f64),
+            AlertLevel::Critical => config.critical.map(|t| t as f64),
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            AlertLevel::Normal | AlertLevel::Warning => 0,
+            AlertLevel::Critical => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertEvent {
+    Raised(AlertLevel),
+    Recovered,
+}
+
+/// Alert state of one sensor value
+#[derive(Debug, Default)]
+pub struct AlertState {
+    level: AlertLevel,
+    // Since when the value is above the warning and critical thresholds
+    above_since: [Option<Instant>; 2],
+    // When the warning and critical levels were last notified
+    last_notified: [Option<Instant>; 2],
+    // The current level was notified, so its recovery is too
+    notified: bool,
+}
+
+impl AlertState {
+    // Highest level the value reaches. Levels already active only clear once
+    // the value drops the hysteresis below their threshold.
+    fn reached(&self, value: f64, config: &AlertConfig) -> AlertLevel {
+        [AlertLevel::Critical, AlertLevel::Warning]
+            .into_iter()
+            .find(|&level| {
+                level.threshold(config).is_some_and(|threshold| {
+                    if level <= self.level {
+                        value > threshold - config.hysteresis as f64
+                    } else {
+                        value >= threshold
+                    }
+                })
+            })
+            .unwrap_or(AlertLevel::Normal)
+    }
+
+    /// Feed a new sample, returns the level change if any
+    pub fn update(&mut self, value: f64, config: &AlertConfig, now: Instant) -> Option<AlertEvent> {
+        let reached = self.reached(value, config);
+
+        for level in [AlertLevel::Warning, AlertLevel::Critical] {
+            let since = &mut self.above_since[level.index()];
+            if reached >= level {
+                since.get_or_insert(now);
+            } else {
+                *since = None;
+            }
+        }
+
+        if reached < self.level {
+            self.level = reached;
+            return (reached == AlertLevel::Normal).then_some(AlertEvent::Recovered);
+        }
+
+        // Rising needs the value to stay above the threshold for the whole duration
+        let duration = Duration::from_secs(u64::from(config.duration));
+        let tripped = [AlertLevel::Critical, AlertLevel::Warning]
+            .into_iter()
+            .find(|&level| {
+                self.above_since[level.index()]
+                    .is_some_and(|since| now.duration_since(since) >= duration)
+            })
+            .unwrap_or(AlertLevel::Normal);
+
+        if tripped <= self.level {
+            return None;
+        }
+        self.level = tripped;
+        Some(AlertEvent::Raised(tripped))
+    }
+
+    /// Whether to show a notification for the event. A level is notified at most once
+    /// per REPEAT_INTERVAL, a recovery only if the alert it ends was.
+    pub fn should_notify(&mut self, event: AlertEvent, now: Instant) -> bool {
+        match event {
+            AlertEvent::Recovered => std::mem::take(&mut self.notified),
+            AlertEvent::Raised(level) => {
+                let last = &mut self.last_notified[level.index()];
+                if last.is_some_and(|t| now.duration_since(t) < REPEAT_INTERVAL) {
+                    return false;
+                }
+                *last = Some(now);
+                self.notified = true;
+                true
+            }
+        }
+    }
+}
+
+/// Latest value of a sensor item with alerts, unit as shown in the notification
+pub struct AlertSample {
+    // Stable across updates, like "cpu" or "gpu:<id>:temp"
+    pub key: String,
+    pub name: String,
+    pub value: Option<f64>,
+    pub unit: &'static str,
+    pub config: AlertConfig,
+}
+
+#[derive(Default)]
+pub struct Alerts {
+    states: HashMap<String, AlertState>,
+    // Last notification of each key, replaced by the next one
+    notifications: HashMap<String, u32>,
+    notifier: BackgroundNotifier,
+    commands: CommandRunner,
+}
+
+impl Alerts {
+    pub fn update(&mut self, samples: Vec<AlertSample>) {
+        let now = Instant::now();
+        self.commands.poll();
+        self.notifications.extend(self.notifier.poll());
+        self.states
+            .retain(|key, _| samples.iter().any(|s| &s.key == key && s.config.enabled));
+
+        for sample in samples.into_iter().filter(|s| s.config.enabled) {
+            let Some(value) = sample.value else {
+                continue;
+            };
+            let state = self.states.entry(sample.key.clone()).or_default();
+            // Commands have their own cooldown, only notifications are rate limited here
+            if let Some(event) = state.update(value, &sample.config, now) {
+                if state.should_notify(event, now) {
+                    self.notify(&sample, value, event);
+                }
+                self.run_command(&sample, value, event, now);
+            }
+        }
+    }
+
+    fn notify(&mut self, sample: &AlertSample, value: f64, event: AlertEvent) {
+        let name = sample.name.as_str();
+        let value = format!("{value:.0}{}", sample.unit);
+        let threshold = |level: AlertLevel| {
+            format!(
+                "{}{}",
+                level.threshold(&sample.config).unwrap_or_default(),
+                sample.unit
+            )
+        };
+
+        let (summary, body, urgency) = match event {
+            AlertEvent::Raised(AlertLevel::Critical) => (
+                fl!("alert-critical-title", name = name),
+                fl!(
+                    "alert-raised-body",
+                    value = value,
+                    threshold = threshold(AlertLevel::Critical)
+                ),
+                Urgency::Critical,
+            ),
+            AlertEvent::Raised(_) => (
+                fl!("alert-warning-title", name = name),
+                fl!(
+                    "alert-raised-body",
+                    value = value,
+                    threshold = threshold(AlertLevel::Warning)
+                ),
+                Urgency::Normal,
+            ),
+            AlertEvent::Recovered => (
+                fl!("alert-recovered-title", name = name),
+                fl!("alert-recovered-body", value = value),
+                Urgency::Low,
+            ),
+        };
+        info!("Alert {}: {event:?}", sample.key);
+
+        let notification = Notification {
+            summary,
+            body,
+            urgency,
+            replaces_id: self
+                .notifications
+                .get(&sample.key)
+                .copied()
+                .unwrap_or_default(),
+        };
+        self.notifier.send(&sample.key, notification);
+    }
+
+    fn run_command(&mut self, sample: &AlertSample, value: f64, event: AlertEvent, now: Instant) {
+        let (state, threshold) = match event {
+            AlertEvent::Raised(AlertLevel::Critical) => ("critical", sample.config.critical),
+            AlertEvent::Raised(_) => ("warning", sample.config.warning),
+            AlertEvent::Recovered => ("normal", None),
+        };
+        self.commands.run(
+            &sample.config.command,
+            sample.config.command_cooldown,
+            &CommandEvent {
+                key: &sample.key,
+                name: &sample.name,
+                state,
+                recovered: event == AlertEvent::Recovered,
+                value,
+                unit: sample.unit,
+                threshold,
+            },
+            now,
+        );
+    }
+
+    /// Past alert commands for the popup
+    pub fn command_log_ui<'a>(&self) -> Option<Element<'a, Message>> {
+        self.commands.log_ui()
+    }
+}
+
+// Empty clears the threshold, anything unparsable keeps the previous value
+fn parse_threshold(input: &str, previous: Option<u64>) -> Option<u64> {
+    if input.trim().is_empty() {
+        None
+    } else {
+        input.trim().parse().ok().or(previous)
+    }
+}
+
+/// The alert settings shared by all sensor items
+pub fn settings_ui<'a>(
+    device: DeviceKind,
+    id: Option<String>,
+    config: &AlertConfig,
+    unit: &str,
+) -> Element<'a, Message> {
+    let mut column = Vec::new();
+
+    let toggle_id = id.clone();
+    let toggle_config = config.clone();
+    column.push(
+        settings::item(
+            fl!("alert-enable"),
+            toggler(config.enabled).on_toggle(move |enabled| {
+                Message::AlertChanged(
+                    device,
+                    toggle_id.clone(),
+                    AlertConfig {
+                        enabled,
+                        ..toggle_config.clone()
+                    },
+                )
+            }),
+        )
+        .into(),
+    );
+
+    if config.enabled {
+        let warning_id = id.clone();
+        let warning_config = config.clone();
+        column.push(
+            settings::item(
+                fl!("alert-warning", unit = unit),
+                row!(
+                    widget::text_input(
+                        "",
+                        config.warning.map(|t| t.to_string()).unwrap_or_default()
+                    )
+                    .width(100)
+                    .on_input(move |s| {
+                        Message::AlertChanged(
+                            device,
+                            warning_id.clone(),
+                            AlertConfig {
+                                warning: parse_threshold(&s, warning_config.warning),
+                                ..warning_config.clone()
+                            },
+                        )
+                    })
+                ),
+            )
+            .into(),
+        );
+
+        let critical_id = id.clone();
+        let critical_config = config.clone();
+        column.push(
+            settings::item(
+                fl!("alert-critical", unit = unit),
+                row!(
+                    widget::text_input(
+                        "",
+                        config.critical.map(|t| t.to_string()).unwrap_or_default()
+                    )
+                    .width(100)
+                    .on_input(move |s| {
+                        Message::AlertChanged(
+                            device,
+                            critical_id.clone(),
+                            AlertConfig {
+                                critical: parse_threshold(&s, critical_config.critical),
+                                ..critical_config.clone()
+                            },
+                        )
+                    })
+                ),
+            )
+            .into(),
+        );
+
+        let hysteresis_id = id.clone();
+        let hysteresis_config = config.clone();
+        column.push(
+            settings::item(
+                fl!("alert-hysteresis", unit = unit),
+                row!(
+                    widget::text_input("", config.hysteresis.to_string())
+                        .width(100)
+                        .on_input(move |s| {
+                            Message::AlertChanged(
+                                device,
+                                hysteresis_id.clone(),
+                                AlertConfig {
+                                    hysteresis: parse_threshold(
+                                        &s,
+                                        Some(hysteresis_config.hysteresis),
+                                    )
+                                    .unwrap_or(0),
+                                    ..hysteresis_config.clone()
+                                },
+                            )
+                        })
+                ),
+            )
+            .into(),
+        );
+
+        let duration_id = id.clone();
+        let duration_config = config.clone();
+        column.push(
+            settings::item(
+                fl!("alert-duration"),
+                widget::spin_button(
+                    config.duration.to_string(),
+                    config.duration,
+                    5,
+                    0,
+                    600,
+                    move |duration| {
+                        Message::AlertChanged(
+                            device,
+                            duration_id.clone(),
+                            AlertConfig {
+                                duration,
+                                ..duration_config.clone()
+                            },
+                        )
+                    },
+                ),
+            )
+            .into(),
+        );
+
+        let command_id = id.clone();
+        let command_config = config.clone();
+        column.push(
+            settings::item_row(vec![
+                widget::text::body(fl!("alert-command")).into(),
+                widget::text_input(fl!("alert-command-placeholder"), config.command.clone())
+                    .on_input(move |command| {
+                        Message::AlertChanged(
+                            device,
+                            command_id.clone(),
+                            AlertConfig {
+                                command,
+                                ..command_config.clone()
+                            },
+                        )
+                    })
+                    .into(),
+            ])
+            .into(),
+        );
+
+        if !config.command.trim().is_empty() {
+            let cooldown_config = config.clone();
+            column.push(
+                settings::item(
+                    fl!("alert-command-cooldown"),
+                    widget::spin_button(
+                        config.command_cooldown.to_string(),
+                        config.command_cooldown,
+                        30,
+                        0,
+                        3600,
+                        move |command_cooldown| {
+                            Message::AlertChanged(
+                                device,
+                                id.clone(),
+                                AlertConfig {
+                                    command_cooldown,
+                                    ..cooldown_config.clone()
+                                },
+                            )
+                        },
+                    ),
+                )
+                .into(),
+            );
+        }
+    }
+
+    Column::with_children(column)
+        .spacing(cosmic::theme::active().cosmic().space_xs())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> AlertConfig {
+        AlertConfig {
+            enabled: true,
+            warning: Some(80),
+            critical: Some(90),
+            hysteresis: 5,
+            duration: 10,
+            ..AlertConfig::default()
+        }
+    }
+
+    fn at(start: Instant, secs: u64) -> Instant {
+        start + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn raises_after_duration() {
+        let config = config();
+        let start = Instant::now();
+        let mut state = AlertState::default();
+
+        assert_eq!(state.update(85.0, &config, start), None);
+        assert_eq!(state.update(85.0, &config, at(start, 5)), None);
+        assert_eq!(
+            state.update(85.0, &config, at(start, 10)),
+            Some(AlertEvent::Raised(AlertLevel::Warning))
+        );
+        assert_eq!(state.update(85.0, &config, at(start, 11)), None);
+        assert_eq!(state.level, AlertLevel::Warning);
+    }
+
+    #[test]
+    fn short_spike_is_ignored() {
+        let config = config();
+        let start = Instant::now();
+        let mut state = AlertState::default();
+
+        assert_eq!(state.update(95.0, &config, start), None);
+        assert_eq!(state.update(50.0, &config, at(start, 5)), None);
+        assert_eq!(state.update(95.0, &config, at(start, 9)), None);
+        assert_eq!(state.update(95.0, &config, at(start, 15)), None);
+        assert_eq!(
+            state.update(95.0, &config, at(start, 19)),
+            Some(AlertEvent::Raised(AlertLevel::Critical))
+        );
+    }
+
+    #[test]
+    fn hysteresis_delays_recovery() {
+        let config = config();
+        let start = Instant::now();
+        let mut state = AlertState::default();
+
+        state.update(85.0, &config, start);
+        state.update(85.0, &config, at(start, 10));
+        assert_eq!(state.update(78.0, &config, at(start, 11)), None);
+        assert_eq!(state.level, AlertLevel::Warning);
+        assert_eq!(
+            state.update(75.0, &config, at(start, 12)),
+            Some(AlertEvent::Recovered)
+        );
+        assert_eq!(state.level, AlertLevel::Normal);
+    }
+
+    #[test]
+    fn repeats_are_rate_limited() {
+        let config = AlertConfig {
+            duration: 0,
+            ..config()
+        };
+        let start = Instant::now();
+        let mut state = AlertState::default();
+        let mut notified = |value: f64, secs: u64| {
+            let now = at(start, secs);
+            state
+                .update(value, &config, now)
+                .map(|event| (event, state.should_notify(event, now)))
+        };
+
+        let warning = AlertEvent::Raised(AlertLevel::Warning);
+        assert_eq!(notified(85.0, 0), Some((warning, true)));
+        assert_eq!(notified(50.0, 1), Some((AlertEvent::Recovered, true)));
+        // Reported, but not notified, and neither is its recovery
+        assert_eq!(notified(85.0, 2), Some((warning, false)));
+        assert_eq!(notified(50.0, 3), Some((AlertEvent::Recovered, false)));
+
+        assert_eq!(notified(85.0, 301), Some((warning, true)));
+    }
+
+    #[test]
+    fn commands_run_on_every_trip() {
+        let config = AlertConfig {
+            duration: 0,
+            command: String::from("true"),
+            command_cooldown: 0,
+            ..config()
+        };
+        let sample = AlertSample {
+            key: String::from("cpu"),
+            name: String::from("CPU"),
+            value: None,
+            unit: "%",
+            config: config.clone(),
+        };
+        let start = Instant::now();
+        let mut state = AlertState::default();
+        let mut alerts = Alerts::default();
+
+        // A second trip within REPEAT_INTERVAL isn't notified, its command still runs
+        let mut notifications = 0;
+        for (secs, value) in [(0, 85.0), (1, 50.0), (2, 85.0), (3, 50.0)] {
+            let now = at(start, secs);
+            if let Some(event) = state.update(value, &config, now) {
+                if state.should_notify(event, now) {
+                    notifications += 1;
+                }
+                alerts.run_command(&sample, value, event, now);
+            }
+        }
+        assert_eq!(notifications, 2);
+        assert_eq!(
+            alerts
+                .commands
+                .log()
+                .map(|run| run.state)
+                .collect::<Vec<_>>(),
+            ["warning", "normal", "warning", "normal"]
+        );
+    }
+}
@@ -8,7 +8,7 @@ use cosmic::iced::advanced::graphics::text::cosmic_text::{Buffer, FontSystem, Me
 use cosmic::iced::alignment::Horizontal::{self};
 use cosmic::iced_winit::graphics::text::cosmic_text::Attrs;
 
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::{fs, time};
 
 use cosmic::app::{Core, Task};
@@ -33,23 +33,33 @@ use cosmic::{
 use zbus::blocking::Connection;
 use zvariant::OwnedObjectPath;
 
-use log::{error, info};
+use log::{error, info, warn};
 
+use crate::alerts::{AlertSample, Alerts};
 use crate::barchart::StackedBarSvg;
 use crate::colorpicker::ColorPicker;
 use crate::config::{
-    ChartColors, ChartKind, ColorVariant, ContentType, DeviceKind, DisksVariant, GpuConfig,
-    NetworkVariant,
+    AlertConfig, ChartColors, ChartKind, ChartWindow, ColorStops, ColorVariant, ContentType,
+    DeviceKind, DisksMetric, DisksVariant, GpuCombinedMode, GpuConfig, NetworkVariant,
 };
+use crate::metrics_service::{Metric, MetricsService};
+use crate::prometheus::{self, Exporter};
 use crate::sensors::cpu::Cpu;
 use crate::sensors::cputemp::CpuTemp;
 use crate::sensors::disks::{self, Disks};
-use crate::sensors::gpus::{Gpu, list_gpus};
+use crate::sensors::disktemp::DiskTemp;
+use crate::sensors::filesystems::Filesystems;
+use crate::sensors::gpu_combined::GpuCombined;
+use crate::sensors::gpus::{
+    Gpu, gpu_devices, gpu_slots, list_gpus, list_new_gpus, sync_gpu_configs,
+};
 use crate::sensors::memory::Memory;
 use crate::sensors::network::{self, Network};
+use crate::sensors::storage_health::StorageHealth;
 use crate::sensors::{Sensor, TempUnit};
 use crate::system_monitors;
 use crate::{config::MinimonConfig, fl};
+use crate::{export, history, history::Series, history_store, trace};
 
 use cosmic::widget::Id as WId;
 
@@ -62,6 +72,7 @@ const RAM_ICON: &str = "io.github.cosmic_utils.minimon-applet-ram";
 const GPU_ICON: &str = "io.github.cosmic_utils.minimon-applet-gpu";
 const NETWORK_ICON: &str = "io.github.cosmic_utils.minimon-applet-network";
 const DISK_ICON: &str = "io.github.cosmic_utils.minimon-applet-harddisk";
+const WARNING_ICON: &str = "dialog-warning-symbolic";
 
 const DEFAULT_MONITOR: &str = "GNOME System Monitor";
 
@@ -77,6 +88,14 @@ pub static SETTINGS_DISKS_CHOICE: LazyLock<&'static str> =
     LazyLock::new(|| fl!("settings-disks").leak());
 pub static SETTINGS_GPU_CHOICE: LazyLock<&'static str> =
     LazyLock::new(|| fl!("settings-gpu").leak());
+pub static SETTINGS_GPU_COMBINED_CHOICE: LazyLock<&'static str> =
+    LazyLock::new(|| fl!("settings-gpu-combined").leak());
+pub static SETTINGS_FILESYSTEMS_CHOICE: LazyLock<&'static str> =
+    LazyLock::new(|| fl!("settings-filesystems").leak());
+pub static SETTINGS_DISK_TEMP_CHOICE: LazyLock<&'static str> =
+    LazyLock::new(|| fl!("settings-disk-temperature").leak());
+pub static SETTINGS_STORAGE_HEALTH_CHOICE: LazyLock<&'static str> =
+    LazyLock::new(|| fl!("settings-storage-health").leak());
 
 pub static SETTINGS_GENERAL_HEADING: LazyLock<&'static str> =
     LazyLock::new(|| fl!("settings-subpage-general").leak());
@@ -92,6 +111,14 @@ pub static SETTINGS_NETWORK_HEADING: LazyLock<&'static str> =
 pub static SETTINGS_DISKS_HEADING: LazyLock<&'static str> =
     LazyLock::new(|| fl!("disks-title").leak());
 pub static SETTINGS_GPU_HEADING: LazyLock<&'static str> = LazyLock::new(|| fl!("gpu-title").leak());
+pub static SETTINGS_GPU_COMBINED_HEADING: LazyLock<&'static str> =
+    LazyLock::new(|| fl!("gpu-combined-heading").leak());
+pub static SETTINGS_FILESYSTEMS_HEADING: LazyLock<&'static str> =
+    LazyLock::new(|| fl!("filesystems-title").leak());
+pub static SETTINGS_DISK_TEMP_HEADING: LazyLock<&'static str> =
+    LazyLock::new(|| fl!("disk-temperature-title").leak());
+pub static SETTINGS_STORAGE_HEALTH_HEADING: LazyLock<&'static str> =
+    LazyLock::new(|| fl!("storage-health-title").leak());
 
 // The UI requires static lifetime of dropdown items
 pub static SYSMON_LIST: LazyLock<BTreeMap<String, system_monitors::DesktopApp>> =
@@ -100,6 +127,34 @@ pub static SYSMON_LIST: LazyLock<BTreeMap<String, system_monitors::DesktopApp>>
 pub static SYSMON_NAMES: LazyLock<Vec<&'static str>> =
     LazyLock::new(|| SYSMON_LIST.values().map(|app| app.name.as_str()).collect());
 
+// A field all item configs share, of the item shown by the device and GPU id
+macro_rules! item_field_mut {
+    ($config:expr, $device:expr, $id:expr, $field:ident) => {{
+        let config = &mut $config;
+        match ($device, $id) {
+            (DeviceKind::Cpu, _) => Some(&mut config.cpu.$field),
+            (DeviceKind::CpuTemp, _) => Some(&mut config.cputemp.$field),
+            (DeviceKind::Memory, _) => Some(&mut config.memory.$field),
+            (DeviceKind::Network(NetworkVariant::Upload), _) => Some(&mut config.network2.$field),
+            (DeviceKind::Network(_), _) => Some(&mut config.network1.$field),
+            (DeviceKind::Disks(DisksVariant::Read), _) => Some(&mut config.disks2.$field),
+            (DeviceKind::Disks(_), _) => Some(&mut config.disks1.$field),
+            (DeviceKind::DiskTemp, _) => Some(&mut config.disktemp.$field),
+            (DeviceKind::Filesystems, _) => Some(&mut config.filesystems.$field),
+            (DeviceKind::GpuCombined, _) => Some(&mut config.gpu_combined.$field),
+            (DeviceKind::Gpu, Some(id)) => config.gpus.get_mut(id).map(|g| &mut g.usage.$field),
+            (DeviceKind::Vram, Some(id)) => config.gpus.get_mut(id).map(|g| &mut g.vram.$field),
+            (DeviceKind::GpuTemp, Some(id)) => config.gpus.get_mut(id).map(|g| &mut g.temp.$field),
+            (DeviceKind::GpuMetric(metric), Some(id)) => config
+                .gpus
+                .get_mut(id)
+                .and_then(|g| g.metrics.get_mut(&metric))
+                .map(|m| &mut m.$field),
+            _ => None,
+        }
+    }};
+}
+
 macro_rules! network_select {
     ($self:ident, $variant:expr) => {
         match $variant {
@@ -136,7 +191,11 @@ pub enum SettingsVariant {
     Memory,
     Network,
     Disks,
+    DiskTemp,
+    Filesystems,
+    StorageHealth,
     Gpu(String),
+    GpuCombined,
 }
 
 pub struct Minimon {
@@ -157,9 +216,36 @@ pub struct Minimon {
     disks1: Disks,
     disks2: Disks,
 
+    /// NVMe and SATA drive temperatures
+    disktemp: DiskTemp,
+
+    /// Filesystem space usage per selected mount point
+    filesystems: Filesystems,
+
+    /// md RAID, btrfs and zfs health
+    storage_health: StorageHealth,
+
     //GPUs, in Btree so they're always ordered the same.
     gpus: BTreeMap<String, Gpu>,
 
+    /// All GPUs as one item, only offered with more than one GPU
+    gpu_combined: GpuCombined,
+
+    /// Threshold alert state of every item
+    alerts: Alerts,
+
+    /// History loaded at start, handed to the sensors once the config is applied
+    restored_history: BTreeMap<String, history_store::Saved>,
+    history_saved: time::Instant,
+    history_saver: history_store::BackgroundSaver,
+    /// Outcome of the last export from the popup, the file or the error
+    history_export: Option<Result<String, String>>,
+    /// The D-Bus interface, None if the session bus isn't reachable
+    metrics_service: Option<MetricsService>,
+    /// The Prometheus endpoint while enabled, or why it couldn't listen
+    prometheus: Option<Exporter>,
+    prometheus_error: Option<String>,
+
     /// The popup id.
     popup: Option<Id>,
 
@@ -182,6 +268,9 @@ pub struct Minimon {
     // Tracks whether any chart or label is showing on the panel
     data_is_visible: bool,
 
+    // GPU devices seen at the last scan, a change triggers re-detection
+    gpu_devices: Vec<String>,
+
     // Used to measure label width, have to be cached because slow to load
     font_system: FontSystem,
 
@@ -232,6 +321,24 @@ pub enum Message {
     ToggleDisksCombined(bool),
     ToggleDisksChart(DisksVariant, bool),
     ToggleDisksLabel(DisksVariant, bool),
+    DisksSelectMetric(DisksVariant, DisksMetric),
+
+    ToggleDiskTempChart(bool),
+    ToggleDiskTempLabel(bool),
+    SelectDiskTempUnit(TempUnit),
+    ToggleDiskTempDrive(String, bool),
+    ToggleGpuCombinedChart(bool),
+    ToggleGpuCombinedLabel(bool),
+    ToggleGpuCombinedVramLabel(bool),
+    ToggleGpuCombinedTempLabel(bool),
+    SelectGpuCombinedMode(GpuCombinedMode),
+    SelectGpuCombinedTempUnit(TempUnit),
+    ToggleStorageHealthWarning(bool),
+    ToggleStorageHealthLabel(bool),
+    ToggleFilesystemsChart(bool),
+    ToggleFilesystemsLabel(bool),
+    ToggleFilesystemsPredict(bool),
+    ToggleFilesystemMount(String, bool),
 
     SelectGraphType(DeviceKind, ChartKind),
     Tick,
@@ -257,19 +364,28 @@ pub enum Message {
     ToggleMonospaceLabels(bool),
     PanelSpacing(u16),
     SelectCpuTempUnit(TempUnit),
+    SelectCpuTempZone(usize),
 
     Settings(Option<SettingsVariant>),
 
     GpuToggleChart(String, DeviceKind, bool),
     GpuToggleLabel(String, DeviceKind, bool),
     GpuToggleStackLabels(String, bool),
+    GpuToggleThrottleWarning(String, bool),
+    GpuTogglePowerIndicator(String, bool),
     GpuSelectGraphType(String, DeviceKind, ChartKind),
     SelectGpuTempUnit(String, TempUnit),
     ToggleDisableOnBattery(String, bool),
     ToggleSymbols(bool),
     SysmonSelect(usize),
+    TogglePrometheus(bool),
+    PrometheusPortChanged(String),
 
     ChangeContentOrder(ContentOrderChange),
+    AlertChanged(DeviceKind, Option<String>, AlertConfig),
+    ColorStopsChanged(DeviceKind, Option<String>, ColorStops),
+    ChartWindowChanged(DeviceKind, Option<String>, ChartWindow),
+    ExportHistory(export::Format),
 
     Tip,
 }
@@ -278,6 +394,18 @@ const APP_ID_DOCK: &str = "io.github.cosmic_utils.minimon-applet-dock";
 const APP_ID_PANEL: &str = "io.github.cosmic_utils.minimon-applet-panel";
 const APP_ID_OTHER: &str = "io.github.cosmic_utils.minimon-applet-other";
 
+/// The app ids of all instances
+pub const APP_IDS: [&str; 3] = [APP_ID_PANEL, APP_ID_DOCK, APP_ID_OTHER];
+
+// Each panel type runs its own instance, with its own config and history
+fn app_id(panel_type: &PanelType) -> &'static str {
+    match panel_type {
+        PanelType::Panel => APP_ID_PANEL,
+        PanelType::Dock => APP_ID_DOCK,
+        PanelType::Other(_) => APP_ID_OTHER,
+    }
+}
+
 impl cosmic::Application for Minimon {
     type Executor = cosmic::executor::Default;
 
@@ -294,6 +422,7 @@ impl cosmic::Application for Minimon {
         }
 
         // Find GPUs
+        let gpu_devices = gpu_devices();
         let gpus: BTreeMap<String, Gpu> = list_gpus()
             .into_iter()
             .map(|mut gpu| {
@@ -306,6 +435,12 @@ impl cosmic::Application for Minimon {
             .collect();
 
         let is_horizontal = core.applet.is_horizontal();
+        let restored_history =
+            history_store::load_or_default(app_id(&core.applet.panel_type), history::now_ms());
+        let metrics_service =
+            MetricsService::start(export::instance(app_id(&core.applet.panel_type)))
+                .inspect_err(|e| warn!("Not serving metrics on D-Bus: {e}"))
+                .ok();
 
         let app = Minimon {
             core,
@@ -316,7 +451,19 @@ impl cosmic::Application for Minimon {
             network2: Network::default(),
             disks1: Disks::default(),
             disks2: Disks::default(),
+            disktemp: DiskTemp::default(),
+            filesystems: Filesystems::default(),
+            storage_health: StorageHealth::default(),
             gpus,
+            gpu_combined: GpuCombined::default(),
+            alerts: Alerts::default(),
+            restored_history,
+            history_saved: time::Instant::now(),
+            history_saver: history_store::BackgroundSaver::default(),
+            history_export: None,
+            metrics_service,
+            prometheus: None,
+            prometheus_error: None,
             popup: None,
             settings_page: None,
             colorpicker: ColorPicker::default(),
@@ -325,6 +472,7 @@ impl cosmic::Application for Minimon {
             is_laptop,
             on_ac: true,
             data_is_visible: false,
+            gpu_devices,
             font_system: FontSystem::new(),
             interface_font: None,
             label_cpu_width: None,
@@ -363,11 +511,7 @@ impl cosmic::Application for Minimon {
         let mut subscriptions: Vec<Subscription<Message>> = vec![
             time_subscription(&self.refresh_rate).map(|_| Message::Tick),
             self.core
-                .watch_config(match self.core.applet.panel_type {
-                    PanelType::Panel => APP_ID_PANEL,
-                    PanelType::Dock => APP_ID_DOCK,
-                    PanelType::Other(_) => APP_ID_OTHER,
-                })
+                .watch_config(app_id(&self.core.applet.panel_type))
                 .map(|u| Message::ConfigChanged(Box::new(u.config))),
         ];
 
@@ -404,8 +548,9 @@ impl cosmic::Application for Minimon {
             }
         }
 
-        // If the applet is not visible, return an icon button to toggle the popup
-        if !self.data_is_visible {
+        // If the applet is not visible, return an icon button to toggle the popup.
+        // Storage problems are shown regardless, they must not go unnoticed.
+        if !self.data_is_visible && !self.storage_health_warning() {
             return self
                 .core
                 .applet
@@ -439,6 +584,18 @@ impl cosmic::Application for Minimon {
                         elements.extend(self.gpu_panel_ui(gpu, horizontal));
                     }
                 }
+                ContentType::GpuCombined => {
+                    elements.extend(self.gpu_combined_panel_ui(horizontal));
+                }
+                ContentType::FilesystemUsage => {
+                    elements.extend(self.filesystems_panel_ui(horizontal));
+                }
+                ContentType::DiskTemp => {
+                    elements.extend(self.disk_temp_panel_ui(horizontal));
+                }
+                ContentType::StorageHealth => {
+                    elements.extend(self.storage_health_panel_ui(horizontal));
+                }
             }
         }
 
@@ -576,6 +733,21 @@ impl cosmic::Application for Minimon {
                             content = content.push(self.disks2.settings_ui());
                         }
                     }
+                    SettingsVariant::DiskTemp => {
+                        content =
+                            content.push(settings_sub_page_heading!(SETTINGS_DISK_TEMP_HEADING));
+                        content = content.push(self.disktemp.settings_ui());
+                    }
+                    SettingsVariant::StorageHealth => {
+                        content = content
+                            .push(settings_sub_page_heading!(SETTINGS_STORAGE_HEALTH_HEADING));
+                        content = content.push(self.storage_health.settings_ui());
+                    }
+                    SettingsVariant::Filesystems => {
+                        content =
+                            content.push(settings_sub_page_heading!(SETTINGS_FILESYSTEMS_HEADING));
+                        content = content.push(self.filesystems.settings_ui());
+                    }
                     SettingsVariant::Gpu(id) => {
                         content = content.push(settings_sub_page_heading!(SETTINGS_GPU_HEADING));
 
@@ -592,6 +764,11 @@ impl cosmic::Application for Minimon {
                             error!("SettingsVariant::Gpu: Not found {id}");
                         }
                     }
+                    SettingsVariant::GpuCombined => {
+                        content =
+                            content.push(settings_sub_page_heading!(SETTINGS_GPU_COMBINED_HEADING));
+                        content = content.push(self.gpu_combined.settings_ui());
+                    }
                     SettingsVariant::General => {
                         content =
                             content.push(settings_sub_page_heading!(SETTINGS_GENERAL_HEADING));
@@ -641,6 +818,29 @@ impl cosmic::Application for Minimon {
                         .read_label(sample_rate_ms, disks::UnitVariant::Long)
                 ));
 
+                let filesystems = widget::text::body(
+                    self.filesystems
+                        .mounts()
+                        .iter()
+                        .map(|m| format!("{} {}", m.mount_point, m.label()))
+                        .collect::<Vec<String>>()
+                        .join(" / "),
+                );
+
+                let disktemp = widget::text::body(
+                    self.disktemp
+                        .visible_drives()
+                        .map(|idx| {
+                            format!(
+                                "{} {}",
+                                self.disktemp.drives()[idx].device,
+                                self.disktemp.drive_label(idx)
+                            )
+                        })
+                        .collect::<Vec<String>>()
+                        .join(" / "),
+                );
+
                 let mut sensor_settings = list::ListColumn::new()
                     .add(Minimon::go_next_with_item(
                         &SETTINGS_GENERAL_HEADING,
@@ -677,8 +877,29 @@ impl cosmic::Application for Minimon {
                         disks,
                         Message::Settings(Some(SettingsVariant::Disks)),
                     ))
+                    .add(Minimon::go_next_with_item(
+                        &SETTINGS_FILESYSTEMS_CHOICE,
+                        filesystems,
+                        Message::Settings(Some(SettingsVariant::Filesystems)),
+                    ))
                     .padding(0);
 
+                if self.disktemp.is_found() {
+                    sensor_settings = sensor_settings.add(Minimon::go_next_with_item(
+                        &SETTINGS_DISK_TEMP_CHOICE,
+                        disktemp,
+                        Message::Settings(Some(SettingsVariant::DiskTemp)),
+                    ));
+                }
+
+                if self.storage_health.is_found() {
+                    sensor_settings = sensor_settings.add(Minimon::go_next_with_item(
+                        &SETTINGS_STORAGE_HEALTH_CHOICE,
+                        widget::text::body(self.storage_health.summary()),
+                        Message::Settings(Some(SettingsVariant::StorageHealth)),
+                    ));
+                }
+
                 if self.has_gpus() {
                     for (key, gpu) in &self.gpus {
                         let temp = gpu.temp.to_string();
@@ -697,7 +918,17 @@ impl cosmic::Application for Minimon {
                     }
                 }
 
+                if self.has_gpu_combined() {
+                    sensor_settings = sensor_settings.add(Minimon::go_next_with_item(
+                        &SETTINGS_GPU_COMBINED_CHOICE,
+                        widget::text::body(self.gpu_combined.summary()),
+                        Message::Settings(Some(SettingsVariant::GpuCombined)),
+                    ));
+                }
+
                 content = content.push(sensor_settings);
+                content = content.push_maybe(self.alerts.command_log_ui());
+                content = content.push(self.export_ui());
             }
 
             content = content.padding(padding).spacing(padding);
@@ -782,7 +1013,22 @@ impl cosmic::Application for Minimon {
                         let (disks, _) = disks_select!(self, variant);
                         self.colorpicker.activate(device, disks.demo_graph());
                     }
-                    DeviceKind::Gpu | DeviceKind::Vram | DeviceKind::GpuTemp => {
+                    DeviceKind::DiskTemp => {
+                        self.colorpicker
+                            .activate(device, self.disktemp.demo_graph());
+                    }
+                    DeviceKind::Filesystems => {
+                        self.colorpicker
+                            .activate(device, self.filesystems.demo_graph());
+                    }
+                    DeviceKind::GpuCombined => {
+                        self.colorpicker
+                            .activate(device, self.gpu_combined.demo_graph());
+                    }
+                    DeviceKind::Gpu
+                    | DeviceKind::Vram
+                    | DeviceKind::GpuTemp
+                    | DeviceKind::GpuMetric(_) => {
                         if let Some(id) = id {
                             if let Some(gpu) = self.gpus.get(&id) {
                                 self.colorpicker.activate(device, gpu.demo_graph(device));
@@ -893,6 +1139,121 @@ impl cosmic::Application for Minimon {
                 self.save_config();
             }
 
+            Message::DisksSelectMetric(variant, metric) => {
+                info!("Message::DisksSelectMetric({variant:?}, {metric:?})");
+                let (_, config) = disks_select!(self, variant);
+                config.metric = metric;
+                self.save_config();
+            }
+
+            Message::ToggleDiskTempChart(toggled) => {
+                info!("Message::ToggleDiskTempChart({toggled:?})");
+                self.config.disktemp.show_chart(toggled);
+                self.save_config();
+            }
+
+            Message::ToggleDiskTempLabel(toggled) => {
+                info!("Message::ToggleDiskTempLabel({toggled:?})");
+                self.config.disktemp.show_label(toggled);
+                self.save_config();
+            }
+
+            Message::SelectDiskTempUnit(unit) => {
+                info!("Message::SelectDiskTempUnit({unit:?})");
+                self.config.disktemp.unit = unit;
+                self.save_config();
+            }
+
+            Message::ToggleGpuCombinedChart(toggled) => {
+                info!("Message::ToggleGpuCombinedChart({toggled:?})");
+                self.config.gpu_combined.show_chart(toggled);
+                self.save_config();
+            }
+
+            Message::ToggleGpuCombinedLabel(toggled) => {
+                info!("Message::ToggleGpuCombinedLabel({toggled:?})");
+                self.config.gpu_combined.show_label(toggled);
+                self.save_config();
+            }
+
+            Message::ToggleGpuCombinedVramLabel(toggled) => {
+                info!("Message::ToggleGpuCombinedVramLabel({toggled:?})");
+                self.config.gpu_combined.vram_label = toggled;
+                self.save_config();
+            }
+
+            Message::ToggleGpuCombinedTempLabel(toggled) => {
+                info!("Message::ToggleGpuCombinedTempLabel({toggled:?})");
+                self.config.gpu_combined.temp_label = toggled;
+                self.save_config();
+            }
+
+            Message::SelectGpuCombinedMode(mode) => {
+                info!("Message::SelectGpuCombinedMode({mode:?})");
+                self.config.gpu_combined.mode = mode;
+                self.save_config();
+            }
+
+            Message::SelectGpuCombinedTempUnit(unit) => {
+                info!("Message::SelectGpuCombinedTempUnit({unit:?})");
+                self.config.gpu_combined.unit = unit;
+                self.save_config();
+            }
+
+            Message::ToggleDiskTempDrive(device, toggled) => {
+                info!("Message::ToggleDiskTempDrive({device}, {toggled:?})");
+                let hidden = &mut self.config.disktemp.hidden;
+                if toggled {
+                    hidden.retain(|d| d != &device);
+                } else if !hidden.contains(&device) {
+                    hidden.push(device);
+                }
+                self.save_config();
+            }
+
+            Message::ToggleStorageHealthWarning(toggled) => {
+                info!("Message::ToggleStorageHealthWarning({toggled:?})");
+                self.config.storage_health.show_warning = toggled;
+                self.save_config();
+            }
+
+            Message::ToggleStorageHealthLabel(toggled) => {
+                info!("Message::ToggleStorageHealthLabel({toggled:?})");
+                self.config.storage_health.label_visible = toggled;
+                self.save_config();
+            }
+
+            Message::ToggleFilesystemsChart(toggled) => {
+                info!("Message::ToggleFilesystemsChart({toggled:?})");
+                self.config.filesystems.show_chart(toggled);
+                self.save_config();
+            }
+
+            Message::ToggleFilesystemsLabel(toggled) => {
+                info!("Message::ToggleFilesystemsLabel({toggled:?})");
+                self.config.filesystems.show_label(toggled);
+                self.save_config();
+            }
+
+            Message::ToggleFilesystemsPredict(toggled) => {
+                info!("Message::ToggleFilesystemsPredict({toggled:?})");
+                self.config.filesystems.predict_full = toggled;
+                self.save_config();
+            }
+
+            Message::ToggleFilesystemMount(mount, toggled) => {
+                info!("Message::ToggleFilesystemMount({mount}, {toggled:?})");
+                let mounts = &mut self.config.filesystems.mounts;
+                if toggled {
+                    if !mounts.contains(&mount) {
+                        mounts.push(mount);
+                    }
+                } else {
+                    mounts.retain(|m| m != &mount);
+                }
+                self.save_config();
+            }
+
             Message::ToggleAdaptiveNet(variant, toggle) => {
                 info!("Message::ToggleAdaptiveNet({variant:?}, {toggle:?})");
                 let (_network, config) = network_select!(self, variant);
@@ -921,6 +1282,14 @@ impl cosmic::Application for Minimon {
                         self.memory.set_graph_kind(kind);
                         self.config.memory.chart = kind;
                     }
+                    DeviceKind::DiskTemp => {
+                        self.disktemp.set_graph_kind(kind);
+                        self.config.disktemp.chart = kind;
+                    }
+                    DeviceKind::GpuCombined => {
+                        self.gpu_combined.set_graph_kind(kind);
+                        self.config.gpu_combined.chart = kind;
+                    }
                     _ => error!("Message::SelectGraphType unsupported kind/device combination."), // Disks and Network don't have graph selection
                 }
                 self.save_config();
@@ -942,6 +1311,44 @@ impl cosmic::Application for Minimon {
 
             Message::Tick => {
                 self.refresh_stats();
+                self.check_alerts();
+                self.serve_metrics();
+                self.update_prometheus();
+            }
+
+            Message::AlertChanged(device, id, alerts) => {
+                info!("Message::AlertChanged({device:?}, {id:?}, {alerts:?})");
+                if let Some(config) = self.alert_config_mut(device, id.as_deref()) {
+                    *config = alerts;
+                    self.save_config();
+                } else {
+                    error!("Message::AlertChanged unsupported kind/device combination.");
+                }
+            }
+
+            Message::ColorStopsChanged(device, id, stops) => {
+                info!("Message::ColorStopsChanged({device:?}, {id:?}, {stops:?})");
+                if let Some(config) = self.color_stops_mut(device, id.as_deref()) {
+                    *config = stops;
+                    self.save_config();
+                } else {
+                    error!("Message::ColorStopsChanged unsupported kind/device combination.");
+                }
+            }
+
+            Message::ChartWindowChanged(device, id, window) => {
+                info!("Message::ChartWindowChanged({device:?}, {id:?}, {window:?})");
+                if let Some(config) = self.chart_window_mut(device, id.as_deref()) {
+                    *config = window;
+                    self.save_config();
+                } else {
+                    error!("Message::ChartWindowChanged unsupported kind/device combination.");
+                }
+            }
+
+            Message::ExportHistory(format) => {
+                info!("Message::ExportHistory({format:?})");
+                self.history_export = Some(self.export_history(format));
             }
 
             Message::SlowTimer => {
@@ -965,6 +1372,21 @@ impl cosmic::Application for Minimon {
                         }
                     }
                 }
+
+                if self.config.storage_health.show_warning || self.popup.is_some() {
+                    self.storage_health.update();
+                }
+
+                let devices = gpu_devices();
+                if devices != self.gpu_devices {
+                    info!("GPU devices changed, re-detecting GPUs");
+                    self.gpu_devices = devices;
+                    self.redetect_gpus();
+                }
+
+                if self.history_saved.elapsed() >= history_store::SAVE_INTERVAL {
+                    self.save_history();
+                }
             }
 
             Message::ToggleCpuChart(toggled) => {
@@ -991,6 +1413,12 @@ impl cosmic::Application for Minimon {
                 self.save_config();
             }
 
+            Message::SelectCpuTempZone(index) => {
+                info!("Message::SelectCpuTempZone({index})");
+                self.config.cputemp.thermal_zone = self.cputemp.zone_choice(index);
+                self.save_config();
+            }
+
             Message::CpuBarSizeChanged(width) => {
                 info!("Message::CpuBarSizeChanged({width})");
                 self.config.cpu.bar_width = width;
@@ -1120,6 +1548,21 @@ impl cosmic::Application for Minimon {
                 self.save_config();
             }
 
+            Message::TogglePrometheus(toggle) => {
+                info!("Message::TogglePrometheus({toggle:?})");
+                self.config.prometheus = toggle;
+                self.save_config();
+            }
+
+            Message::PrometheusPortChanged(string) => {
+                if let Ok(port) = string.trim().parse::<u16>()
+                    && port != 0
+                {
+                    self.config.prometheus_port = port;
+                    self.save_config();
+                }
+            }
+
             Message::Settings(setting) => {
                 info!("Message::Settings({setting:?})");
                 self.settings_page = setting;
@@ -1139,6 +1582,7 @@ impl cosmic::Application for Minimon {
                         DeviceKind::Gpu => config.usage.show_chart(toggled),
                         DeviceKind::Vram => config.vram.show_chart(toggled),
                         DeviceKind::GpuTemp => config.temp.show_chart(toggled),
+                        DeviceKind::GpuMetric(m) => config.metric_mut(m).show_chart(toggled),
                         _ => error!("GpuToggleChart: wrong kind {device:?}"),
                     },
                 );
@@ -1153,6 +1597,7 @@ impl cosmic::Application for Minimon {
                         DeviceKind::Gpu => config.usage.show_label(toggled),
                         DeviceKind::Vram => config.vram.show_label(toggled),
                         DeviceKind::GpuTemp => config.temp.show_label(toggled),
+                        DeviceKind::GpuMetric(m) => config.metric_mut(m).show_label(toggled),
                         _ => error!("GpuToggleLabel: wrong kind {device:?}"),
                     },
                 );
@@ -1179,6 +1624,26 @@ impl cosmic::Application for Minimon {
                 }
             }
 
+            Message::GpuToggleThrottleWarning(id, toggled) => {
+                info!("Message::GpuToggleThrottleWarning({id:?}, {toggled:?})");
+                if let Some(c) = self.config.gpus.get_mut(&id) {
+                    c.throttle_warning = toggled;
+                    self.save_config();
+                } else {
+                    error!("GpuToggleThrottleWarning: wrong id {id:?}");
+                }
+            }
+
+            Message::GpuTogglePowerIndicator(id, toggled) => {
+                info!("Message::GpuTogglePowerIndicator({id:?}, {toggled:?})");
+                if let Some(c) = self.config.gpus.get_mut(&id) {
+                    c.power_indicator = toggled;
+                    self.save_config();
+                } else {
+                    error!("GpuTogglePowerIndicator: wrong id {id:?}");
+                }
+            }
+
             Message::GpuSelectGraphType(id, device, kind) => {
                 info!("Message::GpuSelectGraphType({id:?}, {device:?}, {kind:?})");
                 self.update_gpu_config(&id, "GpuSelectGraphType", device, |config, device| {
@@ -1186,6 +1651,7 @@ impl cosmic::Application for Minimon {
                         DeviceKind::Gpu => config.usage.chart = kind,
                         DeviceKind::Vram => config.vram.chart = kind,
                         DeviceKind::GpuTemp => config.temp.chart = kind,
+                        DeviceKind::GpuMetric(m) => config.metric_mut(m).chart = kind,
                         _ => error!("GpuSelectGraphType: wrong kind {device:?}"),
                     }
                 });
@@ -1194,6 +1660,11 @@ impl cosmic::Application for Minimon {
                         DeviceKind::Gpu => gpu.gpu.set_graph_kind(kind),
                         DeviceKind::Vram => gpu.vram.set_graph_kind(kind),
                         DeviceKind::GpuTemp => gpu.temp.set_graph_kind(kind),
+                        DeviceKind::GpuMetric(m) => {
+                            if let Some(graph) = gpu.metrics.get_mut(&m) {
+                                graph.set_graph_kind(kind);
+                            }
+                        }
                         _ => error!("GpuSelectGraphType: wrong kind {device:?}"),
                     }
                 }
@@ -1239,34 +1710,269 @@ impl Minimon {
         self.network2.update_config(&config.network2, rr);
         self.disks1.update_config(&config.disks1, rr);
         self.disks2.update_config(&config.disks2, rr);
+        self.disktemp.update_config(&config.disktemp, rr);
+        self.filesystems.update_config(&config.filesystems, rr);
+        self.storage_health.update_config(&config.storage_health);
+        self.gpu_combined.update_config(&config.gpu_combined, rr);
+        self.config.content_order.complete();
         self.sync_gpu_configs();
+        self.update_data_is_visible();
+        self.calculate_max_label_widths();
+        self.restore_history();
+        self.sync_prometheus();
+    }
 
-        // Track whether anything is visible on the panel, or just the app-icon
-        {
-            self.data_is_visible = false;
-            for gpu in self.gpus.values() {
-                if let Some(g) = self.config.gpus.get(&gpu.id())
-                    && g.is_visible()
-                {
-                    self.data_is_visible = true;
-                    break;
+    // Every history kept across restarts, by a key like the alert keys
+    fn history_series(&mut self) -> Vec<Series<'_>> {
+        fn prefixed<'a>(
+            prefix: &str,
+            histories: Vec<Series<'a>>,
+        ) -> impl Iterator<Item = Series<'a>> {
+            histories.into_iter().map(move |s| Series {
+                name: format!("{prefix}:{}", s.name),
+                ..s
+            })
+        }
+
+        let mut series = Vec::new();
+        series.extend(prefixed("cpu", self.cpu.histories_mut()));
+        series.extend(prefixed("cputemp", self.cputemp.histories_mut()));
+        series.extend(prefixed("memory", self.memory.histories_mut()));
+        series.extend(prefixed("network1", self.network1.histories_mut()));
+        series.extend(prefixed("network2", self.network2.histories_mut()));
+        series.extend(prefixed("disks1", self.disks1.histories_mut()));
+        series.extend(prefixed("disks2", self.disks2.histories_mut()));
+        series.extend(prefixed("disktemp", self.disktemp.histories_mut()));
+        let ids: Vec<String> = self
+            .gpus
+            .iter()
+            .filter(|(_, g)| !g.is_accelerator())
+            .map(|(id, _)| id.clone())
+            .collect();
+        series.extend(prefixed(
+            "gpu_combined",
+            self.gpu_combined.histories_mut(&ids),
+        ));
+        for (id, gpu) in &mut self.gpus {
+            series.extend(prefixed(&format!("gpu:{id}"), gpu.histories_mut()));
+        }
+        series
+    }
+
+    // Hand the loaded history to the sensors, after the first config so the
+    // sensors already chart what the snapshot was taken of
+    fn restore_history(&mut self) {
+        if self.restored_history.is_empty() {
+            return;
+        }
+        let mut restored = std::mem::take(&mut self.restored_history);
+        for series in self.history_series() {
+            if let Some(saved) = restored.remove(&series.name)
+                && saved.unit == series.unit
+            {
+                *series.history = saved.history;
+            }
+        }
+    }
+
+    fn save_history(&mut self) {
+        self.history_saved = time::Instant::now();
+        let Some(path) = history_store::path(app_id(&self.core.applet.panel_type)) else {
+            return;
+        };
+        let snapshot = history_store::Snapshot::new(&self.history_series());
+        self.history_saver.save(path, snapshot);
+    }
+
+    // Answer the GetHistory calls and publish the latest values on D-Bus. Items that
+    // weren't sampled in the last two updates are left out, they are hidden.
+    fn serve_metrics(&mut self) {
+        let Some(requests) = self.metrics_service.as_ref().map(MetricsService::requests) else {
+            return;
+        };
+        let now = history::now_ms();
+        let max_age = time::Duration::from_millis(u64::from(self.config.refresh_rate) * 2);
+
+        let series = self.history_series();
+        for request in requests {
+            let samples = series
+                .iter()
+                .find(|s| s.name == request.sensor)
+                .map(|s| s.history.samples(now, request.window));
+            request.reply(samples);
+        }
+        let metrics: Vec<Metric> = series
+            .iter()
+            .filter_map(|s| {
+                Some(Metric {
+                    key: s.name.clone(),
+                    unit: s.unit,
+                    value: s.history.latest(now, max_age)?,
+                })
+            })
+            .collect();
+
+        if let Some(service) = &self.metrics_service {
+            service.publish(&metrics);
+        }
+    }
+
+    // Start, move or stop the Prometheus endpoint as configured
+    fn sync_prometheus(&mut self) {
+        let port = self
+            .config
+            .prometheus
+            .then_some(self.config.prometheus_port);
+        if self.prometheus.as_ref().map(Exporter::port) == port {
+            return;
+        }
+
+        // Stopped first, a new one may take the same port
+        self.prometheus = None;
+        self.prometheus_error = None;
+        if let Some(port) = port {
+            match Exporter::start(port) {
+                Ok(exporter) => self.prometheus = Some(exporter),
+                Err(e) => {
+                    error!("Failed to serve Prometheus metrics on port {port}: {e}");
+                    self.prometheus_error = Some(e.to_string());
                 }
             }
+        }
+    }
+
+    fn update_prometheus(&self) {
+        let Some(exporter) = &self.prometheus else {
+            return;
+        };
+        let mut metrics = prometheus::Metrics::default();
+        self.cpu.prometheus(&mut metrics);
+        self.cputemp.prometheus(&mut metrics);
+        self.memory.prometheus(&mut metrics);
+        // Both network and disks items sample all devices, the first has every reading
+        self.network1.prometheus(&mut metrics);
+        self.disks1.prometheus(&mut metrics);
+        self.disktemp.prometheus(&mut metrics);
+        for gpu in self.gpus.values() {
+            gpu.prometheus(&mut metrics);
+        }
+        exporter.update(&metrics);
+    }
 
-            if self.config.cpu.visible()
-                || self.config.cputemp.visible()
-                || self.config.memory.visible()
-                || self.config.network1.visible()
-                || (self.config.network1.variant != NetworkVariant::Combined
-                    && self.config.network2.visible())
-                || self.config.disks1.visible()
-                || (self.config.disks1.variant != DisksVariant::Combined
-                    && self.config.disks2.visible())
+    // Write the live history of this instance to the download directory
+    fn export_history(&mut self, format: export::Format) -> Result<String, String> {
+        let path = export::default_path(format).ok_or_else(|| fl!("export-no-directory"))?;
+        let instance = export::instance(app_id(&self.core.applet.panel_type));
+        let series = self.history_series();
+        let sources: Vec<export::Source> = series
+            .iter()
+            .map(|s| export::Source {
+                instance,
+                key: &s.name,
+                unit: s.unit,
+                history: &*s.history,
+            })
+            .collect();
+        match export::write_file(&path, &sources) {
+            Ok(rows) => {
+                info!("Exported {rows} history rows to {path:?}");
+                Ok(path.display().to_string())
+            }
+            Err(e) => {
+                error!("Failed to export history to {path:?}: {e}");
+                Err(e.to_string())
+            }
+        }
+    }
+
+    fn export_ui(&self) -> Element<'_, Message> {
+        let spacing = cosmic::theme::spacing();
+        let status = self.history_export.as_ref().map(|result| {
+            widget::text::caption(match result {
+                Ok(path) => fl!("export-saved", path = path.as_str()),
+                Err(e) => fl!("export-failed", error = e.as_str()),
+            })
+        });
+
+        Column::new()
+            .push(settings::item(
+                fl!("export-history"),
+                row!(
+                    widget::button::standard(fl!("export-csv"))
+                        .on_press(Message::ExportHistory(export::Format::Csv)),
+                    widget::button::standard(fl!("export-json"))
+                        .on_press(Message::ExportHistory(export::Format::JsonLines)),
+                )
+                .spacing(spacing.space_xxs),
+            ))
+            .push_maybe(status)
+            .spacing(spacing.space_xxs)
+            .into()
+    }
+
+    // Track whether anything is visible on the panel, or just the app-icon
+    fn update_data_is_visible(&mut self) {
+        self.data_is_visible = false;
+        for gpu in self.gpus.values() {
+            if let Some(g) = self.config.gpus.get(&gpu.id())
+                && (g.is_visible() || g.power_indicator)
             {
                 self.data_is_visible = true;
+                break;
             }
         }
-        self.calculate_max_label_widths();
+
+        if self.config.cpu.visible()
+            || self.config.cputemp.visible()
+            || self.config.memory.visible()
+            || self.config.network1.visible()
+            || (self.config.network1.variant != NetworkVariant::Combined
+                && self.config.network2.visible())
+            || self.config.disks1.visible()
+            || (self.config.disks1.variant != DisksVariant::Combined
+                && self.config.disks2.visible())
+            || (self.disktemp.is_found() && self.config.disktemp.visible())
+            || self.config.filesystems.visible()
+            || self.show_gpu_combined()
+        {
+            self.data_is_visible = true;
+        }
+    }
+
+    // Add GPUs that appeared and drop those that are gone, keeping the state of the others
+    fn redetect_gpus(&mut self) {
+        // GPUs without a slot are replayed or unknown to sysfs, they stay
+        let slots = gpu_slots();
+        self.gpus.retain(|id, gpu| {
+            let present = gpu.pci_slot().is_none_or(|slot| slots.contains(slot));
+            if !present {
+                info!("GPU removed. Name: {}. UUID: {id}", gpu.name());
+            }
+            present
+        });
+
+        // Only build backends for new slots, probing wakes suspended GPUs
+        let known: HashSet<String> = self
+            .gpus
+            .values()
+            .filter_map(|gpu| gpu.pci_slot().map(str::to_string))
+            .collect();
+
+        for mut gpu in list_new_gpus(&known) {
+            let id = gpu.id();
+            if self.gpus.contains_key(&id) {
+                continue;
+            }
+            info!("Found GPU. Name: {}. UUID: {id}", gpu.name());
+            if self.is_laptop {
+                gpu.set_laptop();
+            }
+            self.gpus.insert(id, gpu);
+        }
+
+        self.sync_gpu_configs();
+        self.maybe_stop_gpus();
+        self.update_data_is_visible();
     }
 
     pub fn sub_page_header<'a, Message: 'static + Clone>(
@@ -1405,6 +2111,28 @@ impl Minimon {
             row!(widget::dropdown(&*SYSMON_NAMES, idx, Message::SysmonSelect).width(220)),
         );
 
+        let prometheus_status = if let Some(e) = &self.prometheus_error {
+            Some(fl!("prometheus-failed", error = e.as_str()))
+        } else {
+            self.prometheus
+                .as_ref()
+                .map(|p| fl!("prometheus-serving", port = p.port().to_string()))
+        };
+        let prometheus_row = Column::new()
+            .push(settings::item(
+                fl!("prometheus-enable"),
+                row!(
+                    widget::text_input("", self.config.prometheus_port.to_string())
+                        .width(100)
+                        .on_input(Message::PrometheusPortChanged),
+                    widget::toggler(self.config.prometheus).on_toggle(Message::TogglePrometheus),
+                )
+                .align_y(Alignment::Center)
+                .spacing(8),
+            ))
+            .push_maybe(prometheus_status.map(widget::text::caption))
+            .spacing(cosmic::theme::spacing().space_xxs);
+
         let content_items = Column::from_vec({
             let mut children = Vec::new();
 
@@ -1420,12 +2148,31 @@ impl Minimon {
                     ContentType::MemoryUsage => text(fl!("settings-memory")),
                     ContentType::NetworkUsage => text(fl!("settings-network")),
                     ContentType::DiskUsage => text(fl!("settings-disks")),
+                    ContentType::FilesystemUsage => text(fl!("settings-filesystems")),
+                    ContentType::DiskTemp => {
+                        if !self.disktemp.is_found() {
+                            continue;
+                        }
+                        text(fl!("settings-disk-temperature"))
+                    }
+                    ContentType::StorageHealth => {
+                        if !self.storage_health.is_found() {
+                            continue;
+                        }
+                        text(fl!("settings-storage-health"))
+                    }
                     ContentType::GpuInfo => {
                         if self.gpus.is_empty() {
                             continue;
                         }
                         text(fl!("settings-gpu"))
                     }
+                    ContentType::GpuCombined => {
+                        if !self.has_gpu_combined() {
+                            continue;
+                        }
+                        text(fl!("settings-gpu-combined"))
+                    }
                 };
 
                 let item_row = row!(
@@ -1469,6 +2216,7 @@ impl Minimon {
             symbol_row,
             spacing_row,
             sysmon_row,
+            prometheus_row,
             content_order
         )
         .spacing(10)
@@ -1519,8 +2267,13 @@ impl Minimon {
         // Add the CPU label if needed
         if self.config.cpu.label_visible() {
             elements.push_back(
-                self.figure_label(formatted_cpu, self.label_cpu_width)
-                    .into(),
+                self.value_label(
+                    formatted_cpu,
+                    self.label_cpu_width,
+                    &self.config.cpu.color_stops,
+                    self.cpu.alert_value(),
+                )
+                .into(),
             );
         }
 
@@ -1566,7 +2319,15 @@ impl Minimon {
 
             // Add the CPU label if needed
             if self.config.cputemp.label_visible() {
-                elements.push_back(self.figure_label(self.cputemp.to_string(), None).into());
+                elements.push_back(
+                    self.value_label(
+                        self.cputemp.to_string(),
+                        None,
+                        &self.config.cputemp.color_stops,
+                        self.cputemp.alert_value(),
+                    )
+                    .into(),
+                );
             }
 
             // Add the CPU chart if needed
@@ -1599,7 +2360,15 @@ impl Minimon {
         // Label section
         if self.config.memory.label_visible() {
             let formatted_mem = self.memory.to_string(!horizontal);
-            elements.push_back(self.figure_label(formatted_mem, None).into());
+            elements.push_back(
+                self.value_label(
+                    formatted_mem,
+                    None,
+                    &self.config.memory.color_stops,
+                    self.memory.alert_value(),
+                )
+                .into(),
+            );
         }
 
         // Chart section
@@ -1623,7 +2392,9 @@ impl Minimon {
         let sample_rate_ms = self.config.refresh_rate;
         let mut elements: VecDeque<Element<Message>> = VecDeque::new();
 
-        let format_label = |text: String| self.figure_label(text, self.label_network_width);
+        let format_label = |text: String, network: &Network, stops: &ColorStops| {
+            self.value_label(text, self.label_network_width, stops, network.alert_value())
+        };
 
         let unit_len = if horizontal {
             network::UnitVariant::Long
@@ -1638,8 +2409,14 @@ impl Minimon {
             if horizontal {
                 dl_row.push(self.figure_label("↓".to_owned(), None).into());
             }
-            dl_row
-                .push(format_label(self.network1.download_label(sample_rate_ms, unit_len)).into());
+            dl_row.push(
+                format_label(
+                    self.network1.download_label(sample_rate_ms, unit_len),
+                    &self.network1,
+                    &self.config.network1.color_stops,
+                )
+                .into(),
+            );
 
             if nw_combined {
                 network_labels.push(widget::vertical_space().into());
@@ -1654,7 +2431,12 @@ impl Minimon {
                     ul_row.push(self.figure_label("↑".to_owned(), None).into());
                 }
                 ul_row.push(
-                    format_label(self.network1.upload_label(sample_rate_ms, unit_len)).into(),
+                    format_label(
+                        self.network1.upload_label(sample_rate_ms, unit_len),
+                        &self.network1,
+                        &self.config.network1.color_stops,
+                    )
+                    .into(),
                 );
 
                 network_labels.push(Row::from_vec(ul_row).into());
@@ -1682,7 +2464,14 @@ impl Minimon {
             if horizontal {
                 ul_row.push(self.figure_label("↑".to_owned(), None).into());
             }
-            ul_row.push(format_label(self.network2.upload_label(sample_rate_ms, unit_len)).into());
+            ul_row.push(
+                format_label(
+                    self.network2.upload_label(sample_rate_ms, unit_len),
+                    &self.network2,
+                    &self.config.network2.color_stops,
+                )
+                .into(),
+            );
 
             network_labels.push(Row::from_vec(ul_row).into());
 
@@ -1710,10 +2499,14 @@ impl Minimon {
         let size = self.core.applet.suggested_size(false);
 
         let disks_combined = self.config.disks1.variant == DisksVariant::Combined;
+        // Utilization is a single figure, even for the combined item
+        let disks_rows = disks_combined && self.disks1.metric() != DisksMetric::Utilization;
         let sample_rate_ms = self.config.refresh_rate;
         let mut elements: VecDeque<Element<Message>> = VecDeque::new();
 
-        let format_label = |text: String| self.figure_label(text, self.label_disks_width);
+        let format_label = |text: String, disks: &Disks, stops: &ColorStops| {
+            self.value_label(text, self.label_disks_width, stops, disks.alert_value())
+        };
 
         let unit_len = if horizontal {
             disks::UnitVariant::Long
@@ -1725,23 +2518,37 @@ impl Minimon {
             let mut disks_labels = Vec::new();
 
             let mut wr_row = Vec::new();
-            if horizontal {
+            if horizontal && self.disks1.metric() != DisksMetric::Utilization {
                 wr_row.push(self.figure_label("w".to_owned(), self.label_w_width).into());
             }
-            wr_row.push(format_label(self.disks1.write_label(sample_rate_ms, unit_len)).into());
+            wr_row.push(
+                format_label(
+                    self.disks1.write_label(sample_rate_ms, unit_len),
+                    &self.disks1,
+                    &self.config.disks1.color_stops,
+                )
+                .into(),
+            );
 
-            if disks_combined {
+            if disks_rows {
                 disks_labels.push(widget::vertical_space().into());
             }
 
             disks_labels.push(Row::from_vec(wr_row).spacing(0).padding(0).into());
 
-            if disks_combined {
+            if disks_rows {
                 let mut rd_row = Vec::new();
                 if horizontal {
                     rd_row.push(self.figure_label("r".to_owned(), self.label_w_width).into());
                 }
-                rd_row.push(format_label(self.disks1.read_label(sample_rate_ms, unit_len)).into());
+                rd_row.push(
+                    format_label(
+                        self.disks1.read_label(sample_rate_ms, unit_len),
+                        &self.disks1,
+                        &self.config.disks1.color_stops,
+                    )
+                    .into(),
+                );
 
                 disks_labels.push(Row::from_vec(rd_row).spacing(0).padding(0).into());
                 disks_labels.push(widget::vertical_space().into());
@@ -1764,10 +2571,17 @@ impl Minimon {
             let mut disks_labels = Vec::new();
 
             let mut rd_row = Vec::new();
-            if horizontal {
+            if horizontal && self.disks2.metric() != DisksMetric::Utilization {
                 rd_row.push(self.figure_label("r".to_owned(), self.label_w_width).into());
             }
-            rd_row.push(format_label(self.disks2.read_label(sample_rate_ms, unit_len)).into());
+            rd_row.push(
+                format_label(
+                    self.disks2.read_label(sample_rate_ms, unit_len),
+                    &self.disks2,
+                    &self.config.disks2.color_stops,
+                )
+                .into(),
+            );
             disks_labels.push(Row::from_vec(rd_row).spacing(0).padding(0).into());
 
             elements.push_back(Column::from_vec(disks_labels).into());
@@ -1790,6 +2604,111 @@ impl Minimon {
         elements
     }
 
+    fn disk_temp_panel_ui(
+        &'_ self,
+        _horizontal: bool,
+    ) -> VecDeque<Element<'_, crate::app::Message>> {
+        let size = self.core.applet.suggested_size(false);
+
+        let mut elements: VecDeque<Element<Message>> = VecDeque::new();
+
+        for idx in self.disktemp.visible_drives() {
+            if self.config.disktemp.label_visible() {
+                elements.push_back(
+                    self.value_label(
+                        self.disktemp.drive_label(idx),
+                        None,
+                        &self.config.disktemp.color_stops,
+                        self.disktemp.drives().get(idx).map(|d| d.latest_sample()),
+                    )
+                    .into(),
+                );
+            }
+
+            if self.config.disktemp.chart_visible() {
+                elements.push_back(
+                    self.disktemp
+                        .drive_chart(idx)
+                        .height(size.0)
+                        .width(size.1)
+                        .into(),
+                );
+            }
+        }
+
+        if self.config.symbols && !elements.is_empty() {
+            self.push_symbolic_icon(&mut elements, TEMP_ICON, true);
+        }
+
+        elements
+    }
+
+    // true if the panel should show the storage health warning
+    fn storage_health_warning(&self) -> bool {
+        self.config.storage_health.show_warning && self.storage_health.has_problems()
+    }
+
+    fn storage_health_panel_ui(
+        &'_ self,
+        _horizontal: bool,
+    ) -> VecDeque<Element<'_, crate::app::Message>> {
+        let mut elements: VecDeque<Element<Message>> = VecDeque::new();
+
+        if self.storage_health_warning() {
+            self.push_symbolic_icon(&mut elements, WARNING_ICON, false);
+
+            if self.config.storage_health.label_visible {
+                let problems = self.storage_health.problems();
+                let label = match problems.len() {
+                    1 => problems[0].clone(),
+                    _ => fl!("storage-health-problems", count = problems.len()),
+                };
+                elements.push_back(self.figure_label(label, None).into());
+            }
+        }
+
+        elements
+    }
+
+    fn filesystems_panel_ui(
+        &'_ self,
+        _horizontal: bool,
+    ) -> VecDeque<Element<'_, crate::app::Message>> {
+        let size = self.core.applet.suggested_size(false);
+
+        let mut elements: VecDeque<Element<Message>> = VecDeque::new();
+
+        for idx in 0..self.filesystems.mounts().len() {
+            if self.config.filesystems.label_visible() {
+                elements.push_back(
+                    self.value_label(
+                        self.filesystems.mount_label(idx),
+                        None,
+                        &self.config.filesystems.color_stops,
+                        self.filesystems.mounts().get(idx).map(|m| m.percentage()),
+                    )
+                    .into(),
+                );
+            }
+
+            if self.config.filesystems.chart_visible() {
+                elements.push_back(
+                    self.filesystems
+                        .mount_chart(idx)
+                        .height(size.0)
+                        .width(size.1)
+                        .into(),
+                );
+            }
+        }
+
+        if self.config.symbols && !elements.is_empty() {
+            self.push_symbolic_icon(&mut elements, DISK_ICON, true);
+        }
+
+        elements
+    }
+
     fn gpu_panel_ui<'a>(
         &'a self,
         gpu: &'a Gpu,
@@ -1804,28 +2723,43 @@ impl Minimon {
             let formatted_vram = gpu.vram.string(!horizontal);
             let stacked_labels =
                 config.stack_labels && config.usage.label_visible() && config.vram.label_visible();
+            let gpu_label = |text: String| {
+                self.value_label(
+                    text,
+                    self.label_gpu_width,
+                    &config.usage.color_stops,
+                    Some(gpu.gpu.latest_sample()),
+                )
+            };
+            let vram_label = |text: String| {
+                self.value_label(text, None, &config.vram.color_stops, gpu.vram.percentage())
+            };
 
             if stacked_labels {
                 let gpu_labels = vec![
                     widget::vertical_space().into(),
-                    self.figure_label(formatted_gpu, self.label_gpu_width)
-                        .into(),
-                    self.figure_label(formatted_vram.clone(), None).into(),
+                    gpu_label(formatted_gpu).into(),
+                    vram_label(formatted_vram.clone()).into(),
                     widget::vertical_space().into(),
                 ];
                 elements.push_back(Column::from_vec(gpu_labels).into());
             } else if config.usage.label_visible() {
-                elements.push_back(
-                    self.figure_label(formatted_gpu, self.label_gpu_width)
-                        .into(),
-                );
+                elements.push_back(gpu_label(formatted_gpu).into());
             }
 
             if config.usage.chart_visible() {
                 elements.push_back(gpu.gpu.chart().height(size.0).width(size.1).into());
             }
             if config.temp.label_visible() {
-                elements.push_back(self.figure_label(gpu.temp.to_string(), None).into());
+                elements.push_back(
+                    self.value_label(
+                        gpu.temp.to_string(),
+                        None,
+                        &config.temp.color_stops,
+                        Some(gpu.temp.latest_sample()),
+                    )
+                    .into(),
+                );
             }
 
             if config.temp.chart_visible() {
@@ -1833,12 +2767,105 @@ impl Minimon {
             }
 
             if config.vram.label_visible() && !stacked_labels {
-                elements.push_back(self.figure_label(formatted_vram, None).into());
+                elements.push_back(vram_label(formatted_vram).into());
             }
 
             if config.vram.chart_visible() {
                 elements.push_back(gpu.vram.chart().height(size.0).width(size.1).into());
             }
+
+            for graph in gpu.metrics.values() {
+                if graph.config().label_visible() {
+                    elements.push_back(
+                        self.value_label(
+                            graph.to_string(),
+                            None,
+                            &graph.config().color_stops,
+                            Some(graph.latest_sample()),
+                        )
+                        .into(),
+                    );
+                }
+                if graph.config().chart_visible() {
+                    elements.push_back(graph.chart().height(size.0).width(size.1).into());
+                }
+            }
+        }
+
+        if self.config.symbols && !elements.is_empty() {
+            self.push_symbolic_icon(&mut elements, GPU_ICON, true);
+        }
+
+        if let Some(state) = gpu.power_state()
+            && self
+                .config
+                .gpus
+                .get(&gpu.id())
+                .is_some_and(|c| c.power_indicator)
+        {
+            self.push_symbolic_icon(&mut elements, state.icon(), false);
+        }
+
+        if self
+            .config
+            .gpus
+            .get(&gpu.id())
+            .is_some_and(|c| c.throttle_warning)
+            && gpu.is_throttled()
+        {
+            self.push_symbolic_icon(&mut elements, WARNING_ICON, false);
+        }
+
+        elements
+    }
+
+    fn gpu_combined_panel_ui(
+        &'_ self,
+        horizontal: bool,
+    ) -> VecDeque<Element<'_, crate::app::Message>> {
+        let size = self.core.applet.suggested_size(false);
+
+        let mut elements: VecDeque<Element<Message>> = VecDeque::new();
+
+        if !self.show_gpu_combined() {
+            return elements;
+        }
+
+        let config = &self.config.gpu_combined;
+        if config.label_visible() {
+            elements.push_back(
+                self.value_label(
+                    self.gpu_combined.usage_label(),
+                    self.label_gpu_width,
+                    &config.color_stops,
+                    Some(self.gpu_combined.latest_sample()),
+                )
+                .into(),
+            );
+        }
+
+        if config.chart_visible() {
+            elements.push_back(
+                self.gpu_combined
+                    .chart()
+                    .height(size.0)
+                    .width(size.1)
+                    .into(),
+            );
+        }
+
+        if config.temp_label {
+            elements.push_back(
+                self.figure_label(self.gpu_combined.temp_label(), None)
+                    .into(),
+            );
+        }
+
+        if config.vram_label {
+            elements.push_back(
+                self.figure_label(self.gpu_combined.vram_label(!horizontal), None)
+                    .into(),
+            );
         }
 
         if self.config.symbols && !elements.is_empty() {
@@ -1860,11 +2887,7 @@ impl Minimon {
     fn save_config(&self) {
         info!("save_config()");
         if let Ok(helper) = cosmic::cosmic_config::Config::new(
-            match self.core.applet.panel_type {
-                PanelType::Panel => APP_ID_PANEL,
-                PanelType::Dock => APP_ID_DOCK,
-                PanelType::Other(_) => APP_ID_OTHER,
-            },
+            app_id(&self.core.applet.panel_type),
             MinimonConfig::VERSION,
         ) && let Err(err) = self.config.write_entry(&helper)
         {
@@ -1891,6 +2914,15 @@ impl Minimon {
                 let (_, config) = disks_select!(self, variant);
                 *config.colors_mut() = *colors;
             }
+            DeviceKind::DiskTemp => {
+                *self.config.disktemp.colors_mut() = *colors;
+            }
+            DeviceKind::Filesystems => {
+                *self.config.filesystems.colors_mut() = *colors;
+            }
+            DeviceKind::GpuCombined => {
+                *self.config.gpu_combined.colors_mut() = *colors;
+            }
             DeviceKind::Gpu => {
                 if let Some(id) = id {
                     if let Some(config) = self.config.gpus.get_mut(&id) {
@@ -1918,30 +2950,44 @@ impl Minimon {
                     }
                 }
             }
+            DeviceKind::GpuMetric(metric) => {
+                if let Some(id) = id {
+                    if let Some(config) = self.config.gpus.get_mut(&id) {
+                        *config.metric_mut(metric).colors_mut() = *colors;
+                    } else {
+                        error!("No config for selected GPU {id}");
+                    }
+                }
+            }
         }
     }
 
     fn refresh_stats(&mut self) {
+        // Replaying, the sensors read the next update of the trace
+        trace::next_frame();
+
         // Update everything if popup open
         let all = self.popup.is_some();
+        // The Prometheus endpoint serves every sensor
+        let sample = all || self.prometheus.is_some();
 
-        if all || self.config.cpu.visible() {
+        if sample || self.config.cpu.needs_update() {
             self.cpu.update();
         }
 
-        if all || self.config.cputemp.visible() {
+        if sample || self.config.cputemp.needs_update() {
             self.cputemp.update();
         }
 
-        if all || self.config.memory.visible() {
+        if sample || self.config.memory.needs_update() {
             self.memory.update();
         }
 
         let combined_network = self.config.network1.variant == NetworkVariant::Combined;
-        if all
-            || (combined_network && self.config.network1.visible())
+        if sample
+            || (combined_network && self.config.network1.needs_update())
             || (!combined_network
-                && (self.config.network1.visible() || self.config.network1.visible()))
+                && (self.config.network1.needs_update() || self.config.network2.needs_update()))
         {
             self.network1.update();
             self.network2.update();
@@ -1949,26 +2995,165 @@ impl Minimon {
 
         let combined_disks = self.config.disks1.variant == DisksVariant::Combined;
 
-        if all
-            || (combined_disks && self.config.disks1.visible())
-            || (!combined_disks && (self.config.disks1.visible() || self.config.disks2.visible()))
+        if sample
+            || (combined_disks && self.config.disks1.needs_update())
+            || (!combined_disks
+                && (self.config.disks1.needs_update() || self.config.disks2.needs_update()))
         {
             self.disks1.update();
             self.disks2.update();
         }
 
+        if sample || self.config.disktemp.needs_update() {
+            self.disktemp.update();
+        }
+
+        if all || self.config.filesystems.needs_update() {
+            self.filesystems.update();
+        }
+
+        // The combined item needs the samples of every GPU
+        let combined = self.has_gpu_combined() && self.config.gpu_combined.needs_update();
         for gpu in &mut self.gpus.values_mut() {
             if let Some(g) = self.config.gpus.get(&gpu.id())
-                && (all || g.is_visible())
+                && (sample || combined || g.needs_update())
             {
                 if all && !gpu.is_active() {
                     gpu.restart();
                 }
                 gpu.update();
+                if all {
+                    gpu.update_processes();
+                }
             }
+
+            // Polled for every GPU, even stopped ones, reading the state doesn't wake it
+            gpu.update_power_state(all);
+        }
+
+        if all || combined {
+            self.gpu_combined.update(&self.gpus);
         }
     }
 
+    // The combined item needs two graphics cards, NPUs don't count
+    fn has_gpu_combined(&self) -> bool {
+        self.gpus.values().filter(|g| !g.is_accelerator()).count() > 1
+    }
+
+    fn show_gpu_combined(&self) -> bool {
+        self.has_gpu_combined() && self.config.gpu_combined.is_visible()
+    }
+
+    // Items with alerts enabled are sampled by refresh_stats() even when hidden
+    fn check_alerts(&mut self) {
+        let mut samples = Vec::new();
+        let mut push =
+            |key: &str, device: DeviceKind, sensor: &dyn Sensor, config: &AlertConfig| {
+                if config.enabled {
+                    samples.push(AlertSample {
+                        key: key.to_string(),
+                        name: device.to_string(),
+                        value: sensor.alert_value(),
+                        unit: sensor.alert_unit(),
+                        config: config.clone(),
+                    });
+                }
+            };
+
+        let config = &self.config;
+        push("cpu", DeviceKind::Cpu, &self.cpu, &config.cpu.alerts);
+        push(
+            "cputemp",
+            DeviceKind::CpuTemp,
+            &self.cputemp,
+            &config.cputemp.alerts,
+        );
+        push(
+            "memory",
+            DeviceKind::Memory,
+            &self.memory,
+            &config.memory.alerts,
+        );
+        push(
+            "network1",
+            DeviceKind::Network(config.network1.variant),
+            &self.network1,
+            &config.network1.alerts,
+        );
+        if config.network1.variant != NetworkVariant::Combined {
+            push(
+                "network2",
+                DeviceKind::Network(config.network2.variant),
+                &self.network2,
+                &config.network2.alerts,
+            );
+        }
+        push(
+            "disks1",
+            DeviceKind::Disks(config.disks1.variant),
+            &self.disks1,
+            &config.disks1.alerts,
+        );
+        if config.disks1.variant != DisksVariant::Combined {
+            push(
+                "disks2",
+                DeviceKind::Disks(config.disks2.variant),
+                &self.disks2,
+                &config.disks2.alerts,
+            );
+        }
+        push(
+            "disktemp",
+            DeviceKind::DiskTemp,
+            &self.disktemp,
+            &config.disktemp.alerts,
+        );
+        push(
+            "filesystems",
+            DeviceKind::Filesystems,
+            &self.filesystems,
+            &config.filesystems.alerts,
+        );
+
+        for gpu in self.gpus.values() {
+            if let Some(g) = config.gpus.get(&gpu.id()) {
+                samples.extend(gpu.alert_samples(g));
+            }
+        }
+        if self.has_gpu_combined() {
+            samples.push(AlertSample {
+                key: String::from("gpu_combined"),
+                name: DeviceKind::GpuCombined.to_string(),
+                value: Some(self.gpu_combined.latest_sample()),
+                unit: "%",
+                config: config.gpu_combined.alerts.clone(),
+            });
+        }
+
+        self.alerts.update(samples);
+    }
+
+    fn alert_config_mut(
+        &mut self,
+        device: DeviceKind,
+        id: Option<&str>,
+    ) -> Option<&mut AlertConfig> {
+        item_field_mut!(self.config, device, id, alerts)
+    }
+
+    fn color_stops_mut(&mut self, device: DeviceKind, id: Option<&str>) -> Option<&mut ColorStops> {
+        item_field_mut!(self.config, device, id, color_stops)
+    }
+
+    fn chart_window_mut(
+        &mut self,
+        device: DeviceKind,
+        id: Option<&str>,
+    ) -> Option<&mut ChartWindow> {
+        item_field_mut!(self.config, device, id, window)
+    }
+
     fn maybe_stop_gpus(&mut self) {
         if self.is_laptop && !self.on_ac {
             for (id, gpu) in &mut self.gpus {
@@ -2015,23 +3200,34 @@ impl Minimon {
         }
     }
 
-    // make sure our liust of detected GPUs and the config list are equal
-    fn sync_gpu_configs(&mut self) {
-        let config_gpus = &mut self.config.gpus;
-
-        // Remove entries not present in detected GPUs
-        config_gpus.retain(|id, _| self.gpus.contains_key(id));
-
-        // Add missing GPU configs
-        for id in self.gpus.keys() {
-            config_gpus.entry(id.clone()).or_default();
+    // A figure label in the color of the stop its value reached
+    fn value_label<'a>(
+        &self,
+        text: String,
+        width: Option<f32>,
+        stops: &ColorStops,
+        value: Option<f64>,
+    ) -> widget::Text<'a, cosmic::Theme> {
+        let label = self.figure_label(text, width);
+        match value.and_then(|v| stops.color(v)) {
+            Some(c) => label.class(cosmic::theme::Text::Color(iced::Color::from_rgba8(
+                c.red,
+                c.green,
+                c.blue,
+                f32::from(c.alpha) / 255.0,
+            ))),
+            None => label,
         }
+    }
 
-        for (id, gpu) in &mut self.gpus {
-            if let Some(config) = config_gpus.get(id) {
-                gpu.update_config(config, self.config.refresh_rate);
-            }
-        }
+    // make sure every detected GPU has a config. Configs of absent GPUs are kept,
+    // so an eGPU that is plugged in again gets its old settings back
+    fn sync_gpu_configs(&mut self) {
+        sync_gpu_configs(
+            &mut self.gpus,
+            &mut self.config.gpus,
+            self.config.refresh_rate,
+        );
     }
 
     fn update_gpu_config<F>(&mut self, id: &str, action: &str, device: DeviceKind, update_fn: F)
@@ -2167,10 +3363,14 @@ impl Minimon {
                 (true, true) => self.measure_text_width("8.88 MB/s", &attrs),
             };
 
-            self.label_disks_width = if is_horizontal {
-                self.measure_text_width("8.88 MB/s", &attrs)
-            } else {
-                self.measure_text_width("8.88M", &attrs)
+            // IOPS labels are the widest, so size for them if either item shows IOPS
+            let disks_iops = self.config.disks1.metric == DisksMetric::Iops
+                || self.config.disks2.metric == DisksMetric::Iops;
+            self.label_disks_width = match (disks_iops, is_horizontal) {
+                (false, false) => self.measure_text_width("8.88M", &attrs),
+                (false, true) => self.measure_text_width("8.88 MB/s", &attrs),
+                (true, false) => self.measure_text_width("8.88K", &attrs),
+                (true, true) => self.measure_text_width("8.88K IOPS", &attrs),
             };
 
             self.label_w_width = self.measure_text_width("W ", &attrs);
@@ -0,0 +1,132 @@
+//! Settings of the chart and label colors that follow the value of a sensor item.
+
+use cosmic::{
+    Element,
+    cosmic_theme::palette::Srgba,
+    iced::{Alignment, widget::row},
+    iced_widget::Column,
+    widget::{self, settings, toggler},
+};
+use std::sync::LazyLock;
+
+use crate::{
+    app::Message,
+    config::{ColorStop, ColorStops, DeviceKind},
+    fl,
+};
+
+// A new stop is added this far above the highest one
+const NEW_STOP_STEP: u64 = 10;
+
+fn rgb(red: u8, green: u8, blue: u8) -> Srgba<u8> {
+    Srgba::from_components((red, green, blue, 255))
+}
+
+static PRESET_COLORS: LazyLock<[(&'static str, Srgba<u8>); 8]> = LazyLock::new(|| {
+    [
+        (fl!("color-green").leak(), rgb(60, 200, 90)),
+        (fl!("color-yellow").leak(), rgb(240, 220, 40)),
+        (fl!("color-amber").leak(), rgb(255, 190, 0)),
+        (fl!("color-orange").leak(), rgb(255, 120, 20)),
+        (fl!("color-red").leak(), rgb(255, 40, 40)),
+        (fl!("color-blue").leak(), rgb(60, 140, 255)),
+        (fl!("color-purple").leak(), rgb(170, 90, 230)),
+        (fl!("color-grey").leak(), rgb(140, 140, 140)),
+    ]
+});
+
+static PRESET_NAMES: LazyLock<Vec<&'static str>> =
+    LazyLock::new(|| PRESET_COLORS.iter().map(|(name, _)| *name).collect());
+
+/// The color stop settings shared by all sensor items, `from` values are in `unit`
+pub fn settings_ui<'a>(
+    device: DeviceKind,
+    id: Option<String>,
+    stops: &ColorStops,
+    unit: &str,
+) -> Element<'a, Message> {
+    let mut column = Vec::new();
+
+    let toggle_id = id.clone();
+    let toggle_stops = stops.clone();
+    column.push(
+        settings::item(
+            fl!("color-stops-enable"),
+            toggler(stops.enabled).on_toggle(move |enabled| {
+                Message::ColorStopsChanged(
+                    device,
+                    toggle_id.clone(),
+                    ColorStops {
+                        enabled,
+                        ..toggle_stops.clone()
+                    },
+                )
+            }),
+        )
+        .into(),
+    );
+
+    if stops.enabled {
+        // Listed as stored, sorting while typing would move the row being edited
+        for (idx, stop) in stops.stops.iter().enumerate() {
+            let from_id = id.clone();
+            let from_stops = stops.clone();
+            let color_id = id.clone();
+            let color_stops = stops.clone();
+            let remove_id = id.clone();
+            let mut remove_stops = stops.clone();
+            remove_stops.stops.remove(idx);
+
+            let selected = PRESET_COLORS.iter().position(|(_, c)| *c == stop.color);
+            column.push(
+                settings::item(
+                    fl!("color-stop-from", unit = unit),
+                    row!(
+                        widget::text_input("", stop.from.to_string())
+                            .width(60)
+                            .on_input(move |s| {
+                                let mut stops = from_stops.clone();
+                                // Empty is the lowest stop, anything unparsable is ignored
+                                if s.trim().is_empty() {
+                                    stops.stops[idx].from = 0;
+                                } else if let Ok(from) = s.trim().parse() {
+                                    stops.stops[idx].from = from;
+                                }
+                                Message::ColorStopsChanged(device, from_id.clone(), stops)
+                            }),
+                        widget::dropdown(&*PRESET_NAMES, selected, move |m| {
+                            let mut stops = color_stops.clone();
+                            stops.stops[idx].color = PRESET_COLORS[m].1;
+                            Message::ColorStopsChanged(device, color_id.clone(), stops)
+                        })
+                        .width(100),
+                        widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                            .on_press(Message::ColorStopsChanged(device, remove_id, remove_stops)),
+                    )
+                    .align_y(Alignment::Center)
+                    .spacing(cosmic::theme::active().cosmic().space_xxs()),
+                )
+                .into(),
+            );
+        }
+
+        let mut added = stops.clone();
+        let last = stops.sorted().last().copied();
+        added.stops.push(ColorStop {
+            from: last.map_or(0, |s| s.from + NEW_STOP_STEP),
+            color: last.map_or(PRESET_COLORS[0].1, |s| s.color),
+        });
+        column.push(
+            row!(
+                widget::horizontal_space(),
+                widget::button::standard(fl!("color-stop-add"))
+                    .on_press(Message::ColorStopsChanged(device, id, added)),
+            )
+            .into(),
+        );
+    }
+
+    Column::with_children(column)
+        .spacing(cosmic::theme::active().cosmic().space_xs())
+        .into()
+}
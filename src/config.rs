@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 
 use cosmic::{
     cosmic_config::{self, CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry},
@@ -52,6 +53,132 @@ impl From<ChartKind> for usize {
     }
 }
 
+/// Time span of the line and heat charts. Live charts the latest updates, the
+/// others are drawn from the downsampled history.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChartWindow {
+    #[default]
+    Live,
+    FiveMinutes,
+    ThirtyMinutes,
+    OneHour,
+    SixHours,
+    OneDay,
+    SevenDays,
+}
+
+impl ChartWindow {
+    pub const ALL: [ChartWindow; 7] = [
+        ChartWindow::Live,
+        ChartWindow::FiveMinutes,
+        ChartWindow::ThirtyMinutes,
+        ChartWindow::OneHour,
+        ChartWindow::SixHours,
+        ChartWindow::OneDay,
+        ChartWindow::SevenDays,
+    ];
+
+    /// None for Live
+    pub fn duration(self) -> Option<Duration> {
+        let minutes = match self {
+            ChartWindow::Live => return None,
+            ChartWindow::FiveMinutes => 5,
+            ChartWindow::ThirtyMinutes => 30,
+            ChartWindow::OneHour => 60,
+            ChartWindow::SixHours => 6 * 60,
+            ChartWindow::OneDay => 24 * 60,
+            ChartWindow::SevenDays => 7 * 24 * 60,
+        };
+        Some(Duration::from_secs(minutes * 60))
+    }
+}
+
+impl From<usize> for ChartWindow {
+    fn from(index: usize) -> Self {
+        ChartWindow::ALL.get(index).copied().unwrap_or_else(|| {
+            log::error!("ChartWindow::From({index}) Invalid index for ChartWindow");
+            ChartWindow::Live
+        })
+    }
+}
+
+impl From<ChartWindow> for usize {
+    fn from(window: ChartWindow) -> Self {
+        ChartWindow::ALL
+            .iter()
+            .position(|w| *w == window)
+            .unwrap_or_default()
+    }
+}
+
+/// GPU measurements beyond usage, VRAM and temperature. Not every GPU reports all of them.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GpuMetric {
+    Power,
+    CoreClock,
+    MemoryClock,
+    Fan,
+    MemoryBusy,
+    Gtt,
+    TempJunction,
+    TempMemory,
+    EncoderUsage,
+    DecoderUsage,
+    PcieRx,
+    PcieTx,
+}
+
+impl GpuMetric {
+    pub const ALL: [GpuMetric; 12] = [
+        GpuMetric::Power,
+        GpuMetric::CoreClock,
+        GpuMetric::MemoryClock,
+        GpuMetric::Fan,
+        GpuMetric::MemoryBusy,
+        GpuMetric::Gtt,
+        GpuMetric::TempJunction,
+        GpuMetric::TempMemory,
+        GpuMetric::EncoderUsage,
+        GpuMetric::DecoderUsage,
+        GpuMetric::PcieRx,
+        GpuMetric::PcieTx,
+    ];
+
+    /// Unit of the values reported by GpuIf::metric
+    pub fn unit(self) -> &'static str {
+        match self {
+            GpuMetric::Power => "W",
+            GpuMetric::CoreClock | GpuMetric::MemoryClock => "MHz",
+            GpuMetric::Fan
+            | GpuMetric::MemoryBusy
+            | GpuMetric::EncoderUsage
+            | GpuMetric::DecoderUsage => "%",
+            GpuMetric::PcieRx | GpuMetric::PcieTx => "MB/s",
+            GpuMetric::Gtt => "GB",
+            GpuMetric::TempJunction | GpuMetric::TempMemory => "C",
+        }
+    }
+}
+
+impl std::fmt::Display for GpuMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GpuMetric::Power => write!(f, "{}", fl!("gpu-metric-power")),
+            GpuMetric::CoreClock => write!(f, "{}", fl!("gpu-metric-core-clock")),
+            GpuMetric::MemoryClock => write!(f, "{}", fl!("gpu-metric-memory-clock")),
+            GpuMetric::Fan => write!(f, "{}", fl!("gpu-metric-fan")),
+            GpuMetric::MemoryBusy => write!(f, "{}", fl!("gpu-metric-memory-busy")),
+            GpuMetric::Gtt => write!(f, "{}", fl!("gpu-metric-gtt")),
+            GpuMetric::TempJunction => write!(f, "{}", fl!("gpu-metric-temp-junction")),
+            GpuMetric::TempMemory => write!(f, "{}", fl!("gpu-metric-temp-memory")),
+            GpuMetric::EncoderUsage => write!(f, "{}", fl!("gpu-metric-encoder")),
+            GpuMetric::DecoderUsage => write!(f, "{}", fl!("gpu-metric-decoder")),
+            GpuMetric::PcieRx => write!(f, "{}", fl!("gpu-metric-pcie-rx")),
+            GpuMetric::PcieTx => write!(f, "{}", fl!("gpu-metric-pcie-tx")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DeviceKind {
     Cpu,
@@ -59,9 +186,13 @@ pub enum DeviceKind {
     Memory,
     Network(NetworkVariant),
     Disks(DisksVariant),
+    DiskTemp,
+    Filesystems,
     Gpu,
     Vram,
     GpuTemp,
+    GpuMetric(GpuMetric),
+    GpuCombined,
 }
 
 impl std::fmt::Display for DeviceKind {
@@ -72,9 +203,13 @@ impl std::fmt::Display for DeviceKind {
             DeviceKind::Memory => write!(f, "{}", fl!("sensor-memory")),
             DeviceKind::Network(_) => write!(f, "{}", fl!("sensor-network")),
             DeviceKind::Disks(_) => write!(f, "{}", fl!("sensor-disks")),
+            DeviceKind::DiskTemp => write!(f, "{}", fl!("sensor-disk-temperature")),
+            DeviceKind::Filesystems => write!(f, "{}", fl!("sensor-filesystems")),
             DeviceKind::Gpu => write!(f, "{}", fl!("sensor-gpu")),
             DeviceKind::Vram => write!(f, "{}", fl!("sensor-vram")),
             DeviceKind::GpuTemp => write!(f, "{}", fl!("sensor-gpu-temp")),
+            DeviceKind::GpuMetric(metric) => write!(f, "{metric}"),
+            DeviceKind::GpuCombined => write!(f, "{}", fl!("sensor-gpu-combined")),
         }
     }
 }
@@ -166,6 +301,21 @@ impl ChartColors {
                 graph2: rgba!(255, 255, 0, 85),
                 ..Default::default()
             },
+            DeviceKind::DiskTemp => match chart {
+                ChartKind::Ring => ChartColors {
+                    graph1: rgba!(255, 6, 0, 255),
+                    ..Default::default()
+                },
+                ChartKind::Line => ChartColors {
+                    graph1: rgba!(255, 90, 0, 85),
+                    ..Default::default()
+                },
+                _ => ChartColors::default(),
+            },
+            DeviceKind::Filesystems => ChartColors {
+                graph1: rgba!(255, 165, 0, 255),
+                ..Default::default()
+            },
             DeviceKind::Gpu => match chart {
                 ChartKind::Ring => ChartColors {
                     graph1: rgba!(0, 255, 0, 255),
@@ -199,6 +349,31 @@ impl ChartColors {
                 },
                 _ => ChartColors::default(),
             },
+            DeviceKind::GpuMetric(_) => match chart {
+                ChartKind::Ring => ChartColors {
+                    graph1: rgba!(0, 200, 255, 255),
+                    ..Default::default()
+                },
+                ChartKind::Line => ChartColors {
+                    graph1: rgba!(0, 200, 255, 85),
+                    ..Default::default()
+                },
+                _ => ChartColors::default(),
+            },
+            DeviceKind::GpuCombined => match chart {
+                ChartKind::Ring => ChartColors {
+                    graph1: rgba!(0, 255, 0, 255),
+                    graph3: rgba!(0, 200, 255, 255),
+                    ..Default::default()
+                },
+                ChartKind::Line => ChartColors {
+                    graph1: rgba!(0, 255, 0, 85),
+                    graph2: rgba!(0, 200, 255, 85),
+                    graph3: rgba!(255, 200, 0, 85),
+                    ..Default::default()
+                },
+                _ => ChartColors::default(),
+            },
         }
     }
 
@@ -263,6 +438,99 @@ impl Colors {
     }
 }
 
+/// Threshold alerts of a sensor item, in the unit the sensor reports for alerts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AlertConfig {
+    pub enabled: bool,
+    pub warning: Option<u64>,
+    pub critical: Option<u64>,
+    // How far the value must drop below a threshold before the alert clears
+    pub hysteresis: u64,
+    // Seconds the value must stay above a threshold before the alert trips
+    pub duration: u32,
+    // Shell command run on every alert and recovery, empty for none
+    #[serde(default)]
+    pub command: String,
+    // Seconds before a new alert runs the command again
+    #[serde(default = "default_command_cooldown")]
+    pub command_cooldown: u32,
+}
+
+fn default_command_cooldown() -> u32 {
+    60
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            warning: None,
+            critical: None,
+            hysteresis: 2,
+            duration: 10,
+            command: String::new(),
+            command_cooldown: default_command_cooldown(),
+        }
+    }
+}
+
+/// From `from` on, in the unit of the item's alerts, charts and labels use `color`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ColorStop {
+    pub from: u64,
+    pub color: Srgba<u8>,
+}
+
+/// Chart and label colors that follow the value, like green, amber and red
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ColorStops {
+    pub enabled: bool,
+    pub stops: Vec<ColorStop>,
+}
+
+impl Default for ColorStops {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            stops: vec![
+                ColorStop {
+                    from: 0,
+                    color: rgba!(60, 200, 90, 255),
+                },
+                ColorStop {
+                    from: 60,
+                    color: rgba!(255, 190, 0, 255),
+                },
+                ColorStop {
+                    from: 85,
+                    color: rgba!(255, 40, 40, 255),
+                },
+            ],
+        }
+    }
+}
+
+impl ColorStops {
+    /// Color of the highest stop the value reached, None if disabled or below all stops
+    pub fn color(&self, value: f64) -> Option<Srgba<u8>> {
+        if !self.enabled {
+            return None;
+        }
+        self.stops
+            .iter()
+            .filter(|s| value >= s.from as f64)
+            .max_by_key(|s| s.from)
+            .map(|s| s.color)
+    }
+
+    /// The stops ordered by value
+    pub fn sorted(&self) -> Vec<ColorStop> {
+        let mut stops = self.stops.clone();
+        stops.sort_by_key(|s| s.from);
+        stops
+    }
+}
+
 macro_rules! make_config {
     ($name:ident { $($extra:tt)* }) => {
         #[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
@@ -272,6 +540,12 @@ macro_rules! make_config {
             label_visible: bool,
             pub chart: ChartKind,
             colors: Colors,
+            #[serde(default)]
+            pub alerts: AlertConfig,
+            #[serde(default)]
+            pub color_stops: ColorStops,
+            #[serde(default)]
+            pub window: ChartWindow,
             $($extra)*
         }
 
@@ -279,6 +553,10 @@ macro_rules! make_config {
               pub fn visible(&self) -> bool {
                 self.chart_visible() || self.label_visible()
             }
+            // Visible, or sampled in the background for alerts
+            pub fn needs_update(&self) -> bool {
+                self.visible() || self.alerts.enabled
+            }
             pub fn chart_visible(&self) -> bool {
                 self.chart_visible
             }
@@ -314,6 +592,9 @@ impl Default for CpuConfig {
             label_visible: false,
             chart: ChartKind::Ring,
             colors: Colors::new(DeviceKind::Cpu),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
             no_decimals: false,
             bar_width: 4,
             bar_spacing: 1,
@@ -323,6 +604,10 @@ impl Default for CpuConfig {
 
 make_config!(CpuTempConfig {
     pub unit: TempUnit,
+    // Key of the thermal zone to read instead of the hwmon sensor, None to pick automatically.
+    // The zone type, with #<n> for the n-th further zone of the same type.
+    #[serde(default)]
+    pub thermal_zone: Option<String>,
 });
 
 impl Default for CpuTempConfig {
@@ -332,7 +617,11 @@ impl Default for CpuTempConfig {
             label_visible: false,
             chart: ChartKind::Heat,
             colors: Colors::new(DeviceKind::CpuTemp),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
             unit: TempUnit::Celcius,
+            thermal_zone: None,
         }
     }
 }
@@ -349,6 +638,9 @@ impl Default for MemoryConfig {
             label_visible: false,
             chart: ChartKind::Ring,
             colors: Colors::new(DeviceKind::Memory),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
             percentage: false,
             show_allocated: false,
         }
@@ -377,6 +669,9 @@ impl Default for NetworkConfig {
             label_visible: false,
             chart: ChartKind::Line,
             colors: Colors::new(DeviceKind::Network(NetworkVariant::Combined)),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
             adaptive: true,
             bandwidth: 62_500_000,
             unit: Some(0),
@@ -393,8 +688,46 @@ pub enum DisksVariant {
     Combined,
 }
 
+/// What the disk items measure, throughput comes from sysinfo, the rest from /proc/diskstats
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DisksMetric {
+    #[default]
+    Throughput,
+    Iops,
+    Latency,
+    Utilization,
+}
+
+impl From<usize> for DisksMetric {
+    fn from(index: usize) -> Self {
+        match index {
+            0 => DisksMetric::Throughput,
+            1 => DisksMetric::Iops,
+            2 => DisksMetric::Latency,
+            3 => DisksMetric::Utilization,
+            _ => {
+                log::error!("DisksMetric::From({}) Invalid index for DisksMetric", index);
+                DisksMetric::Throughput
+            }
+        }
+    }
+}
+
+impl From<DisksMetric> for usize {
+    fn from(metric: DisksMetric) -> Self {
+        match metric {
+            DisksMetric::Throughput => 0,
+            DisksMetric::Iops => 1,
+            DisksMetric::Latency => 2,
+            DisksMetric::Utilization => 3,
+        }
+    }
+}
+
 make_config!(DisksConfig {
     pub variant: DisksVariant,
+    #[serde(default)]
+    pub metric: DisksMetric,
 });
 
 impl Default for DisksConfig {
@@ -404,7 +737,114 @@ impl Default for DisksConfig {
             label_visible: false,
             chart: ChartKind::Line,
             colors: Colors::new(DeviceKind::Disks(DisksVariant::Combined)),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
             variant: DisksVariant::Combined,
+            metric: DisksMetric::Throughput,
+        }
+    }
+}
+
+make_config!(DiskTempConfig {
+    pub unit: TempUnit,
+    pub hidden: Vec<String>,
+});
+
+impl Default for DiskTempConfig {
+    fn default() -> Self {
+        Self {
+            chart_visible: false,
+            label_visible: false,
+            chart: ChartKind::Ring,
+            colors: Colors::new(DeviceKind::DiskTemp),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
+            unit: TempUnit::Celcius,
+            hidden: Vec::new(),
+        }
+    }
+}
+
+/// How the usage of several GPUs is combined into one value
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GpuCombinedMode {
+    #[default]
+    Average,
+    Max,
+}
+
+impl From<usize> for GpuCombinedMode {
+    fn from(index: usize) -> Self {
+        match index {
+            0 => GpuCombinedMode::Average,
+            1 => GpuCombinedMode::Max,
+            _ => {
+                log::error!("GpuCombinedMode::From({}) Invalid index", index);
+                GpuCombinedMode::Average
+            }
+        }
+    }
+}
+
+impl From<GpuCombinedMode> for usize {
+    fn from(mode: GpuCombinedMode) -> Self {
+        match mode {
+            GpuCombinedMode::Average => 0,
+            GpuCombinedMode::Max => 1,
+        }
+    }
+}
+
+make_config!(GpuCombinedConfig {
+    pub mode: GpuCombinedMode,
+    pub vram_label: bool,
+    pub temp_label: bool,
+    pub unit: TempUnit,
+});
+
+impl Default for GpuCombinedConfig {
+    fn default() -> Self {
+        Self {
+            chart_visible: false,
+            label_visible: false,
+            chart: ChartKind::Line,
+            colors: Colors::new(DeviceKind::GpuCombined),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
+            mode: GpuCombinedMode::Average,
+            vram_label: false,
+            temp_label: false,
+            unit: TempUnit::Celcius,
+        }
+    }
+}
+
+impl GpuCombinedConfig {
+    pub fn is_visible(&self) -> bool {
+        self.visible() || self.vram_label || self.temp_label
+    }
+}
+
+make_config!(FilesystemsConfig {
+    pub mounts: Vec<String>,
+    pub predict_full: bool,
+});
+
+impl Default for FilesystemsConfig {
+    fn default() -> Self {
+        Self {
+            chart_visible: false,
+            label_visible: false,
+            chart: ChartKind::Ring,
+            colors: Colors::new(DeviceKind::Filesystems),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
+            mounts: vec![String::from("/")],
+            predict_full: false,
         }
     }
 }
@@ -418,6 +858,9 @@ impl Default for GpuUsageConfig {
             label_visible: false,
             chart: ChartKind::Ring,
             colors: Colors::new(DeviceKind::Gpu),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
         }
     }
 }
@@ -431,6 +874,9 @@ impl Default for GpuVramConfig {
             label_visible: false,
             chart: ChartKind::Ring,
             colors: Colors::new(DeviceKind::Vram),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
         }
     }
 }
@@ -446,11 +892,30 @@ impl Default for GpuTempConfig {
             label_visible: false,
             chart: ChartKind::Ring,
             colors: Colors::new(DeviceKind::GpuTemp),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
             unit: TempUnit::Celcius,
         }
     }
 }
 
+make_config!(GpuMetricConfig {});
+
+impl Default for GpuMetricConfig {
+    fn default() -> Self {
+        Self {
+            chart_visible: false,
+            label_visible: false,
+            chart: ChartKind::Line,
+            colors: Colors::new(DeviceKind::GpuMetric(GpuMetric::Power)),
+            alerts: AlertConfig::default(),
+            color_stops: ColorStops::default(),
+            window: ChartWindow::default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
 #[version = 1]
 pub struct GpuConfig {
@@ -459,11 +924,38 @@ pub struct GpuConfig {
     pub temp: GpuTempConfig,
     pub pause_on_battery: bool,
     pub stack_labels: bool,
+    #[serde(default)]
+    pub metrics: BTreeMap<GpuMetric, GpuMetricConfig>,
+    #[serde(default)]
+    pub throttle_warning: bool,
+    #[serde(default)]
+    pub power_indicator: bool,
 }
 
 impl GpuConfig {
     pub fn is_visible(&self) -> bool {
-        self.usage.visible() || self.vram.visible() || self.temp.visible()
+        self.usage.visible()
+            || self.vram.visible()
+            || self.temp.visible()
+            || self.metrics.values().any(GpuMetricConfig::visible)
+            || self.throttle_warning
+    }
+
+    // Visible, or sampled in the background for alerts
+    pub fn needs_update(&self) -> bool {
+        self.is_visible()
+            || self.usage.needs_update()
+            || self.vram.needs_update()
+            || self.temp.needs_update()
+            || self.metrics.values().any(GpuMetricConfig::needs_update)
+    }
+
+    pub fn metric(&self, metric: GpuMetric) -> Option<&GpuMetricConfig> {
+        self.metrics.get(&metric)
+    }
+
+    pub fn metric_mut(&mut self, metric: GpuMetric) -> &mut GpuMetricConfig {
+        self.metrics.entry(metric).or_default()
     }
 }
 
@@ -475,6 +967,26 @@ impl Default for GpuConfig {
             temp: GpuTempConfig::default(),
             pause_on_battery: true,
             stack_labels: true,
+            metrics: BTreeMap::new(),
+            throttle_warning: false,
+            power_indicator: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
+#[version = 1]
+pub struct StorageHealthConfig {
+    /// Show a warning symbol in the panel while an array or filesystem has problems
+    pub show_warning: bool,
+    pub label_visible: bool,
+}
+
+impl Default for StorageHealthConfig {
+    fn default() -> Self {
+        Self {
+            show_warning: true,
+            label_visible: false,
         }
     }
 }
@@ -487,6 +999,10 @@ pub enum ContentType {
     NetworkUsage,
     DiskUsage,
     GpuInfo,
+    FilesystemUsage,
+    DiskTemp,
+    StorageHealth,
+    GpuCombined,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
@@ -505,11 +1021,26 @@ impl Default for ContentOrder {
                 ContentType::NetworkUsage,
                 ContentType::DiskUsage,
                 ContentType::GpuInfo,
+                ContentType::FilesystemUsage,
+                ContentType::DiskTemp,
+                ContentType::StorageHealth,
+                ContentType::GpuCombined,
             ],
         }
     }
 }
 
+impl ContentOrder {
+    /// Append items missing from configs saved by older versions, so they can be ordered
+    pub fn complete(&mut self) {
+        for content in ContentOrder::default().order {
+            if !self.order.contains(&content) {
+                self.order.push(content);
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq)]
 #[version = 1]
 pub struct MinimonConfig {
@@ -527,7 +1058,14 @@ pub struct MinimonConfig {
     pub disks1: DisksConfig,
     pub disks2: DisksConfig,
 
+    pub disktemp: DiskTempConfig,
+
+    pub filesystems: FilesystemsConfig,
+
+    pub storage_health: StorageHealthConfig,
+
     pub gpus: HashMap<String, GpuConfig>,
+    pub gpu_combined: GpuCombinedConfig,
 
     pub sysmon: Option<String>,
 
@@ -535,6 +1073,10 @@ pub struct MinimonConfig {
     pub panel_spacing: u16,
 
     pub content_order: ContentOrder,
+
+    /// Serve the readings for Prometheus on 127.0.0.1:prometheus_port
+    pub prometheus: bool,
+    pub prometheus_port: u16,
 }
 
 impl Default for MinimonConfig {
@@ -562,11 +1104,17 @@ impl Default for MinimonConfig {
                 variant: DisksVariant::Read,
                 ..Default::default()
             },
+            disktemp: DiskTempConfig::default(),
+            filesystems: FilesystemsConfig::default(),
+            storage_health: StorageHealthConfig::default(),
             gpus: HashMap::new(),
+            gpu_combined: GpuCombinedConfig::default(),
             sysmon: None,
             symbols: false,
             panel_spacing: 3, // Slider setting for cosmic.space_xs()
             content_order: ContentOrder::default(),
+            prometheus: false,
+            prometheus_port: crate::prometheus::DEFAULT_PORT,
         }
     }
 }
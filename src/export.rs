@@ -0,0 +1,271 @@
+//! Export of the recorded history to CSV or JSON Lines, to attach to bug reports.
+//!
+//! Every row is one raw sample or one aggregate of a tier. Aggregates of the tiers
+//! overlap in time, `resolution` tells them apart: 0 for raw samples, otherwise the
+//! length of the aggregated interval in seconds.
+
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::history::History;
+
+const CSV_HEADER: &str = "timestamp,instance,sensor,unit,resolution,samples,min,avg,max";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    JsonLines,
+}
+
+impl Format {
+    /// JSON Lines for .json, .jsonl and .ndjson files, CSV for anything else
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext)
+                if ["json", "jsonl", "ndjson"]
+                    .iter()
+                    .any(|e| ext.eq_ignore_ascii_case(e)) =>
+            {
+                Format::JsonLines
+            }
+            _ => Format::Csv,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// The history of one sensor item, `key` like the alert keys, e.g. gpu:<id>:temp
+pub struct Source<'a> {
+    // panel, dock or other
+    pub instance: &'a str,
+    pub key: &'a str,
+    pub unit: &'a str,
+    pub history: &'a History,
+}
+
+/// The instance name of an app id, the panel type it ends with
+pub fn instance(app_id: &str) -> &str {
+    app_id.rsplit('-').next().unwrap_or(app_id)
+}
+
+/// minimon-<date>-<time> in the XDG download directory
+pub fn default_path(format: Format) -> Option<PathBuf> {
+    let name = format!(
+        "minimon-{}.{}",
+        Local::now().format("%Y%m%d-%H%M%S"),
+        format.extension()
+    );
+    Some(download_dir()?.join(name))
+}
+
+// XDG_DOWNLOAD_DIR of user-dirs.dirs, ~/Downloads if it isn't set
+fn download_dir() -> Option<PathBuf> {
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| home.join(".config"));
+
+    let configured = fs::read_to_string(config.join("user-dirs.dirs"))
+        .ok()
+        .and_then(|dirs| {
+            dirs.lines().find_map(|line| {
+                let value = line.trim().strip_prefix("XDG_DOWNLOAD_DIR=")?;
+                let value = value.trim_matches('"');
+                match value.strip_prefix("$HOME") {
+                    Some(rest) => Some(home.join(rest.trim_start_matches('/'))),
+                    None => Some(PathBuf::from(value)).filter(|p| p.is_absolute()),
+                }
+            })
+        });
+    Some(configured.unwrap_or_else(|| home.join("Downloads")))
+}
+
+/// Write all samples and aggregates of `sources`, returns the number of rows
+pub fn write(out: &mut impl Write, format: Format, sources: &[Source]) -> io::Result<usize> {
+    if format == Format::Csv {
+        writeln!(out, "{CSV_HEADER}")?;
+    }
+
+    let mut rows = 0;
+    for source in sources {
+        for &(time, value) in &source.history.raw {
+            write_row(out, format, source, time, 0, 1, [value; 3])?;
+            rows += 1;
+        }
+        for tier in &source.history.tiers {
+            let resolution = tier.resolution.as_secs();
+            for b in &tier.buckets {
+                write_row(
+                    out,
+                    format,
+                    source,
+                    b.start,
+                    resolution,
+                    b.count,
+                    [b.min, b.avg, b.max],
+                )?;
+                rows += 1;
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Write `sources` to `path` in the format of its extension
+pub fn write_file(path: &Path, sources: &[Source]) -> io::Result<usize> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut out = BufWriter::new(fs::File::create(path)?);
+    let rows = write(&mut out, Format::from_path(path), sources)?;
+    out.flush()?;
+    Ok(rows)
+}
+
+fn write_row(
+    out: &mut impl Write,
+    format: Format,
+    source: &Source,
+    time: u64,
+    resolution: u64,
+    samples: u32,
+    [min, avg, max]: [f64; 3],
+) -> io::Result<()> {
+    let timestamp = DateTime::<Utc>::from_timestamp_millis(time as i64)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Millis, true);
+    match format {
+        Format::Csv => writeln!(
+            out,
+            "{timestamp},{},{},{},{resolution},{samples},{min},{avg},{max}",
+            csv_field(source.instance),
+            csv_field(source.key),
+            csv_field(source.unit),
+        ),
+        Format::JsonLines => writeln!(
+            out,
+            r#"{{"timestamp":"{timestamp}","instance":{},"sensor":{},"unit":{},"resolution":{resolution},"samples":{samples},"min":{},"avg":{},"max":{}}}"#,
+            json_string(source.instance),
+            json_string(source.key),
+            json_string(source.unit),
+            json_number(min),
+            json_number(avg),
+            json_number(max),
+        ),
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// JSON has no NaN or infinity
+pub fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        String::from("null")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: u64 = 1_700_000_400_000;
+
+    fn history() -> History {
+        let mut history = History::default();
+        history.push(START, 10.0);
+        history.push(START + 1000, 20.0);
+        history
+    }
+
+    #[test]
+    fn csv_rows() {
+        let history = history();
+        let sources = [Source {
+            instance: "panel",
+            key: "gpu:0000:01:00.0,1:temp",
+            unit: "°C",
+            history: &history,
+        }];
+        let mut out = Vec::new();
+        // Two raw samples, both in the same aggregate of every tier
+        assert_eq!(write(&mut out, Format::Csv, &sources).unwrap(), 5);
+
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            r#"2023-11-14T22:20:00.000Z,panel,"gpu:0000:01:00.0,1:temp",°C,0,1,10,10,10"#
+        );
+        assert_eq!(
+            lines[3],
+            r#"2023-11-14T22:20:00.000Z,panel,"gpu:0000:01:00.0,1:temp",°C,10,2,10,15,20"#
+        );
+    }
+
+    #[test]
+    fn json_lines_rows() {
+        let history = history();
+        let sources = [Source {
+            instance: "dock",
+            key: "disks1:\"sda\"",
+            unit: "B/s",
+            history: &history,
+        }];
+        let mut out = Vec::new();
+        write(&mut out, Format::JsonLines, &sources).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out.lines().last().unwrap(),
+            r#"{"timestamp":"2023-11-14T22:20:00.000Z","instance":"dock","sensor":"disks1:\"sda\"","unit":"B/s","resolution":600,"samples":2,"min":10,"avg":15,"max":20}"#
+        );
+    }
+
+    #[test]
+    fn format_and_instance() {
+        assert_eq!(Format::from_path(Path::new("a.JSONL")), Format::JsonLines);
+        assert_eq!(Format::from_path(Path::new("a.json")), Format::JsonLines);
+        assert_eq!(Format::from_path(Path::new("a.csv")), Format::Csv);
+        assert_eq!(Format::from_path(Path::new("a")), Format::Csv);
+        assert_eq!(
+            instance("io.github.cosmic_utils.minimon-applet-panel"),
+            "panel"
+        );
+    }
+}
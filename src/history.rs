@@ -0,0 +1,479 @@
+//! Sample history of a sensor value: the raw samples of the last minutes, and
+//! min/avg/max aggregates at 10 s, 1 min and 10 min resolution for hours and days.
+
+use cosmic::{Element, widget};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    app::Message,
+    config::{ChartWindow, DeviceKind},
+    fl,
+};
+
+// Raw samples are kept this long
+const RAW_RETENTION: Duration = Duration::from_secs(10 * 60);
+
+// Resolution and retention of the aggregate tiers, finest first
+const TIERS: [(Duration, Duration); 3] = [
+    (Duration::from_secs(10), Duration::from_secs(6 * 3600)),
+    (Duration::from_secs(60), Duration::from_secs(2 * 86400)),
+    (Duration::from_secs(600), Duration::from_secs(14 * 86400)),
+];
+
+// Without a sample for this long, or two intervals of the source, the sensor wasn't
+// sampled, like while the applet wasn't running, and charts leave the time empty
+const MAX_GAP: Duration = Duration::from_secs(120);
+
+static WINDOW_OPTIONS: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
+    ChartWindow::ALL
+        .iter()
+        .map(|w| {
+            let label = match w {
+                ChartWindow::Live => fl!("chart-window-live"),
+                ChartWindow::FiveMinutes => fl!("chart-window-minutes", minutes = 5),
+                ChartWindow::ThirtyMinutes => fl!("chart-window-minutes", minutes = 30),
+                ChartWindow::OneHour => fl!("chart-window-hours", hours = 1),
+                ChartWindow::SixHours => fl!("chart-window-hours", hours = 6),
+                ChartWindow::OneDay => fl!("chart-window-days", days = 1),
+                ChartWindow::SevenDays => fl!("chart-window-days", days = 7),
+            };
+            &*label.leak()
+        })
+        .collect()
+});
+
+/// Milliseconds since the epoch, the timestamps of the history
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// A history of a sensor item with the name and unit it's saved and exported under
+pub struct Series<'a> {
+    pub name: String,
+    pub unit: &'static str,
+    pub history: &'a mut History,
+}
+
+impl<'a> Series<'a> {
+    pub fn new(name: impl Into<String>, unit: &'static str, history: &'a mut History) -> Self {
+        Series {
+            name: name.into(),
+            unit,
+            history,
+        }
+    }
+}
+
+/// Samples in one interval of an aggregate tier
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aggregate {
+    // Start of the interval, in ms since the epoch
+    pub start: u64,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub count: u32,
+}
+
+impl Aggregate {
+    fn new(start: u64, value: f64) -> Self {
+        Aggregate {
+            start,
+            min: value,
+            avg: value,
+            max: value,
+            count: 1,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.count += 1;
+        self.avg += (value - self.avg) / f64::from(self.count);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Tier {
+    pub resolution: Duration,
+    pub retention: Duration,
+    pub buckets: VecDeque<Aggregate>,
+}
+
+impl Tier {
+    fn new(resolution: Duration, retention: Duration) -> Self {
+        Tier {
+            resolution,
+            retention,
+            buckets: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, time: u64, value: f64) {
+        let resolution = self.resolution.as_millis() as u64;
+        let start = time - time % resolution;
+        match self.buckets.back_mut() {
+            Some(bucket) if bucket.start == start => bucket.add(value),
+            _ => self.buckets.push_back(Aggregate::new(start, value)),
+        }
+
+        let oldest = time.saturating_sub(self.retention.as_millis() as u64);
+        while self.buckets.front().is_some_and(|b| b.start < oldest) {
+            self.buckets.pop_front();
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    // (ms since the epoch, value)
+    pub raw: VecDeque<(u64, f64)>,
+    pub tiers: [Tier; 3],
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            raw: VecDeque::new(),
+            tiers: TIERS.map(|(resolution, retention)| Tier::new(resolution, retention)),
+        }
+    }
+}
+
+impl History {
+    pub fn push(&mut self, time: u64, value: f64) {
+        // After the clock stepped back, drop what was sampled after `time`
+        // so the samples stay in order
+        self.truncate_after(time);
+
+        self.raw.push_back((time, value));
+        let oldest = time.saturating_sub(RAW_RETENTION.as_millis() as u64);
+        while self.raw.front().is_some_and(|&(t, _)| t < oldest) {
+            self.raw.pop_front();
+        }
+
+        for tier in &mut self.tiers {
+            tier.push(time, value);
+        }
+    }
+
+    pub fn push_now(&mut self, value: f64) {
+        self.push(now_ms(), value);
+    }
+
+    // Raw samples after `time` and aggregates of later intervals
+    fn truncate_after(&mut self, time: u64) {
+        while self.raw.back().is_some_and(|&(t, _)| t > time) {
+            self.raw.pop_back();
+        }
+        for tier in &mut self.tiers {
+            let resolution = tier.resolution.as_millis() as u64;
+            let start = time - time % resolution;
+            while tier.buckets.back().is_some_and(|b| b.start > start) {
+                tier.buckets.pop_back();
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.raw.clear();
+        for tier in &mut self.tiers {
+            tier.buckets.clear();
+        }
+    }
+
+    /// Drop the aggregates outside their retention or after `now`, as after loading
+    /// a snapshot taken before a restart or a change of the clock
+    pub fn prune(&mut self, now: u64) {
+        let oldest = now.saturating_sub(RAW_RETENTION.as_millis() as u64);
+        self.raw.retain(|&(t, _)| t >= oldest && t <= now);
+        for tier in &mut self.tiers {
+            let oldest = now.saturating_sub(tier.retention.as_millis() as u64);
+            tier.buckets.retain(|b| b.start >= oldest && b.start <= now);
+        }
+    }
+
+    // Resolution and (time, value) pairs of the finest source that covers the window,
+    // aggregates at the middle of their interval
+    fn source(&self, window: Duration) -> (Duration, Box<dyn Iterator<Item = (u64, f64)> + '_>) {
+        if window <= RAW_RETENTION {
+            return (Duration::ZERO, Box::new(self.raw.iter().copied()));
+        }
+        let tier = self
+            .tiers
+            .iter()
+            .find(|t| t.retention >= window)
+            .unwrap_or(&self.tiers[self.tiers.len() - 1]);
+        let half = tier.resolution.as_millis() as u64 / 2;
+        (
+            tier.resolution,
+            Box::new(tier.buckets.iter().map(move |b| (b.start + half, b.avg))),
+        )
+    }
+
+    /// The (time, value) pairs of the window ending at `now`, from the finest source
+    /// that covers it
+    pub fn samples(&self, now: u64, window: Duration) -> Vec<(u64, f64)> {
+        let start = now.saturating_sub(window.as_millis() as u64);
+        self.source(window)
+            .1
+            .filter(|&(time, _)| time >= start && time <= now)
+            .collect()
+    }
+
+    /// The latest raw sample, unless it's older than `max_age`
+    pub fn latest(&self, now: u64, max_age: Duration) -> Option<f64> {
+        self.raw
+            .back()
+            .filter(|&&(time, _)| now.saturating_sub(time) <= max_age.as_millis() as u64)
+            .map(|&(_, value)| value)
+    }
+
+    /// The window ending at `now` averaged into `points` equal slots, oldest first.
+    /// Slots before the first sample and in gaps of the history are 0, shorter
+    /// stretches without a sample repeat the previous slot.
+    pub fn window(&self, now: u64, window: Duration, points: usize) -> VecDeque<f64> {
+        if points == 0 {
+            return VecDeque::new();
+        }
+        let span = window.as_millis() as u64;
+        let start = now.saturating_sub(span);
+        let slot = (span / points as u64).max(1);
+
+        let (resolution, source) = self.source(window);
+        let max_gap = MAX_GAP.max(resolution * 2).as_millis() as u64;
+
+        // Sum, count and time of the latest sample of each slot
+        let mut slots = vec![(0.0, 0u32, 0u64); points];
+        for (time, value) in source {
+            if time < start {
+                continue;
+            }
+            let idx = (((time - start) / slot) as usize).min(points - 1);
+            slots[idx].0 += value;
+            slots[idx].1 += 1;
+            slots[idx].2 = time;
+        }
+
+        let mut previous: Option<(f64, u64)> = None;
+        slots
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (sum, count, latest))| {
+                if count > 0 {
+                    let value = sum / f64::from(count);
+                    previous = Some((value, latest));
+                    return value;
+                }
+                let slot_start = start + idx as u64 * slot;
+                match previous {
+                    Some((value, time)) if slot_start.saturating_sub(time) <= max_gap => value,
+                    _ => 0.0,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The samples a line or heat chart draws: the live ones, or the window from the history
+pub fn chart_samples<'a>(
+    live: &'a VecDeque<f64>,
+    history: &History,
+    window: ChartWindow,
+) -> Cow<'a, VecDeque<f64>> {
+    match window.duration() {
+        Some(duration) => Cow::Owned(history.window(now_ms(), duration, live.len())),
+        None => Cow::Borrowed(live),
+    }
+}
+
+/// Like chart_samples(), for the counts of network and disks. They keep more live
+/// samples than the chart shows, so the window is spread over `points`. The history
+/// is per second, `scale` converts it to the per update counts of the live samples.
+pub fn chart_samples_u64<'a>(
+    live: &'a VecDeque<u64>,
+    history: &History,
+    window: ChartWindow,
+    points: usize,
+    scale: f64,
+) -> Cow<'a, VecDeque<u64>> {
+    match window.duration() {
+        Some(duration) => Cow::Owned(
+            history
+                .window(now_ms(), duration, points)
+                .into_iter()
+                .map(|v| (v * scale).round() as u64)
+                .collect(),
+        ),
+        None => Cow::Borrowed(live),
+    }
+}
+
+/// The chart window setting shared by all sensor items with line or heat charts
+pub fn window_ui<'a>(
+    device: DeviceKind,
+    id: Option<String>,
+    window: ChartWindow,
+) -> Element<'a, Message> {
+    widget::settings::item(
+        fl!("chart-window"),
+        widget::dropdown(&*WINDOW_OPTIONS, Some(window.into()), move |m| {
+            Message::ChartWindowChanged(device, id.clone(), m.into())
+        })
+        .width(120),
+    )
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // On a 10 minute boundary, like the intervals of all tiers
+    const START: u64 = 1_700_000_400_000;
+
+    fn secs(s: u64) -> u64 {
+        START + s * 1000
+    }
+
+    #[test]
+    fn tiers_aggregate() {
+        let mut history = History::default();
+        for s in 0..20 {
+            history.push(secs(s), s as f64);
+        }
+
+        let tier = &history.tiers[0];
+        assert_eq!(tier.buckets.len(), 2);
+        assert_eq!(tier.buckets[0].start, secs(0));
+        assert_eq!(tier.buckets[0].min, 0.0);
+        assert_eq!(tier.buckets[0].max, 9.0);
+        assert_eq!(tier.buckets[0].avg, 4.5);
+        assert_eq!(tier.buckets[1].count, 10);
+        assert_eq!(history.tiers[1].buckets.len(), 1);
+        assert_eq!(history.tiers[1].buckets[0].avg, 9.5);
+    }
+
+    #[test]
+    fn retention_drops_old_samples() {
+        let mut history = History::default();
+        for s in (0..=7 * 3600).step_by(5) {
+            history.push(secs(s), 1.0);
+        }
+
+        assert_eq!(history.raw.front().unwrap().0, secs(7 * 3600 - 600));
+        let oldest_10s = history.tiers[0].buckets.front().unwrap().start;
+        assert_eq!(oldest_10s, secs(3600));
+        assert_eq!(history.tiers[1].buckets.front().unwrap().start, secs(0));
+    }
+
+    #[test]
+    fn window_averages_slots() {
+        let mut history = History::default();
+        for s in 0..300 {
+            history.push(secs(s), if s < 150 { 10.0 } else { 20.0 });
+        }
+
+        let samples = history.window(secs(299), Duration::from_secs(300), 10);
+        assert_eq!(samples.len(), 10);
+        assert_eq!(samples[0], 10.0);
+        assert_eq!(samples[9], 20.0);
+    }
+
+    #[test]
+    fn window_uses_aggregates_for_hours() {
+        let mut history = History::default();
+        for s in (0..3 * 3600).step_by(2) {
+            history.push(secs(s), 50.0);
+        }
+
+        // Before the history starts
+        let samples = history.window(secs(3 * 3600), Duration::from_secs(6 * 3600), 6);
+        assert_eq!(samples, VecDeque::from([0.0, 0.0, 0.0, 50.0, 50.0, 50.0]));
+    }
+
+    #[test]
+    fn window_leaves_gaps_empty() {
+        let mut history = History::default();
+        for s in 0..60 {
+            history.push(secs(s), 30.0);
+        }
+        // The applet didn't run for 49 minutes
+        for s in 3000..3060 {
+            history.push(secs(s), 60.0);
+        }
+
+        let samples = history.window(secs(3600), Duration::from_secs(3600), 6);
+        assert_eq!(samples, VecDeque::from([30.0, 0.0, 0.0, 0.0, 0.0, 60.0]));
+    }
+
+    #[test]
+    fn prune_drops_expired_and_future() {
+        let mut history = History::default();
+        history.push(secs(0), 1.0);
+        history.push(secs(2 * 3600), 2.0);
+
+        let mut future = history.clone();
+        future.prune(secs(3600));
+        assert!(future.raw.is_empty());
+        assert_eq!(future.tiers[0].buckets.len(), 1);
+        assert_eq!(future.tiers[0].buckets[0].start, secs(0));
+        assert_eq!(future.tiers[2].buckets.len(), 1);
+
+        history.prune(secs(7 * 3600));
+        assert!(history.raw.is_empty());
+        assert_eq!(history.tiers[0].buckets.len(), 1);
+        assert_eq!(history.tiers[0].buckets[0].start, secs(2 * 3600));
+        assert_eq!(history.tiers[2].buckets.len(), 2);
+    }
+
+    #[test]
+    fn clock_step_back_keeps_earlier_samples() {
+        let mut history = History::default();
+        for s in 0..120 {
+            history.push(secs(s), 1.0);
+        }
+        // Back by 30 s, into the 10 s interval starting at 90 s
+        history.push(secs(95), 2.0);
+
+        assert_eq!(history.raw.len(), 97);
+        assert_eq!(history.raw.back(), Some(&(secs(95), 2.0)));
+        let tier = &history.tiers[0];
+        assert_eq!(tier.buckets.len(), 10);
+        assert_eq!(tier.buckets[9].start, secs(90));
+        assert_eq!(tier.buckets[9].count, 11);
+        assert_eq!(history.tiers[1].buckets.len(), 2);
+
+        history.push(secs(96), 3.0);
+        assert_eq!(history.raw.back(), Some(&(secs(96), 3.0)));
+    }
+
+    #[test]
+    fn samples_of_window() {
+        let mut history = History::default();
+        for s in 0..1200 {
+            history.push(secs(s), s as f64);
+        }
+
+        let raw = history.samples(secs(1199), Duration::from_secs(60));
+        assert_eq!(raw.len(), 61);
+        assert_eq!(raw[0], (secs(1139), 1139.0));
+
+        // Beyond the raw samples, 10 s aggregates at the middle of their interval
+        let aggregated = history.samples(secs(1199), Duration::from_secs(1200));
+        assert_eq!(aggregated.len(), 120);
+        assert_eq!(aggregated[0], (secs(5), 4.5));
+
+        assert_eq!(
+            history.latest(secs(1200), Duration::from_secs(5)),
+            Some(1199.0)
+        );
+        assert_eq!(history.latest(secs(1300), Duration::from_secs(5)), None);
+    }
+}
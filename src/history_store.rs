@@ -0,0 +1,327 @@
+//! Snapshots of the aggregated sensor history in $XDG_STATE_HOME/minimon, so the
+//! longer chart windows survive restarts of the panel and logging out.
+//!
+//! The file is little endian binary: the magic and format version, then per series
+//! its key, unit and the buckets of each tier. Raw samples aren't kept, they cover only
+//! the last minutes.
+
+use log::{error, info, warn};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
+
+use crate::history::{Aggregate, History, Series};
+
+const MAGIC: &[u8; 4] = b"MMHS";
+// Bump when the layout changes, files of other versions are ignored
+const VERSION: u16 = 2;
+
+/// How often the history is written
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+// Bounds that keep a damaged or foreign file from using lots of memory
+const MAX_SERIES: usize = 256;
+const MAX_KEY_LEN: usize = 256;
+
+/// A series read back from a snapshot
+#[derive(Debug, Clone)]
+pub struct Saved {
+    pub unit: String,
+    pub history: History,
+}
+
+/// $XDG_STATE_HOME/minimon/<app_id>.history, or in ~/.local/state if it isn't set.
+/// Panel and dock each run an instance with its own file.
+pub fn path(app_id: &str) -> Option<PathBuf> {
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/state")))?;
+    Some(state.join("minimon").join(format!("{app_id}.history")))
+}
+
+/// Write the aggregates of all series, replacing the previous snapshot atomically
+pub fn save(path: &Path, series: &[Series]) -> io::Result<()> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(MAGIC);
+    buf.extend_from_slice(&VERSION.to_le_bytes());
+
+    let series: Vec<_> = series
+        .iter()
+        .filter(|s| s.name.len() <= MAX_KEY_LEN && s.unit.len() <= usize::from(u8::MAX))
+        .take(MAX_SERIES)
+        .collect();
+    buf.extend_from_slice(&(series.len() as u16).to_le_bytes());
+    for s in series {
+        buf.extend_from_slice(&(s.name.len() as u16).to_le_bytes());
+        buf.extend_from_slice(s.name.as_bytes());
+        buf.push(s.unit.len() as u8);
+        buf.extend_from_slice(s.unit.as_bytes());
+
+        for tier in &s.history.tiers {
+            let resolution = tier.resolution.as_millis() as u64;
+            let first = tier.buckets.front().map_or(0, |b| b.start);
+            buf.extend_from_slice(&(resolution as u32).to_le_bytes());
+            buf.extend_from_slice(&(tier.buckets.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&first.to_le_bytes());
+            // Starts are stored as intervals since the first, values as f32
+            for b in &tier.buckets {
+                buf.extend_from_slice(&(((b.start - first) / resolution) as u32).to_le_bytes());
+                buf.extend_from_slice(&(b.min as f32).to_le_bytes());
+                buf.extend_from_slice(&(b.avg as f32).to_le_bytes());
+                buf.extend_from_slice(&(b.max as f32).to_le_bytes());
+                buf.extend_from_slice(&(b.count.min(u32::from(u16::MAX)) as u16).to_le_bytes());
+            }
+        }
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(&buf)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+/// Copies of the series taken on the UI thread, for the writer thread
+pub struct Snapshot(Vec<(String, &'static str, History)>);
+
+impl Snapshot {
+    pub fn new(series: &[Series]) -> Self {
+        Snapshot(
+            series
+                .iter()
+                .map(|s| (s.name.clone(), s.unit, s.history.clone()))
+                .collect(),
+        )
+    }
+}
+
+/// Writes snapshots from a worker thread, as encoding and syncing the file would
+/// stall the panel. The thread starts with the first save.
+#[derive(Default)]
+pub struct BackgroundSaver {
+    snapshots: Option<Sender<(PathBuf, Snapshot)>>,
+}
+
+impl BackgroundSaver {
+    /// Queue the snapshot to replace the one at `path`
+    pub fn save(&mut self, path: PathBuf, snapshot: Snapshot) {
+        let snapshots = self.snapshots.get_or_insert_with(|| {
+            let (sender, snapshots) = mpsc::channel::<(PathBuf, Snapshot)>();
+            std::thread::spawn(move || {
+                for (path, Snapshot(mut copies)) in snapshots {
+                    let series: Vec<Series> = copies
+                        .iter_mut()
+                        .map(|(name, unit, history)| Series::new(name.clone(), unit, history))
+                        .collect();
+                    if let Err(e) = save(&path, &series) {
+                        error!("Failed to save history to {path:?}: {e}");
+                    }
+                }
+            });
+            sender
+        });
+        if snapshots.send((path, snapshot)).is_err() {
+            warn!("History writer stopped, history not saved");
+        }
+    }
+}
+
+/// Read a snapshot. Aggregates past their retention, from before the gap while
+/// the applet wasn't running, are dropped.
+pub fn load(path: &Path, now: u64) -> io::Result<BTreeMap<String, Saved>> {
+    let data = fs::read(path)?;
+    let mut r = data.as_slice();
+
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a history file"));
+    }
+    let version = read_u16(&mut r)?;
+    if version != VERSION {
+        return Err(invalid(&format!("unsupported version {version}")));
+    }
+
+    let count = usize::from(read_u16(&mut r)?);
+    if count > MAX_SERIES {
+        return Err(invalid("too many series"));
+    }
+    let mut series = BTreeMap::new();
+    for _ in 0..count {
+        let len = usize::from(read_u16(&mut r)?);
+        if len > MAX_KEY_LEN {
+            return Err(invalid("key too long"));
+        }
+        let key = read_string(&mut r, len)?;
+        let len = usize::from(read_u8(&mut r)?);
+        let unit = read_string(&mut r, len)?;
+
+        let mut history = History::default();
+        for tier in &mut history.tiers {
+            let resolution = u64::from(read_u32(&mut r)?);
+            let buckets = read_u32(&mut r)? as usize;
+            let first = read_u64(&mut r)?;
+            let max_buckets = tier.retention.as_secs() / tier.resolution.as_secs() + 1;
+            if resolution != tier.resolution.as_millis() as u64 || buckets as u64 > max_buckets {
+                return Err(invalid("tier doesn't match"));
+            }
+
+            tier.buckets = VecDeque::with_capacity(buckets);
+            for _ in 0..buckets {
+                let offset = u64::from(read_u32(&mut r)?);
+                let min = f64::from(read_f32(&mut r)?);
+                let avg = f64::from(read_f32(&mut r)?);
+                let max = f64::from(read_f32(&mut r)?);
+                let count = u32::from(read_u16(&mut r)?);
+                tier.buckets.push_back(Aggregate {
+                    start: first + offset * resolution,
+                    min,
+                    avg,
+                    max,
+                    count,
+                });
+            }
+        }
+        history.prune(now);
+        series.insert(key, Saved { unit, history });
+    }
+
+    info!("Loaded history of {} series from {path:?}", series.len());
+    Ok(series)
+}
+
+/// Load the snapshot of the instance, empty if there is none or it can't be read
+pub fn load_or_default(app_id: &str, now: u64) -> BTreeMap<String, Saved> {
+    let Some(path) = path(app_id) else {
+        return BTreeMap::new();
+    };
+    match load(&path, now) {
+        Ok(series) => series,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+        Err(e) => {
+            warn!("Ignoring history file {path:?}: {e}");
+            BTreeMap::new()
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn read_string(r: &mut &[u8], len: usize) -> io::Result<String> {
+    let mut bytes = vec![0; len];
+    r.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid("string not UTF-8"))
+}
+
+fn read_u8(r: &mut &[u8]) -> io::Result<u8> {
+    let mut b = [0; 1];
+    r.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn read_u16(r: &mut &[u8]) -> io::Result<u16> {
+    let mut b = [0; 2];
+    r.read_exact(&mut b)?;
+    Ok(u16::from_le_bytes(b))
+}
+
+fn read_u32(r: &mut &[u8]) -> io::Result<u32> {
+    let mut b = [0; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u64(r: &mut &[u8]) -> io::Result<u64> {
+    let mut b = [0; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+fn read_f32(r: &mut &[u8]) -> io::Result<f32> {
+    let mut b = [0; 4];
+    r.read_exact(&mut b)?;
+    Ok(f32::from_le_bytes(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: u64 = 1_700_000_400_000;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("minimon-history-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut history = History::default();
+        for s in 0..3600 {
+            history.push(START + s * 1000, (s % 100) as f64);
+        }
+        let dir = temp_dir("roundtrip");
+        let path = dir.join("panel.history");
+        let mut saved = history.clone();
+        save(&path, &[Series::new("cpu:load", "%", &mut saved)]).unwrap();
+
+        // Restarted an hour later
+        let loaded = load(&path, START + 7200 * 1000).unwrap();
+        assert_eq!(loaded["cpu:load"].unit, "%");
+        let restored = &loaded["cpu:load"].history;
+        assert!(restored.raw.is_empty());
+        for (tier, original) in restored.tiers.iter().zip(&history.tiers) {
+            assert_eq!(tier.buckets.len(), original.buckets.len());
+            assert_eq!(tier.buckets[5].start, original.buckets[5].start);
+            assert_eq!(
+                tier.buckets[5].avg,
+                f64::from(original.buckets[5].avg as f32)
+            );
+            assert_eq!(tier.buckets[5].count, original.buckets[5].count);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let dir = temp_dir("version");
+        let path = dir.join("panel.history");
+        save(&path, &[]).unwrap();
+        let mut data = fs::read(&path).unwrap();
+        data[4] = 99;
+        fs::write(&path, &data).unwrap();
+
+        let err = load(&path, START).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saves_in_background() {
+        let mut history = History::default();
+        history.push(START, 42.0);
+        let dir = temp_dir("background");
+        let path = dir.join("panel.history");
+
+        let mut saver = BackgroundSaver::default();
+        let snapshot = Snapshot::new(&[Series::new("cpu:load", "%", &mut history)]);
+        saver.save(path.clone(), snapshot);
+
+        let loaded = (0..100)
+            .find_map(|_| {
+                std::thread::sleep(Duration::from_millis(20));
+                load(&path, START).ok()
+            })
+            .unwrap();
+        assert_eq!(loaded["cpu:load"].unit, "%");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,21 +2,36 @@
 
 use app::Minimon;
 
+mod alert_commands;
+mod alerts;
 mod app;
+mod barchart;
+mod color_stops;
 mod colorpicker;
 mod config;
+mod export;
+mod history;
+mod history_store;
 mod i18n;
+mod metrics_service;
+mod notifications;
+mod prometheus;
 mod sensors;
+mod snapshot;
 mod svg_graph;
-mod barchart;
 mod system_monitors;
+mod trace;
 
 #[cfg(feature = "lyon_charts")]
 mod charts;
 
+#[cfg(test)]
+mod test_bus;
+
 use chrono::Local;
 use log::info;
 use std::io;
+use std::path::Path;
 
 fn setup_logger() -> Result<(), Box<dyn std::error::Error>> {
     #[cfg(debug_assertions)]
@@ -79,9 +94,92 @@ fn setup_logger() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+// Write the saved history of every instance, which the running applets save every
+// few minutes
+fn export_history(path: &Path) -> io::Result<usize> {
+    let now = history::now_ms();
+    let saved: Vec<_> = app::APP_IDS
+        .iter()
+        .map(|id| {
+            (
+                export::instance(id),
+                history_store::load_or_default(id, now),
+            )
+        })
+        .collect();
+    let sources: Vec<export::Source> = saved
+        .iter()
+        .flat_map(|(instance, series)| {
+            series.iter().map(move |(key, s)| export::Source {
+                instance,
+                key,
+                unit: &s.unit,
+                history: &s.history,
+            })
+        })
+        .collect();
+    export::write_file(path, &sources)
+}
+
 fn main() -> cosmic::iced::Result {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("--export-history") {
+        let Some(path) = args.get(1) else {
+            eprintln!(
+                "Usage: cosmic-applet-minimon --export-history <file.csv|file.jsonl>\n\
+                 Exports the history the applets last saved, every {} minutes and without the\n\
+                 raw samples. The export button in the popup has the latest ones too.",
+                history_store::SAVE_INTERVAL.as_secs() / 60
+            );
+            std::process::exit(2);
+        };
+        match export_history(Path::new(path)) {
+            Ok(rows) => println!("Exported {rows} rows to {path}"),
+            Err(e) => {
+                eprintln!("Failed to export history to {path}: {e}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if matches!(
+        args.first().map(String::as_str),
+        Some("--snapshot" | "--watch")
+    ) {
+        let options = snapshot::Options::parse(&args).unwrap_or_else(|e| {
+            eprintln!("{e}\n{}", snapshot::USAGE);
+            std::process::exit(2);
+        });
+        if let Err(e) = snapshot::run(&options) {
+            eprintln!("Failed to print the readings: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     setup_logger().expect("Failed to initialize logger");
 
+    if args.first().map(String::as_str) == Some("--replay") {
+        let Some(path) = args.get(1) else {
+            eprintln!("Usage: cosmic-applet-minimon --replay <trace>");
+            std::process::exit(2);
+        };
+        if let Err(e) = trace::replay(Path::new(path)) {
+            eprintln!("Failed to replay {path}: {e}");
+            std::process::exit(1);
+        }
+    }
+    if args.first().map(String::as_str) == Some("--record") {
+        let Some(path) = args.get(1) else {
+            eprintln!("Usage: cosmic-applet-minimon --record <trace>");
+            std::process::exit(2);
+        };
+        if let Err(e) = trace::record(Path::new(path)) {
+            eprintln!("Failed to record to {path}: {e}");
+            std::process::exit(1);
+        }
+    }
+
     #[cfg(not(debug_assertions))]
     println!(
         "In Release builds use 'journalctl SYSLOG_IDENTIFIER=cosmic-applet-minimon' to see logs"
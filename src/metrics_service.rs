@@ -0,0 +1,315 @@
+//! The io.github.cosmic_utils.Minimon interface on the session bus, so other tools can
+//! read what the applet samples.
+//!
+//! Properties hold the latest value and unit of every sampled sensor item, keyed like
+//! the history, e.g. "cpu:load" or "gpu:<id>:temp". `MetricsChanged` is emitted after
+//! every update and `GetHistory(sensor, seconds)` returns the (ms since the epoch,
+//! value) pairs of the recorded history.
+//!
+//! The histories belong to the sensors, so method calls are handed to the applet,
+//! which answers them on its next update. The call waits without blocking the other
+//! calls on the connection.
+
+use log::{info, warn};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+use zbus::blocking::Connection;
+use zbus::fdo::RequestNameFlags;
+use zbus::object_server::SignalEmitter;
+
+pub const BUS_NAME: &str = "io.github.cosmic_utils.Minimon";
+pub const OBJECT_PATH: &str = "/io/github/cosmic_utils/Minimon";
+
+/// The latest value of a sensor item
+pub struct Metric {
+    pub key: String,
+    pub unit: &'static str,
+    pub value: f64,
+}
+
+type Samples = Vec<(u64, f64)>;
+
+// Where the applet leaves its answer to a GetHistory call
+#[derive(Default)]
+struct ReplySlot {
+    // None if the request was dropped unanswered, Some(None) for an unknown sensor
+    answer: Option<Option<Samples>>,
+    done: bool,
+    waker: Option<Waker>,
+}
+
+/// A GetHistory call waiting for the applet
+pub struct HistoryRequest {
+    pub sensor: String,
+    pub window: Duration,
+    slot: Arc<Mutex<ReplySlot>>,
+}
+
+impl HistoryRequest {
+    /// Answer with the samples, None if there is no such sensor
+    pub fn reply(self, samples: Option<Samples>) {
+        if let Ok(mut slot) = self.slot.lock() {
+            slot.answer = Some(samples);
+        }
+    }
+}
+
+// Answered or not, the call completes once the applet is done with the request
+impl Drop for HistoryRequest {
+    fn drop(&mut self) {
+        if let Ok(mut slot) = self.slot.lock() {
+            slot.done = true;
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// Resolves when the HistoryRequest sharing the slot is dropped
+struct Reply(Arc<Mutex<ReplySlot>>);
+
+impl Future for Reply {
+    type Output = Option<Option<Samples>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let Ok(mut slot) = self.0.lock() else {
+            return Poll::Ready(None);
+        };
+        if slot.done {
+            Poll::Ready(slot.answer.take())
+        } else {
+            slot.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+#[derive(Default)]
+struct Latest {
+    values: HashMap<String, f64>,
+    units: HashMap<String, String>,
+}
+
+struct Interface {
+    instance: String,
+    latest: Arc<Mutex<Latest>>,
+    requests: Sender<HistoryRequest>,
+}
+
+#[zbus::interface(name = "io.github.cosmic_utils.Minimon")]
+impl Interface {
+    /// The panel type of the applet instance: panel, dock or other
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn instance(&self) -> String {
+        self.instance.clone()
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn metrics(&self) -> HashMap<String, f64> {
+        self.latest
+            .lock()
+            .map(|l| l.values.clone())
+            .unwrap_or_default()
+    }
+
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn units(&self) -> HashMap<String, String> {
+        self.latest
+            .lock()
+            .map(|l| l.units.clone())
+            .unwrap_or_default()
+    }
+
+    // Answered on the applet's next update, at most a refresh interval later
+    async fn get_history(&self, sensor: String, seconds: u32) -> zbus::fdo::Result<Samples> {
+        let slot = Arc::new(Mutex::new(ReplySlot::default()));
+        let request = HistoryRequest {
+            sensor: sensor.clone(),
+            window: Duration::from_secs(u64::from(seconds)),
+            slot: slot.clone(),
+        };
+        self.requests
+            .send(request)
+            .map_err(|_| zbus::fdo::Error::Failed(String::from("applet stopped")))?;
+
+        match Reply(slot).await {
+            Some(Some(samples)) => Ok(samples),
+            Some(None) => Err(zbus::fdo::Error::InvalidArgs(format!(
+                "unknown sensor {sensor}"
+            ))),
+            None => Err(zbus::fdo::Error::Failed(String::from(
+                "applet didn't answer",
+            ))),
+        }
+    }
+
+    #[zbus(signal)]
+    async fn metrics_changed(
+        emitter: &SignalEmitter<'_>,
+        metrics: HashMap<String, f64>,
+    ) -> zbus::Result<()>;
+}
+
+/// Serves the interface for one applet instance
+pub struct MetricsService {
+    connection: Connection,
+    latest: Arc<Mutex<Latest>>,
+    requests: Receiver<HistoryRequest>,
+}
+
+impl MetricsService {
+    /// Serve on the session bus. The first instance gets the bus name, the others
+    /// wait in its queue and are reachable by their unique name meanwhile.
+    pub fn start(instance: &str) -> zbus::Result<Self> {
+        Self::with_connection(Connection::session()?, instance)
+    }
+
+    /// Serve on an existing connection, for a private bus
+    pub fn with_connection(connection: Connection, instance: &str) -> zbus::Result<Self> {
+        let latest = Arc::new(Mutex::new(Latest::default()));
+        let (sender, requests) = mpsc::channel();
+        connection.object_server().at(
+            OBJECT_PATH,
+            Interface {
+                instance: instance.to_string(),
+                latest: latest.clone(),
+                requests: sender,
+            },
+        )?;
+
+        let reply = connection
+            .request_name_with_flags(BUS_NAME, RequestNameFlags::AllowReplacement.into())?;
+        info!("Serving {BUS_NAME} at {OBJECT_PATH}: {reply:?}");
+
+        Ok(MetricsService {
+            connection,
+            latest,
+            requests,
+        })
+    }
+
+    /// Set the properties and emit MetricsChanged
+    pub fn publish(&self, metrics: &[Metric]) {
+        let values: HashMap<String, f64> =
+            metrics.iter().map(|m| (m.key.clone(), m.value)).collect();
+        if let Ok(mut latest) = self.latest.lock() {
+            latest.values = values.clone();
+            latest.units = metrics
+                .iter()
+                .map(|m| (m.key.clone(), m.unit.to_string()))
+                .collect();
+        }
+
+        let result = SignalEmitter::new(self.connection.inner(), OBJECT_PATH)
+            .and_then(|emitter| zbus::block_on(Interface::metrics_changed(&emitter, values)));
+        if let Err(e) = result {
+            warn!("Failed to emit MetricsChanged: {e}");
+        }
+    }
+
+    /// GetHistory calls received since the last update, never blocks
+    pub fn requests(&self) -> Vec<HistoryRequest> {
+        self.requests.try_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus::PrivateBus;
+    use zbus::blocking::connection::Builder;
+
+    fn proxy(connection: &Connection) -> zbus::blocking::Proxy<'_> {
+        zbus::blocking::Proxy::new(connection, BUS_NAME, OBJECT_PATH, BUS_NAME).unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon, run with --include-ignored"]
+    fn properties_follow_publish() {
+        let bus = PrivateBus::start();
+        let service = MetricsService::with_connection(bus.connect(), "panel").unwrap();
+        service.publish(&[Metric {
+            key: String::from("cpu:load"),
+            unit: "%",
+            value: 42.5,
+        }]);
+
+        let client = bus.connect();
+        let proxy = proxy(&client);
+        let instance: String = proxy.get_property("Instance").unwrap();
+        let metrics: HashMap<String, f64> = proxy.get_property("Metrics").unwrap();
+        let units: HashMap<String, String> = proxy.get_property("Units").unwrap();
+        assert_eq!(instance, "panel");
+        assert_eq!(metrics.get("cpu:load"), Some(&42.5));
+        assert_eq!(units.get("cpu:load").map(String::as_str), Some("%"));
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon, run with --include-ignored"]
+    fn history_is_answered_by_the_applet() {
+        let bus = PrivateBus::start();
+        let service = MetricsService::with_connection(bus.connect(), "panel").unwrap();
+
+        let address = bus.address.clone();
+        let caller = std::thread::spawn(move || {
+            let client = Builder::address(address.as_str()).unwrap().build().unwrap();
+            let proxy = proxy(&client);
+            let known: Vec<(u64, f64)> = proxy.call("GetHistory", &("cpu:load", 60u32)).unwrap();
+            let unknown = proxy.call::<_, _, Vec<(u64, f64)>>("GetHistory", &("nope", 60u32));
+            (known, unknown.is_err())
+        });
+
+        // The applet's updates
+        let mut answered = 0;
+        while answered < 2 {
+            for request in service.requests() {
+                if request.sensor == "cpu:load" {
+                    assert_eq!(request.window, Duration::from_secs(60));
+                    request.reply(Some(vec![(1000, 1.0), (2000, 2.0)]));
+                } else {
+                    request.reply(None);
+                }
+                answered += 1;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let (known, unknown_failed) = caller.join().unwrap();
+        assert_eq!(known, vec![(1000, 1.0), (2000, 2.0)]);
+        assert!(unknown_failed);
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon, run with --include-ignored"]
+    fn pending_history_does_not_block_properties() {
+        let bus = PrivateBus::start();
+        let service = MetricsService::with_connection(bus.connect(), "panel").unwrap();
+
+        let address = bus.address.clone();
+        let caller = std::thread::spawn(move || {
+            let client = Builder::address(address.as_str()).unwrap().build().unwrap();
+            proxy(&client).call::<_, _, Vec<(u64, f64)>>("GetHistory", &("cpu:load", 60u32))
+        });
+        let request = loop {
+            if let Some(request) = service.requests().pop() {
+                break request;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        };
+
+        // The applet hasn't answered yet
+        let client = bus.connect();
+        let instance: String = proxy(&client).get_property("Instance").unwrap();
+        assert_eq!(instance, "panel");
+
+        // Dropping the request unanswered fails the call instead of leaving it waiting
+        drop(request);
+        assert!(caller.join().unwrap().is_err());
+    }
+}
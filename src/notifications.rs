@@ -0,0 +1,318 @@
+//! Desktop notifications through org.freedesktop.Notifications on the session bus,
+//! see https://specifications.freedesktop.org/notification-spec/latest/
+
+use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use zbus::blocking::Connection;
+
+const APP_NAME: &str = "Minimon";
+const APP_ICON: &str = "io.github.cosmic_utils.minimon-applet";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low = 0,
+    Normal = 1,
+    Critical = 2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+    pub urgency: Urgency,
+    // Id of a previous notification to update in place, 0 for a new one
+    pub replaces_id: u32,
+}
+
+/// Sends notifications, connecting to the session bus on first use
+#[derive(Default)]
+pub struct Notifier {
+    connection: Option<Connection>,
+}
+
+impl Notifier {
+    /// Use an existing connection, for a private bus
+    #[cfg(test)]
+    pub fn with_connection(connection: Connection) -> Self {
+        Notifier {
+            connection: Some(connection),
+        }
+    }
+
+    fn connection(&mut self) -> zbus::Result<&Connection> {
+        if self.connection.is_none() {
+            self.connection = Some(Connection::session()?);
+        }
+        Ok(self.connection.as_ref().expect("connection was just set"))
+    }
+
+    /// Show a notification, returns its id as assigned by the notification server
+    pub fn notify(&mut self, notification: &Notification) -> zbus::Result<u32> {
+        let connection = self.connection()?;
+        let proxy = zbus::blocking::Proxy::new(
+            connection,
+            "org.freedesktop.Notifications",
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+        )?;
+
+        let actions: Vec<&str> = Vec::new();
+        let mut hints: HashMap<&str, zvariant::Value> = HashMap::new();
+        hints.insert("urgency", zvariant::Value::U8(notification.urgency as u8));
+
+        let id: u32 = proxy
+            .call(
+                "Notify",
+                &(
+                    APP_NAME,
+                    notification.replaces_id,
+                    APP_ICON,
+                    notification.summary.as_str(),
+                    notification.body.as_str(),
+                    actions,
+                    hints,
+                    // Server default expiry
+                    -1i32,
+                ),
+            )
+            .inspect_err(|e| warn!("Notify failed: {e}"))?;
+
+        info!(
+            "Notification {id}: {} - {}",
+            notification.summary, notification.body
+        );
+        Ok(id)
+    }
+}
+
+// A Notifier on its own thread, it stops when the requests sender is dropped
+struct Worker {
+    requests: Sender<(String, Notification)>,
+    ids: Receiver<(String, u32)>,
+}
+
+impl Worker {
+    fn spawn(mut notifier: Notifier) -> Worker {
+        let (requests, pending) = mpsc::channel::<(String, Notification)>();
+        let (assigned, ids) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (key, notification) in pending {
+                match notifier.notify(&notification) {
+                    Ok(id) => {
+                        if assigned.send((key, id)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => warn!("Notification for {key} not shown: {e}"),
+                }
+            }
+        });
+        Worker { requests, ids }
+    }
+}
+
+/// Sends notifications from a worker thread, as the D-Bus call blocks until the
+/// notification server answers. The assigned ids come back through `poll()`.
+#[derive(Default)]
+pub struct BackgroundNotifier {
+    worker: Option<Worker>,
+}
+
+impl BackgroundNotifier {
+    /// Send through an existing notifier, for a private bus
+    #[cfg(test)]
+    pub fn with_notifier(notifier: Notifier) -> Self {
+        BackgroundNotifier {
+            worker: Some(Worker::spawn(notifier)),
+        }
+    }
+
+    /// Queue a notification, `key` identifies it in the ids returned by `poll()`
+    pub fn send(&mut self, key: &str, notification: Notification) {
+        let worker = self
+            .worker
+            .get_or_insert_with(|| Worker::spawn(Notifier::default()));
+        if worker
+            .requests
+            .send((key.to_string(), notification))
+            .is_err()
+        {
+            warn!("Notification worker stopped, {key} not shown");
+        }
+    }
+
+    /// Ids assigned to the notifications shown since the last call, by key. Never blocks.
+    pub fn poll(&mut self) -> Vec<(String, u32)> {
+        self.worker
+            .as_ref()
+            .map(|w| w.ids.try_iter().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus::PrivateBus;
+    use std::sync::{Arc, Mutex};
+    use zbus::blocking::connection::Builder;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Received {
+        app_name: String,
+        replaces_id: u32,
+        summary: String,
+        body: String,
+        urgency: Option<u8>,
+    }
+
+    struct MockServer {
+        received: Arc<Mutex<Vec<Received>>>,
+        next_id: u32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl MockServer {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &mut self,
+            app_name: String,
+            replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            body: String,
+            _actions: Vec<String>,
+            hints: HashMap<String, zvariant::OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let urgency = hints.get("urgency").and_then(|v| match &**v {
+                zvariant::Value::U8(u) => Some(*u),
+                _ => None,
+            });
+            self.received.lock().unwrap().push(Received {
+                app_name,
+                replaces_id,
+                summary,
+                body,
+                urgency,
+            });
+
+            if replaces_id != 0 {
+                replaces_id
+            } else {
+                self.next_id += 1;
+                self.next_id
+            }
+        }
+    }
+
+    fn serve(bus: &PrivateBus) -> (Connection, Arc<Mutex<Vec<Received>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let server = MockServer {
+            received: received.clone(),
+            next_id: 0,
+        };
+        let connection = Builder::address(bus.address.as_str())
+            .unwrap()
+            .name("org.freedesktop.Notifications")
+            .unwrap()
+            .serve_at("/org/freedesktop/Notifications", server)
+            .unwrap()
+            .build()
+            .expect("serve mock notification server");
+        (connection, received)
+    }
+
+    fn notification(summary: &str, urgency: Urgency, replaces_id: u32) -> Notification {
+        Notification {
+            summary: summary.to_string(),
+            body: "body".to_string(),
+            urgency,
+            replaces_id,
+        }
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon, run with --include-ignored"]
+    fn notify_reaches_server() {
+        let bus = PrivateBus::start();
+        let (_server, received) = serve(&bus);
+        let mut notifier = Notifier::with_connection(bus.connect());
+
+        let id = notifier
+            .notify(&notification("CPU warning", Urgency::Critical, 0))
+            .unwrap();
+        assert_eq!(id, 1);
+
+        let received = received.lock().unwrap();
+        assert_eq!(
+            *received,
+            vec![Received {
+                app_name: APP_NAME.to_string(),
+                replaces_id: 0,
+                summary: "CPU warning".to_string(),
+                body: "body".to_string(),
+                urgency: Some(2),
+            }]
+        );
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon, run with --include-ignored"]
+    fn notify_replaces_previous() {
+        let bus = PrivateBus::start();
+        let (_server, received) = serve(&bus);
+        let mut notifier = Notifier::with_connection(bus.connect());
+
+        let id = notifier
+            .notify(&notification("warning", Urgency::Normal, 0))
+            .unwrap();
+        let second = notifier
+            .notify(&notification("recovered", Urgency::Low, id))
+            .unwrap();
+        assert_eq!(second, id);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1].replaces_id, id);
+        assert_eq!(received[1].urgency, Some(0));
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon, run with --include-ignored"]
+    fn notify_without_server_fails() {
+        let bus = PrivateBus::start();
+        let mut notifier = Notifier::with_connection(bus.connect());
+
+        assert!(
+            notifier
+                .notify(&notification("nobody listens", Urgency::Normal, 0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    #[ignore = "needs dbus-daemon, run with --include-ignored"]
+    fn background_notifier_returns_ids() {
+        let bus = PrivateBus::start();
+        let (_server, received) = serve(&bus);
+        let mut notifier =
+            BackgroundNotifier::with_notifier(Notifier::with_connection(bus.connect()));
+
+        notifier.send("cpu", notification("CPU warning", Urgency::Normal, 0));
+        notifier.send("gpu", notification("GPU warning", Urgency::Normal, 0));
+
+        let mut ids = Vec::new();
+        for _ in 0..100 {
+            ids.extend(notifier.poll());
+            if ids.len() == 2 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        assert_eq!(ids, [(String::from("cpu"), 1), (String::from("gpu"), 2)]);
+        assert_eq!(received.lock().unwrap().len(), 2);
+        assert!(notifier.poll().is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+//! Opt-in HTTP endpoint on 127.0.0.1 with the current readings in the Prometheus text
+//! format, see https://prometheus.io/docs/instrumenting/exposition_formats/
+//!
+//! The sensors add their gauges to `Metrics` on every update, the listener thread
+//! serves the last rendering of it. `--snapshot` prints the same gauges.
+
+use log::{info, warn};
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Port used when none is configured
+pub const DEFAULT_PORT: u16 = 9719;
+
+const PREFIX: &str = "minimon_";
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+// A scraper that doesn't send its request in time is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+// Bounds the request headers read from a client
+const MAX_REQUEST_LINES: usize = 100;
+
+struct Family {
+    name: String,
+    help: &'static str,
+    samples: Vec<(Labels, f64)>,
+}
+
+/// Label names and values of a sample
+pub type Labels = Vec<(String, String)>;
+
+/// One sample of a gauge, `name` without the minimon_ prefix
+pub struct Sample<'a> {
+    pub name: &'a str,
+    pub help: &'static str,
+    pub labels: &'a [(String, String)],
+    pub value: f64,
+}
+
+/// The gauges of one update
+#[derive(Default)]
+pub struct Metrics {
+    families: Vec<Family>,
+}
+
+impl Metrics {
+    /// Add a sample of the gauge `minimon_<name>`, values that aren't finite are skipped
+    pub fn gauge(&mut self, name: &str, help: &'static str, labels: &[(&str, &str)], value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        let labels: Labels = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        match self.families.iter_mut().find(|f| f.name == name) {
+            Some(family) => family.samples.push((labels, value)),
+            None => self.families.push(Family {
+                name: name.to_string(),
+                help,
+                samples: vec![(labels, value)],
+            }),
+        }
+    }
+
+    /// All samples, grouped by gauge in the order they were added
+    pub fn samples(&self) -> impl Iterator<Item = Sample<'_>> {
+        self.families.iter().flat_map(|family| {
+            family.samples.iter().map(|(labels, value)| Sample {
+                name: &family.name,
+                help: family.help,
+                labels,
+                value: *value,
+            })
+        })
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for family in &self.families {
+            let _ = writeln!(out, "# HELP {PREFIX}{} {}", family.name, family.help);
+            let _ = writeln!(out, "# TYPE {PREFIX}{} gauge", family.name);
+            for (labels, value) in &family.samples {
+                let _ = writeln!(
+                    out,
+                    "{PREFIX}{}{} {value}",
+                    family.name,
+                    render_labels(labels)
+                );
+            }
+        }
+        out
+    }
+}
+
+/// `{name="value",...}`, empty without labels
+pub fn render_labels(labels: &[(String, String)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The listener, stopped when dropped
+pub struct Exporter {
+    port: u16,
+    body: Arc<Mutex<String>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Exporter {
+    /// Listen on 127.0.0.1:`port`, 0 for any free port
+    pub fn start(port: u16) -> io::Result<Exporter> {
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))?;
+        let port = listener.local_addr()?.port();
+        let body = Arc::new(Mutex::new(String::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_body = body.clone();
+        let thread_stop = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name(String::from("prometheus"))
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if thread_stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    match stream {
+                        Ok(stream) => {
+                            if let Err(e) = serve(stream, &thread_body) {
+                                warn!("Prometheus request failed: {e}");
+                            }
+                        }
+                        Err(e) => warn!("Prometheus accept failed: {e}"),
+                    }
+                }
+            })?;
+
+        info!("Serving Prometheus metrics on http://127.0.0.1:{port}/metrics");
+        Ok(Exporter {
+            port,
+            body,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Serve `metrics` from now on
+    pub fn update(&self, metrics: &Metrics) {
+        let rendered = metrics.render();
+        if let Ok(mut body) = self.body.lock() {
+            *body = rendered;
+        }
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Wake the listener blocked in accept
+        let _ = TcpStream::connect((Ipv4Addr::LOCALHOST, self.port));
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        info!("Stopped serving Prometheus metrics on port {}", self.port);
+    }
+}
+
+fn serve(stream: TcpStream, body: &Mutex<String>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Skip the headers, the request has no body
+    let mut line = String::new();
+    for _ in 0..MAX_REQUEST_LINES {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+    let path = path.map(|p| p.split('?').next().unwrap_or(p));
+
+    let mut stream = &stream;
+    match (method, path) {
+        (Some("GET"), Some("/metrics" | "/")) => {
+            let body = body.lock().map(|b| b.clone()).unwrap_or_default();
+            respond(&mut stream, "200 OK", CONTENT_TYPE, &body)
+        }
+        (Some("GET"), _) => respond(&mut stream, "404 Not Found", "text/plain", "Not found\n"),
+        _ => respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            "Only GET is supported\n",
+        ),
+    }
+}
+
+fn respond(out: &mut impl Write, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn get(port: u16, path: &str) -> String {
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn renders_families() {
+        let mut metrics = Metrics::default();
+        metrics.gauge("cpu_usage_percent", "CPU load", &[], 12.5);
+        metrics.gauge(
+            "gpu_temperature_celsius",
+            "GPU temperature",
+            &[("id", "0000:01:00.0"), ("name", "Radeon \"RX\"")],
+            61.0,
+        );
+        metrics.gauge("cpu_usage_percent", "CPU load", &[], f64::NAN);
+        metrics.gauge(
+            "gpu_temperature_celsius",
+            "GPU temperature",
+            &[("id", "1"), ("name", "Arc")],
+            48.0,
+        );
+
+        assert_eq!(
+            metrics.render(),
+            "# HELP minimon_cpu_usage_percent CPU load\n\
+             # TYPE minimon_cpu_usage_percent gauge\n\
+             minimon_cpu_usage_percent 12.5\n\
+             # HELP minimon_gpu_temperature_celsius GPU temperature\n\
+             # TYPE minimon_gpu_temperature_celsius gauge\n\
+             minimon_gpu_temperature_celsius{id=\"0000:01:00.0\",name=\"Radeon \\\"RX\\\"\"} 61\n\
+             minimon_gpu_temperature_celsius{id=\"1\",name=\"Arc\"} 48\n"
+        );
+    }
+
+    #[test]
+    fn serves_metrics() {
+        let exporter = Exporter::start(0).unwrap();
+        let mut metrics = Metrics::default();
+        metrics.gauge("memory_used_bytes", "Used memory", &[], 1024.0);
+        exporter.update(&metrics);
+
+        let response = get(exporter.port(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(CONTENT_TYPE));
+        assert!(response.ends_with("minimon_memory_used_bytes 1024\n"));
+
+        assert!(get(exporter.port(), "/other").starts_with("HTTP/1.1 404"));
+    }
+}
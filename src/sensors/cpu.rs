@@ -1,10 +1,15 @@
 use crate::{
+    alerts,
     barchart::StackedBarSvg,
+    color_stops,
     colorpicker::DemoGraph,
     config::{ChartColors, ChartKind, ColorVariant, CpuConfig, DeviceKind},
     fl,
+    history::{self, History, Series},
+    prometheus::Metrics,
     sensors::INVALID_IMG,
     svg_graph::SvgColors,
+    trace,
 };
 use bounded_vec_deque::BoundedVecDeque;
 use cosmic::{
@@ -25,13 +30,7 @@ use cosmic::{
 
 use crate::app::Message;
 
-use std::{
-    collections::HashMap,
-    fmt::Write,
-    fs::File,
-    io::{BufRead, BufReader},
-    path::Path,
-};
+use std::{collections::HashMap, fmt::Write, fs};
 
 use super::Sensor;
 
@@ -101,6 +100,8 @@ pub struct Cpu {
     prev_core_stats: HashMap<usize, CpuStat>,
     // Total CPU load for the last MAX_SAMPLES updates
     samples_sum: BoundedVecDeque<f64>,
+    // Total CPU load for the chart windows longer than the live samples
+    history: History,
     // CPU load for the last MAX_SAMPLES updates, split into user and system
     samples_split: BoundedVecDeque<CpuLoad>,
     graph_options: Vec<&'static str>,
@@ -216,6 +217,8 @@ impl Sensor for Cpu {
         self.samples_split.push_back(self.total_cpu_load);
         self.samples_sum
             .push_back(self.total_cpu_load.user_pct + self.total_cpu_load.system_pct);
+        self.history
+            .push_now(self.total_cpu_load.user_pct + self.total_cpu_load.system_pct);
     }
 
     fn demo_graph(&self) -> Box<dyn DemoGraph> {
@@ -261,6 +264,9 @@ impl Sensor for Cpu {
         height_hint: u16,
         _width_hint: u16,
     ) -> cosmic::widget::Container<'_, crate::app::Message, Theme, Renderer> {
+        let colors = self
+            .svg_colors
+            .with_stops(&self.config.color_stops, self.alert_value());
         let svg = match self.config.chart {
             ChartKind::Ring => {
                 let latest = self.latest_sample();
@@ -278,9 +284,13 @@ impl Sensor for Cpu {
 
                 let percentage: u8 = latest.round().clamp(0.0, 100.0) as u8;
 
-                crate::svg_graph::ring(&value, percentage, None, &self.svg_colors)
+                crate::svg_graph::ring(&value, percentage, None, &colors)
             }
-            ChartKind::Line => crate::svg_graph::line(&self.samples_sum, 100.0, &self.svg_colors),
+            ChartKind::Line => crate::svg_graph::line(
+                &history::chart_samples(&self.samples_sum, &self.history, self.config.window),
+                100.0,
+                &colors,
+            ),
             ChartKind::StackedBars => {
                 StackedBarSvg::new(self.config.bar_width, height_hint, self.config.bar_spacing)
                     .svg(&self.core_loads, &self.svg_colors)
@@ -415,6 +425,21 @@ impl Sensor for Cpu {
             .align_y(Center)
             .into(),
         );
+        cpu_column.push(alerts::settings_ui(
+            DeviceKind::Cpu,
+            None,
+            &config.alerts,
+            self.alert_unit(),
+        ));
+        cpu_column.push(color_stops::settings_ui(
+            DeviceKind::Cpu,
+            None,
+            &config.color_stops,
+            self.alert_unit(),
+        ));
+        if config.chart == ChartKind::Line {
+            cpu_column.push(history::window_ui(DeviceKind::Cpu, None, config.window));
+        }
 
         cpu_elements.push(Element::from(
             Column::with_children(cpu_column).spacing(cosmic.space_xs()),
@@ -425,6 +450,18 @@ impl Sensor for Cpu {
             .spacing(0)
             .into()
     }
+
+    fn alert_value(&self) -> Option<f64> {
+        self.samples_sum.back().copied()
+    }
+
+    fn alert_unit(&self) -> &'static str {
+        "%"
+    }
+
+    fn histories_mut(&mut self) -> Vec<Series<'_>> {
+        vec![Series::new("load", "%", &mut self.history)]
+    }
 }
 
 impl Cpu {
@@ -464,6 +501,7 @@ impl Cpu {
                 std::iter::repeat_n(0.0, MAX_SAMPLES),
                 MAX_SAMPLES,
             ),
+            history: History::default(),
             samples_split: BoundedVecDeque::from_iter(
                 std::iter::repeat_n(
                     CpuLoad {
@@ -490,18 +528,41 @@ impl Cpu {
         self.core_loads.len()
     }
 
+    pub fn prometheus(&self, metrics: &mut Metrics) {
+        metrics.gauge(
+            "cpu_usage_percent",
+            "CPU load of all cores",
+            &[],
+            self.latest_sample(),
+        );
+        let mut cores: Vec<_> = self.core_loads.iter().collect();
+        cores.sort_by_key(|(core, _)| **core);
+        for (core, load) in cores {
+            metrics.gauge(
+                "cpu_core_usage_percent",
+                "CPU load per core",
+                &[("core", &core.to_string())],
+                load.user_pct + load.system_pct,
+            );
+        }
+    }
+
     fn read_cpu_stats(cpu_stats: &mut HashMap<usize, CpuStat>) {
-        // Open /proc/stat file
-        let Ok(file) = File::open(Path::new("/proc/stat")) else {
+        // Only the cpu lines of /proc/stat, they are what a trace records
+        let Ok(stat) = trace::input("cpu", || {
+            fs::read_to_string("/proc/stat").map(|stat| {
+                stat.lines()
+                    .filter(|line| line.starts_with("cpu"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        }) else {
             return;
         };
 
-        let reader = BufReader::new(file);
         cpu_stats.clear();
 
-        // Read each line from the file
-        for line in reader.lines() {
-            let Ok(line) = line else { continue };
+        for line in stat.lines() {
             // Split line into parts
             let parts: Vec<&str> = line.split_whitespace().collect();
 
@@ -1,9 +1,13 @@
 use crate::{
+    alerts, color_stops,
     colorpicker::DemoGraph,
     config::{ChartColors, ChartKind, ColorVariant, CpuTempConfig, DeviceKind},
     fl,
+    history::{self, History, Series},
+    prometheus::Metrics,
     sensors::INVALID_IMG,
     svg_graph::SvgColors,
+    trace,
 };
 use cosmic::{Element, iced::Alignment::Center, widget::Container};
 
@@ -31,6 +35,7 @@ use std::{
 use std::fs::read_dir;
 use std::io;
 
+use super::thermal::{self, ThermalZone};
 use super::{CpuVariant, Sensor, TempUnit};
 
 const MAX_SAMPLES: usize = 21;
@@ -137,10 +142,27 @@ impl HwmonTemp {
     }
 }
 
+// Where the temperature is read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TempSource {
+    Hwmon,
+    // Index in CpuTemp::zones
+    Zone(usize),
+    // Replayed, see crate::trace
+    Trace,
+}
+
 #[derive(Debug)]
 pub struct CpuTemp {
     hwmon_temp: Option<HwmonTemp>,
+    zones: Vec<ThermalZone>,
+    // Latest reading of every zone, for the settings page
+    zone_temps: Vec<Option<f64>>,
+    source: Option<TempSource>,
+    // "Automatic" followed by the zones
+    zone_options: Vec<&'static str>,
     pub samples: BoundedVecDeque<f64>,
+    history: History,
     graph_options: Vec<&'static str>,
     unit_options: Vec<&'static str>,
     /// colors cached so we don't need to convert to string every time
@@ -166,6 +188,7 @@ impl DemoGraph for CpuTemp {
                 &std::collections::VecDeque::from(DEMO_SAMPLES),
                 100,
                 &self.svg_colors,
+                &self.config.color_stops,
             ),
             ChartKind::StackedBars => {
                 log::error!("StackedBars not supported for CpuTemp");
@@ -204,6 +227,14 @@ impl DemoGraph for CpuTemp {
 impl Sensor for CpuTemp {
     fn update_config(&mut self, config: &dyn Any, _refresh_rate: u32) {
         if let Some(cfg) = config.downcast_ref::<CpuTempConfig>() {
+            let source = self.select_source(cfg.thermal_zone.as_deref());
+            if source != self.source {
+                info!("CpuTemp source {source:?}");
+                self.samples =
+                    BoundedVecDeque::from_iter(std::iter::repeat_n(0.0, MAX_SAMPLES), MAX_SAMPLES);
+                self.history.clear();
+                self.source = source;
+            }
             self.config = cfg.clone();
             self.svg_colors.set_colors(cfg.colors());
         }
@@ -219,13 +250,35 @@ impl Sensor for CpuTemp {
     }
 
     fn update(&mut self) {
-        if let Some(hw) = &self.hwmon_temp {
-            match hw.read_temp() {
-                Ok(temp) => {
-                    self.samples.push_back(f64::from(temp));
-                }
-                Err(e) => info!("Error reading temp data {e:?}"),
+        if !trace::is_replaying() {
+            self.zone_temps = self.zones.iter().map(|z| z.read_temp().ok()).collect();
+        }
+        let Some(source) = self.source else {
+            return;
+        };
+        let temp = trace::input("cputemp", || -> io::Result<f64> {
+            match source {
+                TempSource::Hwmon => self
+                    .hwmon_temp
+                    .as_ref()
+                    .ok_or_else(|| io::Error::other("No hwmon sensor"))?
+                    .read_temp()
+                    .map(f64::from),
+                TempSource::Zone(idx) => self
+                    .zone_temps
+                    .get(idx)
+                    .copied()
+                    .flatten()
+                    .ok_or_else(|| io::Error::other("No thermal zone reading")),
+                TempSource::Trace => Err(io::Error::other("Only read from the trace")),
+            }
+        });
+        match temp {
+            Ok(temp) => {
+                self.samples.push_back(temp);
+                self.history.push_now(temp);
             }
+            Err(e) => info!("Error reading temp data {e:?}"),
         }
     }
 
@@ -239,10 +292,7 @@ impl Sensor for CpuTemp {
     fn chart(
         &self,
     ) -> cosmic::widget::Container<crate::app::Message, cosmic::Theme, cosmic::Renderer> {
-        let mut max: f64 = 100.0;
-        if let Some(hwmon) = &self.hwmon_temp {
-            max = hwmon.crit_temp;
-        }
+        let max = self.max_temp();
         match self.config.kind {
             ChartKind::Ring => {
                 let latest = self.latest_sample();
@@ -280,10 +330,11 @@ impl Sensor for CpuTemp {
         _height_hint: u16,
         _width_hint: u16,
     ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
-        let mut max: f64 = 100.0;
-        if let Some(hwmon) = &self.hwmon_temp {
-            max = hwmon.crit_temp;
-        }
+        let max = self.max_temp();
+        let colors = self
+            .svg_colors
+            .with_stops(&self.config.color_stops, self.alert_value());
+        let samples = history::chart_samples(&self.samples, &self.history, self.config.window);
         let svg = match self.config.chart {
             ChartKind::Ring => {
                 let latest = self.latest_sample();
@@ -296,10 +347,12 @@ impl Sensor for CpuTemp {
 
                 let percentage: u8 = latest.round().clamp(0.0, 100.0) as u8;
 
-                crate::svg_graph::ring(&value, percentage, None, &self.svg_colors)
+                crate::svg_graph::ring(&value, percentage, None, &colors)
+            }
+            ChartKind::Line => crate::svg_graph::line(&samples, max, &colors),
+            ChartKind::Heat => {
+                crate::svg_graph::heat(&samples, max as u64, &colors, &self.config.color_stops)
             }
-            ChartKind::Line => crate::svg_graph::line(&self.samples, max, &self.svg_colors),
-            ChartKind::Heat => crate::svg_graph::heat(&self.samples, max as u64, &self.svg_colors),
             ChartKind::StackedBars => {
                 log::error!("StackedBars not supported for CpuTemp");
                 INVALID_IMG.to_string()
@@ -337,6 +390,13 @@ impl Sensor for CpuTemp {
 
         let selected_graph: Option<usize> = Some(self.graph_kind().into());
         let selected_unit: Option<usize> = Some(self.config.unit.into());
+        let selected_zone: Option<usize> = Some(
+            self.config
+                .thermal_zone
+                .as_ref()
+                .and_then(|k| self.zones.iter().position(|z| &z.key == k))
+                .map_or(0, |idx| idx + 1),
+        );
 
         let config = &self.config;
         let temp_kind = self.graph_kind();
@@ -369,20 +429,74 @@ impl Sensor for CpuTemp {
                         Message::ColorPickerOpen(DeviceKind::CpuTemp, temp_kind, None)
                     ),
                 )
-                .align_y(Center)
+                .align_y(Center),
+                alerts::settings_ui(DeviceKind::CpuTemp, None, &config.alerts, self.alert_unit()),
+                color_stops::settings_ui(
+                    DeviceKind::CpuTemp,
+                    None,
+                    &config.color_stops,
+                    self.alert_unit()
+                ),
+            )
+            .push_maybe(
+                (config.chart != ChartKind::Ring)
+                    .then(|| history::window_ui(DeviceKind::CpuTemp, None, config.window)),
             )
             .spacing(cosmic.space_xs()),
         ));
 
         let mut expl = String::with_capacity(128);
-        if let Some(hw) = &self.hwmon_temp {
-            if hw.cpu == super::CpuVariant::Amd {
-                expl.push_str(&fl!("cpu-temp-amd"));
-            } else {
-                expl.push_str(&fl!("cpu-temp-intel"));
+        match self.source {
+            Some(TempSource::Hwmon) => {
+                if let Some(hw) = &self.hwmon_temp {
+                    if hw.cpu == super::CpuVariant::Amd {
+                        expl.push_str(&fl!("cpu-temp-amd"));
+                    } else {
+                        expl.push_str(&fl!("cpu-temp-intel"));
+                    }
+                }
+            }
+            Some(TempSource::Zone(idx)) => {
+                expl.push_str(&fl!(
+                    "cpu-temp-zone",
+                    zone = self.zones[idx].zone_type.clone()
+                ));
             }
+            Some(TempSource::Trace) | None => {}
         }
 
+        let mut zones = widget::column::with_capacity(self.zones.len()).spacing(cosmic.space_xxs());
+        for (idx, zone) in self.zones.iter().enumerate() {
+            let temp = self
+                .zone_temps
+                .get(idx)
+                .copied()
+                .flatten()
+                .map_or_else(|| String::from("--"), |t| format!("{}C", t.trunc()));
+            zones = zones.push(settings::item(
+                format!("{} ({})", zone.zone_type, zone.name),
+                widget::text::body(if zone.trip_points.is_empty() {
+                    temp
+                } else {
+                    format!("{temp}  {}", zone.trip_points_label())
+                }),
+            ));
+        }
+
+        let zone_settings = (!self.zones.is_empty()).then(|| {
+            column!(
+                settings::item(
+                    fl!("cpu-temp-source"),
+                    widget::dropdown(&self.zone_options, selected_zone, |m| {
+                        Message::SelectCpuTempZone(m)
+                    })
+                ),
+                widget::text::heading(fl!("cpu-temp-zones")),
+                zones,
+            )
+            .spacing(cosmic.space_xs())
+        });
+
         column!(
             Element::from(widget::text::body(expl)),
             Element::from(
@@ -391,9 +505,23 @@ impl Sensor for CpuTemp {
                     .spacing(0)
             )
         )
+        .push_maybe(zone_settings)
         .spacing(10)
         .into()
     }
+
+    fn alert_value(&self) -> Option<f64> {
+        self.is_found().then(|| self.latest_sample())
+    }
+
+    // Always Celcius, whatever unit the label shows
+    fn alert_unit(&self) -> &'static str {
+        "°C"
+    }
+
+    fn histories_mut(&mut self) -> Vec<Series<'_>> {
+        vec![Series::new("temp", "°C", &mut self.history)]
+    }
 }
 
 impl Default for CpuTemp {
@@ -410,28 +538,86 @@ impl Default for CpuTemp {
             Err(e) => info!("CpuTemp:detect: No CPU Temp IF found. {e:?}"),
         }
 
+        let zones = thermal::thermal_zones();
+        let mut zone_options = vec![fl!("cpu-temp-automatic").leak() as &'static str];
+        zone_options.extend(
+            zones
+                .iter()
+                .map(|z| -> &'static str { format!("{} ({})", z.zone_type, z.name).leak() }),
+        );
+
         let mut cpu = CpuTemp {
             hwmon_temp: hwmon,
+            zones,
+            zone_temps: Vec::new(),
+            source: None,
+            zone_options,
             samples: BoundedVecDeque::from_iter(std::iter::repeat_n(0.0, MAX_SAMPLES), MAX_SAMPLES),
+            history: History::default(),
             graph_options: super::GRAPH_OPTIONS_RING_LINE_HEAT.to_vec(),
             svg_colors: SvgColors::new(&ChartColors::default()),
             unit_options: super::UNIT_OPTIONS.to_vec(),
             config: CpuTempConfig::default(),
         };
         cpu.set_colors(&ChartColors::default());
+        cpu.source = cpu.select_source(None);
         cpu
     }
 }
 
 impl CpuTemp {
-    // true if a CPU temperature hwmon path was found
+    // true if a CPU temperature hwmon path or thermal zone was found
     pub fn is_found(&self) -> bool {
-        self.hwmon_temp.is_some()
+        self.source.is_some()
+    }
+
+    // The trace while replaying, else the selected zone if present, else the hwmon sensor,
+    // else the most likely zone
+    fn select_source(&self, zone_key: Option<&str>) -> Option<TempSource> {
+        if trace::is_replaying() {
+            trace::has_input("cputemp").then_some(TempSource::Trace)
+        } else if let Some(zone_key) = zone_key
+            && let Some(idx) = self.zones.iter().position(|z| z.key == zone_key)
+        {
+            Some(TempSource::Zone(idx))
+        } else if self.hwmon_temp.is_some() {
+            Some(TempSource::Hwmon)
+        } else {
+            thermal::find_cpu_zone(&self.zones).map(TempSource::Zone)
+        }
+    }
+
+    /// Zone key for an entry of the source dropdown, None for automatic
+    pub fn zone_choice(&self, index: usize) -> Option<String> {
+        index
+            .checked_sub(1)
+            .and_then(|idx| self.zones.get(idx))
+            .map(|z| z.key.clone())
+    }
+
+    // Top of the chart scale
+    fn max_temp(&self) -> f64 {
+        match self.source {
+            Some(TempSource::Hwmon) => self.hwmon_temp.as_ref().map_or(100.0, |hw| hw.crit_temp),
+            Some(TempSource::Zone(idx)) => self.zones[idx].critical().unwrap_or(100.0),
+            Some(TempSource::Trace) | None => 100.0,
+        }
     }
 
     pub fn latest_sample(&self) -> f64 {
         *self.samples.back().unwrap_or(&0f64)
     }
+
+    pub fn prometheus(&self, metrics: &mut Metrics) {
+        if self.is_found() {
+            metrics.gauge(
+                "cpu_temperature_celsius",
+                "CPU temperature",
+                &[],
+                self.latest_sample(),
+            );
+        }
+    }
 }
 
 use std::fmt;
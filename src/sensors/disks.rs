@@ -3,9 +3,12 @@ use bounded_vec_deque::BoundedVecDeque;
 use sysinfo::{DiskRefreshKind, Disks as DisksInfo};
 
 use crate::{
+    alerts, color_stops,
     colorpicker::DemoGraph,
     config::{ChartColors, ChartKind, ColorVariant, DeviceKind, DisksConfig},
     fl,
+    history::{self, History, Series},
+    prometheus::Metrics,
     svg_graph::SvgColors,
 };
 
@@ -26,8 +29,12 @@ use cosmic::{
 };
 
 use crate::app::Message;
-use crate::config::DisksVariant;
+use crate::config::{DisksMetric, DisksVariant};
 use std::any::Any;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
 
 use super::Sensor;
 
@@ -35,8 +42,19 @@ const MAX_SAMPLES: usize = 30;
 const GRAPH_SAMPLES: usize = 21;
 const UNITS_SHORT: [&str; 5] = ["B", "K", "M", "G", "T"];
 const UNITS_LONG: [&str; 5] = ["B/s", "KB/s", "MB/s", "GB/s", "TB/s"];
+const UNITS_IOPS_SHORT: [&str; 5] = ["", "K", "M", "G", "T"];
+const UNITS_IOPS_LONG: [&str; 5] = [" IOPS", "K IOPS", "M IOPS", "G IOPS", "T IOPS"];
 use std::sync::LazyLock;
 
+pub static METRIC_OPTIONS: LazyLock<[&'static str; 4]> = LazyLock::new(|| {
+    [
+        fl!("disks-metric-throughput").leak(),
+        fl!("disks-metric-iops").leak(),
+        fl!("disks-metric-latency").leak(),
+        fl!("disks-metric-utilization").leak(),
+    ]
+});
+
 pub static COLOR_CHOICES_COMBINED: LazyLock<[(&'static str, ColorVariant); 4]> =
     LazyLock::new(|| {
         [
@@ -69,11 +87,125 @@ pub enum UnitVariant {
     Long,
 }
 
+/// Counters for one block device from /proc/diskstats
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DiskStat {
+    pub reads: u64,
+    pub read_ms: u64,
+    pub writes: u64,
+    pub write_ms: u64,
+    pub io_ticks_ms: u64,
+}
+
+impl DiskStat {
+    /// Read counters for physical block devices, partitions and virtual devices are skipped
+    pub fn read_all() -> HashMap<String, DiskStat> {
+        let Ok(content) = fs::read_to_string("/proc/diskstats") else {
+            return HashMap::new();
+        };
+        // Only whole disks backed by a real device, this skips partitions, loop, zram and dm
+        DiskStat::parse(&content, |name| {
+            Path::new("/sys/block").join(name).join("device").exists()
+        })
+    }
+
+    // The counters of the devices in /proc/diskstats content that `keep` accepts
+    fn parse(content: &str, keep: impl Fn(&str) -> bool) -> HashMap<String, DiskStat> {
+        let mut stats = HashMap::new();
+        for line in content.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 14 {
+                continue;
+            }
+
+            let name = parts[2];
+            if !keep(name) {
+                continue;
+            }
+
+            let field = |i: usize| parts[i].parse::<u64>().unwrap_or(0);
+            stats.insert(
+                name.to_string(),
+                DiskStat {
+                    reads: field(3),
+                    read_ms: field(6),
+                    writes: field(7),
+                    write_ms: field(10),
+                    io_ticks_ms: field(12),
+                },
+            );
+        }
+        stats
+    }
+}
+
+/// I/O activity between two diskstats snapshots
+#[derive(Debug, Default, PartialEq)]
+struct IoActivity {
+    reads: u64,
+    writes: u64,
+    read_latency_us: u64,
+    write_latency_us: u64,
+    // Percentage of the interval the busiest device had I/O in flight
+    utilization: u64,
+}
+
+impl IoActivity {
+    // Devices missing from `prev` have no activity yet
+    fn between(
+        prev: &HashMap<String, DiskStat>,
+        current: &HashMap<String, DiskStat>,
+        elapsed_ms: u64,
+    ) -> IoActivity {
+        let mut reads = 0;
+        let mut writes = 0;
+        let mut read_ms = 0;
+        let mut write_ms = 0;
+        let mut busiest_ms = 0;
+
+        for (name, stat) in current {
+            if let Some(prev) = prev.get(name) {
+                reads += stat.reads.saturating_sub(prev.reads);
+                writes += stat.writes.saturating_sub(prev.writes);
+                read_ms += stat.read_ms.saturating_sub(prev.read_ms);
+                write_ms += stat.write_ms.saturating_sub(prev.write_ms);
+                busiest_ms = busiest_ms.max(stat.io_ticks_ms.saturating_sub(prev.io_ticks_ms));
+            }
+        }
+
+        let latency_us = |ms: u64, ops: u64| (ms * 1000).checked_div(ops).unwrap_or(0);
+
+        IoActivity {
+            reads,
+            writes,
+            read_latency_us: latency_us(read_ms, reads),
+            write_latency_us: latency_us(write_ms, writes),
+            utilization: (busiest_ms * 100)
+                .checked_div(elapsed_ms)
+                .map_or(0, |percent| percent.min(100)),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Disks {
     disks: DisksInfo,
     write: BoundedVecDeque<u64>,
     read: BoundedVecDeque<u64>,
+    // Completed operations per sample interval
+    write_ops: BoundedVecDeque<u64>,
+    read_ops: BoundedVecDeque<u64>,
+    // Average time per completed operation, in microseconds
+    write_latency: BoundedVecDeque<u64>,
+    read_latency: BoundedVecDeque<u64>,
+    // Percentage of the interval the busiest device had I/O in flight
+    utilization: BoundedVecDeque<u64>,
+    // Write and read of the charted metric for the longer chart windows, counts per second
+    history_write: History,
+    history_read: History,
+    diskstats: HashMap<String, DiskStat>,
+    diskstats_time: Instant,
+    metric_options: Vec<&'static str>,
     max_y: Option<u64>,
     svg_colors: SvgColors,
     config: DisksConfig,
@@ -85,6 +217,16 @@ impl DemoGraph for Disks {
         let write = std::collections::VecDeque::from(DL_DEMO);
         let read = std::collections::VecDeque::from(UL_DEMO);
 
+        if self.config.metric == DisksMetric::Utilization {
+            let util = std::collections::VecDeque::from(UTIL_DEMO);
+            return crate::svg_graph::line_adaptive(
+                &util,
+                GRAPH_SAMPLES,
+                &self.svg_colors,
+                Some(100),
+            );
+        }
+
         match self.config.variant {
             DisksVariant::Combined => {
                 crate::svg_graph::double_line(&write, &read, GRAPH_SAMPLES, &self.svg_colors, None)
@@ -129,6 +271,10 @@ impl DemoGraph for Disks {
 impl Sensor for Disks {
     fn update_config(&mut self, config: &dyn Any, refresh_rate: u32) {
         if let Some(cfg) = config.downcast_ref::<DisksConfig>() {
+            if cfg.metric != self.config.metric {
+                self.history_write.clear();
+                self.history_read.clear();
+            }
             self.config = cfg.clone();
             self.svg_colors.set_colors(cfg.colors());
             self.refresh_rate = refresh_rate;
@@ -158,6 +304,16 @@ impl Sensor for Disks {
 
         self.write.push_back(wr);
         self.read.push_back(rd);
+
+        self.update_diskstats();
+
+        let (write, read) = self.metric_samples();
+        let (write, read) = (write.back().copied(), read.back().copied());
+        let per_second = self.per_second();
+        self.history_write
+            .push_now(write.unwrap_or(0) as f64 * per_second);
+        self.history_read
+            .push_now(read.unwrap_or(0) as f64 * per_second);
     }
 
     fn demo_graph(&self) -> Box<dyn DemoGraph> {
@@ -216,24 +372,32 @@ impl Sensor for Disks {
         _height_hint: u16,
         _width_hint: u16,
     ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
-        let svg = match self.config.variant {
-            DisksVariant::Combined => crate::svg_graph::double_line(
-                &self.write,
-                &self.read,
-                GRAPH_SAMPLES,
-                &self.svg_colors,
-                self.max_y,
-            ),
-            DisksVariant::Write => crate::svg_graph::line_adaptive(
-                &self.write,
+        let (write, read) = self.metric_samples();
+        let window = self.config.window;
+        let scale = 1.0 / self.per_second();
+        let write =
+            history::chart_samples_u64(write, &self.history_write, window, GRAPH_SAMPLES, scale);
+        let read =
+            history::chart_samples_u64(read, &self.history_read, window, GRAPH_SAMPLES, scale);
+
+        let svg = match (self.config.metric, self.config.variant) {
+            (DisksMetric::Utilization, _) => {
+                crate::svg_graph::line_adaptive(&write, GRAPH_SAMPLES, &self.svg_colors, Some(100))
+            }
+            (_, DisksVariant::Combined) => crate::svg_graph::double_line(
+                &write,
+                &read,
                 GRAPH_SAMPLES,
                 &self.svg_colors,
                 self.max_y,
             ),
-            DisksVariant::Read => {
+            (_, DisksVariant::Write) => {
+                crate::svg_graph::line_adaptive(&write, GRAPH_SAMPLES, &self.svg_colors, self.max_y)
+            }
+            (_, DisksVariant::Read) => {
                 let mut cols = self.svg_colors.clone();
                 cols.graph1 = cols.graph2.clone();
-                crate::svg_graph::line_adaptive(&self.read, GRAPH_SAMPLES, &cols, self.max_y)
+                crate::svg_graph::line_adaptive(&read, GRAPH_SAMPLES, &cols, self.max_y)
             }
         };
         let icon = cosmic::widget::icon::from_svg_bytes(svg.into_bytes());
@@ -255,6 +419,8 @@ impl Sensor for Disks {
 
         let rdrate = format!("R {}", &self.read_label(sample_rate_ms, UnitVariant::Long));
 
+        let utilrate = self.utilization_label();
+
         let config = &self.config;
         let k = self.config.variant;
 
@@ -267,6 +433,13 @@ impl Sensor for Disks {
         rate = rate.push(Element::from(cosmic::widget::text::body("")));
 
         match self.config.variant {
+            _ if self.config.metric == DisksMetric::Utilization => {
+                rate = rate.push(
+                    cosmic::widget::text::body(utilrate)
+                        .width(90)
+                        .align_x(Alignment::Center),
+                );
+            }
             DisksVariant::Combined => {
                 rate = rate.push(
                     cosmic::widget::text::body(wrrate)
@@ -315,6 +488,35 @@ impl Sensor for Disks {
             .into(),
         );
 
+        let selected_metric: Option<usize> = Some(self.config.metric.into());
+        disk_bandwidth_items.push(
+            settings::item(
+                fl!("disks-metric"),
+                widget::dropdown(&self.metric_options, selected_metric, move |m| {
+                    Message::DisksSelectMetric(k, m.into())
+                }),
+            )
+            .into(),
+        );
+
+        disk_bandwidth_items.push(alerts::settings_ui(
+            DeviceKind::Disks(k),
+            None,
+            &config.alerts,
+            self.alert_unit(),
+        ));
+        disk_bandwidth_items.push(color_stops::settings_ui(
+            DeviceKind::Disks(k),
+            None,
+            &config.color_stops,
+            self.alert_unit(),
+        ));
+        disk_bandwidth_items.push(history::window_ui(
+            DeviceKind::Disks(k),
+            None,
+            config.window,
+        ));
+
         disk_bandwidth_items.push(
             row!(
                 widget::horizontal_space(),
@@ -332,10 +534,17 @@ impl Sensor for Disks {
 
         disk_elements.push(Element::from(disk_right_column.spacing(cosmic.space_xs())));
 
-        let title_content = match self.config.variant {
-            DisksVariant::Combined => fl!("disks-title-combined"),
-            DisksVariant::Write => fl!("disks-title-write"),
-            DisksVariant::Read => fl!("disks-title-read"),
+        let title_content = match (self.config.metric, self.config.variant) {
+            (DisksMetric::Throughput, DisksVariant::Combined) => fl!("disks-title-combined"),
+            (DisksMetric::Throughput, DisksVariant::Write) => fl!("disks-title-write"),
+            (DisksMetric::Throughput, DisksVariant::Read) => fl!("disks-title-read"),
+            (DisksMetric::Iops, DisksVariant::Combined) => fl!("disks-title-iops-combined"),
+            (DisksMetric::Iops, DisksVariant::Write) => fl!("disks-title-iops-write"),
+            (DisksMetric::Iops, DisksVariant::Read) => fl!("disks-title-iops-read"),
+            (DisksMetric::Latency, DisksVariant::Combined) => fl!("disks-title-latency-combined"),
+            (DisksMetric::Latency, DisksVariant::Write) => fl!("disks-title-latency-write"),
+            (DisksMetric::Latency, DisksVariant::Read) => fl!("disks-title-latency-read"),
+            (DisksMetric::Utilization, _) => fl!("disks-title-utilization"),
         };
         let title = widget::text::heading(title_content);
 
@@ -346,6 +555,42 @@ impl Sensor for Disks {
         .spacing(cosmic::theme::spacing().space_xs)
         .into()
     }
+
+    // The combined item adds up both directions, except latency where the slower counts
+    fn alert_value(&self) -> Option<f64> {
+        let write = self.metric_value(&self.write, &self.write_ops, &self.write_latency);
+        let read = self.metric_value(&self.read, &self.read_ops, &self.read_latency);
+        Some(match (self.config.metric, self.config.variant) {
+            (DisksMetric::Utilization, _) | (_, DisksVariant::Write) => write,
+            (_, DisksVariant::Read) => read,
+            (DisksMetric::Latency, DisksVariant::Combined) => write.max(read),
+            (_, DisksVariant::Combined) => write + read,
+        })
+    }
+
+    fn alert_unit(&self) -> &'static str {
+        match self.config.metric {
+            DisksMetric::Throughput => "MB/s",
+            DisksMetric::Iops => " IOPS",
+            DisksMetric::Latency => "ms",
+            DisksMetric::Utilization => "%",
+        }
+    }
+
+    // Named after the metric, so a snapshot is never restored into another one
+    fn histories_mut(&mut self) -> Vec<Series<'_>> {
+        let metric = format!("{:?}", self.config.metric).to_lowercase();
+        let unit = match self.config.metric {
+            DisksMetric::Throughput => "B/s",
+            DisksMetric::Iops => "IOPS",
+            DisksMetric::Latency => "µs",
+            DisksMetric::Utilization => "%",
+        };
+        vec![
+            Series::new(format!("{metric}-write"), unit, &mut self.history_write),
+            Series::new(format!("{metric}-read"), unit, &mut self.history_read),
+        ]
+    }
 }
 
 impl Default for Disks {
@@ -355,6 +600,25 @@ impl Default for Disks {
             disks,
             write: BoundedVecDeque::from_iter(std::iter::repeat_n(0, MAX_SAMPLES), MAX_SAMPLES),
             read: BoundedVecDeque::from_iter(std::iter::repeat_n(0, MAX_SAMPLES), MAX_SAMPLES),
+            write_ops: BoundedVecDeque::from_iter(std::iter::repeat_n(0, MAX_SAMPLES), MAX_SAMPLES),
+            read_ops: BoundedVecDeque::from_iter(std::iter::repeat_n(0, MAX_SAMPLES), MAX_SAMPLES),
+            write_latency: BoundedVecDeque::from_iter(
+                std::iter::repeat_n(0, MAX_SAMPLES),
+                MAX_SAMPLES,
+            ),
+            read_latency: BoundedVecDeque::from_iter(
+                std::iter::repeat_n(0, MAX_SAMPLES),
+                MAX_SAMPLES,
+            ),
+            utilization: BoundedVecDeque::from_iter(
+                std::iter::repeat_n(0, MAX_SAMPLES),
+                MAX_SAMPLES,
+            ),
+            history_write: History::default(),
+            history_read: History::default(),
+            diskstats: DiskStat::read_all(),
+            diskstats_time: Instant::now(),
+            metric_options: METRIC_OPTIONS.to_vec(),
             max_y: None,
             svg_colors: SvgColors::new(&ChartColors::default()),
             config: DisksConfig::default(),
@@ -436,16 +700,190 @@ impl Disks {
         (total_bitrate as f64 * scale).floor() as u64
     }
 
-    // Get bytes per second
+    fn makestr_iops(val: u64, format: UnitVariant) -> String {
+        let mut value = val as f64;
+        let mut unit_index = 0;
+        let units = if format == UnitVariant::Short {
+            UNITS_IOPS_SHORT
+        } else {
+            UNITS_IOPS_LONG
+        };
+
+        while value >= 999.0 && unit_index < units.len() - 1 {
+            value /= 1000.0;
+            unit_index += 1;
+        }
+
+        if unit_index == 0 {
+            format!("{}{}", value.trunc(), units[unit_index])
+        } else if value < 10.0 {
+            format!(
+                "{:.2}{}",
+                (value * 100.0).trunc() / 100.0,
+                units[unit_index]
+            )
+        } else {
+            format!("{:.1}{}", (value * 10.0).trunc() / 10.0, units[unit_index])
+        }
+    }
+
+    // Latency is stored in microseconds, shown in milliseconds
+    fn makestr_latency(val: u64, format: UnitVariant) -> String {
+        let ms = val as f64 / 1000.0;
+        let unit = if format == UnitVariant::Short {
+            "ms"
+        } else {
+            " ms"
+        };
+
+        if ms < 10.0 {
+            format!("{:.2}{unit}", (ms * 100.0).trunc() / 100.0)
+        } else if ms < 100.0 {
+            format!("{:.1}{unit}", (ms * 10.0).trunc() / 10.0)
+        } else {
+            format!("{}{unit}", ms.trunc())
+        }
+    }
+
+    fn metric_label(
+        &self,
+        bytes: &BoundedVecDeque<u64>,
+        ops: &BoundedVecDeque<u64>,
+        latency: &BoundedVecDeque<u64>,
+        sample_interval_ms: u32,
+        format: UnitVariant,
+    ) -> String {
+        match self.config.metric {
+            DisksMetric::Throughput => {
+                Disks::makestr(Disks::last_second_rate(bytes, sample_interval_ms), format)
+            }
+            DisksMetric::Iops => {
+                Disks::makestr_iops(Disks::last_second_rate(ops, sample_interval_ms), format)
+            }
+            DisksMetric::Latency => Disks::makestr_latency(*latency.back().unwrap_or(&0), format),
+            DisksMetric::Utilization => self.utilization_label(),
+        }
+    }
+
+    // MB/s, operations per second, milliseconds or percent, for alerts
+    fn metric_value(
+        &self,
+        bytes: &BoundedVecDeque<u64>,
+        ops: &BoundedVecDeque<u64>,
+        latency: &BoundedVecDeque<u64>,
+    ) -> f64 {
+        match self.config.metric {
+            DisksMetric::Throughput => {
+                Disks::last_second_rate(bytes, self.refresh_rate) as f64 / 1_000_000.0
+            }
+            DisksMetric::Iops => Disks::last_second_rate(ops, self.refresh_rate) as f64,
+            DisksMetric::Latency => *latency.back().unwrap_or(&0) as f64 / 1000.0,
+            DisksMetric::Utilization => *self.utilization.back().unwrap_or(&0) as f64,
+        }
+    }
+
+    // Get bytes, operations or latency per second, depending on the selected metric
     pub fn write_label(&self, sample_interval_ms: u32, format: UnitVariant) -> String {
-        let val = Disks::last_second_rate(&self.write, sample_interval_ms);
-        Disks::makestr(val, format)
+        self.metric_label(
+            &self.write,
+            &self.write_ops,
+            &self.write_latency,
+            sample_interval_ms,
+            format,
+        )
     }
 
-    // Get bytes per second
+    // Get bytes, operations or latency per second, depending on the selected metric
     pub fn read_label(&self, sample_interval_ms: u32, format: UnitVariant) -> String {
-        let val = Disks::last_second_rate(&self.read, sample_interval_ms);
-        Disks::makestr(val, format)
+        self.metric_label(
+            &self.read,
+            &self.read_ops,
+            &self.read_latency,
+            sample_interval_ms,
+            format,
+        )
+    }
+
+    pub fn utilization_label(&self) -> String {
+        format!("{}%", self.utilization.back().unwrap_or(&0))
+    }
+
+    pub fn metric(&self) -> DisksMetric {
+        self.config.metric
+    }
+
+    // Every metric, independent of the one the item shows
+    pub fn prometheus(&self, metrics: &mut Metrics) {
+        let rate = |samples| Disks::last_second_rate(samples, self.refresh_rate) as f64;
+        let latest = |samples: &BoundedVecDeque<u64>| *samples.back().unwrap_or(&0) as f64;
+        for (direction, bytes, ops, latency) in [
+            ("write", &self.write, &self.write_ops, &self.write_latency),
+            ("read", &self.read, &self.read_ops, &self.read_latency),
+        ] {
+            let labels = [("direction", direction)];
+            metrics.gauge(
+                "disk_bytes_per_second",
+                "Throughput of all disks",
+                &labels,
+                rate(bytes),
+            );
+            metrics.gauge(
+                "disk_operations_per_second",
+                "Completed operations of all disks",
+                &labels,
+                rate(ops),
+            );
+            metrics.gauge(
+                "disk_latency_seconds",
+                "Average time per completed operation",
+                &labels,
+                latest(latency) / 1_000_000.0,
+            );
+        }
+        metrics.gauge(
+            "disk_utilization_percent",
+            "Share of the time the busiest disk had I/O in flight",
+            &[],
+            latest(&self.utilization),
+        );
+    }
+
+    /// Write and read samples of the metric the chart shows
+    fn metric_samples(&self) -> (&BoundedVecDeque<u64>, &BoundedVecDeque<u64>) {
+        match self.config.metric {
+            DisksMetric::Throughput => (&self.write, &self.read),
+            DisksMetric::Iops => (&self.write_ops, &self.read_ops),
+            DisksMetric::Latency => (&self.write_latency, &self.read_latency),
+            DisksMetric::Utilization => (&self.utilization, &self.utilization),
+        }
+    }
+
+    // Converts the per interval counts of bytes and operations to rates per second,
+    // latency and utilization are kept as they are
+    fn per_second(&self) -> f64 {
+        match self.config.metric {
+            DisksMetric::Throughput | DisksMetric::Iops => {
+                1000.0 / f64::from(self.refresh_rate.max(1))
+            }
+            DisksMetric::Latency | DisksMetric::Utilization => 1.0,
+        }
+    }
+
+    /// Derive IOPS, latency and utilization from the /proc/diskstats counters
+    fn update_diskstats(&mut self) {
+        let now = Instant::now();
+        let elapsed_ms = now.duration_since(self.diskstats_time).as_millis() as u64;
+        let current = DiskStat::read_all();
+        let activity = IoActivity::between(&self.diskstats, &current, elapsed_ms);
+
+        self.read_ops.push_back(activity.reads);
+        self.write_ops.push_back(activity.writes);
+        self.read_latency.push_back(activity.read_latency_us);
+        self.write_latency.push_back(activity.write_latency_us);
+        self.utilization.push_back(activity.utilization);
+
+        self.diskstats = current;
+        self.diskstats_time = now;
     }
 }
 
@@ -453,7 +891,86 @@ const DL_DEMO: [u64; 21] = [
     208, 2071, 0, 1056588, 912575, 912875, 912975, 912600, 1397, 1173024, 1228, 6910, 2493,
     1102101, 380, 2287, 1109656, 1541, 3798, 1132822, 68479,
 ];
+const UTIL_DEMO: [u64; 21] = [
+    2, 5, 3, 41, 38, 40, 37, 39, 12, 64, 6, 9, 4, 58, 2, 3, 61, 4, 7, 66, 14,
+];
 const UL_DEMO: [u64; 21] = [
     0, 1687, 0, 9417, 9161, 838, 6739, 1561, 212372, 312372, 412372, 512372, 512372, 512372,
     412372, 312372, 112372, 864, 0, 8587, 760,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // major minor name, then reads merged sectors ms, writes merged sectors ms, in-flight io_ticks weighted
+    const BEFORE: &str = "\
+ 259       0 nvme0n1 1000 10 8000 500 2000 20 16000 4000 0 3000 4500 0 0 0 0 0 0
+ 259       1 nvme0n1p1 900 10 7000 450 1900 20 15000 3900 0 2900 4350 0 0 0 0 0 0
+   8       0 sda 100 0 800 200 50 0 400 100 0 250 300
+   8      32 sdc 7 56
+   7       0 loop0 10 0 80 1 0 0 0 0 0 1 1 0 0 0 0 0 0
+";
+    const AFTER: &str = "\
+ 259       0 nvme0n1 1100 10 8800 700 2400 20 19200 4800 0 3400 5500 0 0 0 0 0 0
+ 259       1 nvme0n1p1 1000 10 7800 650 2300 20 18200 4700 0 3300 5350 0 0 0 0 0 0
+   8       0 sda 100 0 800 200 60 0 480 130 0 350 330 0 0 0 0 0 0
+   8      16 sdb 5 0 40 5 0 0 0 0 0 5 5 0 0 0 0 0 0
+   7       0 loop0 20 0 160 2 0 0 0 0 0 2 2 0 0 0 0 0 0
+";
+
+    fn whole_disk(name: &str) -> bool {
+        !name.starts_with("loop") && !name.contains('p')
+    }
+
+    #[test]
+    fn parse_diskstats() {
+        let stats = DiskStat::parse(BEFORE, whole_disk);
+        // sda has the 14 fields of kernels before 4.18, sdc is cut short
+        let mut names: Vec<_> = stats.keys().collect();
+        names.sort();
+        assert_eq!(names, ["nvme0n1", "sda"]);
+        assert_eq!(stats["sda"].io_ticks_ms, 250);
+        let nvme = stats["nvme0n1"];
+        assert_eq!(
+            (
+                nvme.reads,
+                nvme.read_ms,
+                nvme.writes,
+                nvme.write_ms,
+                nvme.io_ticks_ms
+            ),
+            (1000, 500, 2000, 4000, 3000)
+        );
+
+        let stats = DiskStat::parse(AFTER, whole_disk);
+        let mut names: Vec<_> = stats.keys().collect();
+        names.sort();
+        assert_eq!(names, ["nvme0n1", "sda", "sdb"]);
+    }
+
+    #[test]
+    fn activity_between_snapshots() {
+        let before = DiskStat::parse(BEFORE, whole_disk);
+        let after = DiskStat::parse(AFTER, whole_disk);
+
+        // nvme0n1: 100 reads in 200 ms, 400 writes in 800 ms, busy 400 ms
+        // sda: 10 writes in 30 ms, busy 100 ms, sdb is new and not counted
+        assert_eq!(
+            IoActivity::between(&before, &after, 1000),
+            IoActivity {
+                reads: 100,
+                writes: 410,
+                read_latency_us: 2000,
+                write_latency_us: 2024,
+                utilization: 40,
+            }
+        );
+        assert_eq!(IoActivity::between(&before, &after, 200).utilization, 100);
+        assert_eq!(IoActivity::between(&before, &after, 0).utilization, 0);
+        assert_eq!(
+            IoActivity::between(&after, &after, 1000),
+            IoActivity::default()
+        );
+    }
+}
@@ -0,0 +1,605 @@
+use crate::{
+    alerts, color_stops,
+    colorpicker::DemoGraph,
+    config::{ChartColors, ChartKind, ColorVariant, DeviceKind, DiskTempConfig},
+    fl,
+    history::{self, History, Series},
+    prometheus::Metrics,
+    sensors::INVALID_IMG,
+    svg_graph::SvgColors,
+    trace::{self, DriveInfo},
+};
+use cosmic::{Element, iced::Alignment::Center, widget::Container};
+
+use cosmic::widget;
+use cosmic::widget::{settings, toggler};
+
+use cosmic::{
+    iced::{
+        Alignment,
+        widget::{column, row},
+    },
+    iced_widget::{Column, Row},
+};
+use log::info;
+
+use crate::app::Message;
+use std::any::Any;
+
+use bounded_vec_deque::BoundedVecDeque;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use std::fs::read_dir;
+use std::io;
+
+use super::{Sensor, TempUnit};
+
+const MAX_SAMPLES: usize = 21;
+// Used when the drive doesn't report a critical temperature
+const DEFAULT_CRIT_TEMP: f64 = 85.0;
+
+/// Temperature of a single NVMe or SATA drive, read through hwmon
+#[derive(Debug)]
+pub struct DriveTemp {
+    /// Block device name, like nvme0n1 or sda
+    pub device: String,
+    pub model: String,
+    pub crit_temp: f64,
+    input_path: PathBuf,
+    pub samples: BoundedVecDeque<f64>,
+    pub history: History,
+}
+
+impl DriveTemp {
+    fn new(device: String, model: String, crit_temp: f64, input_path: PathBuf) -> DriveTemp {
+        DriveTemp {
+            device,
+            model,
+            crit_temp,
+            input_path,
+            samples: BoundedVecDeque::from_iter(std::iter::repeat_n(0.0, MAX_SAMPLES), MAX_SAMPLES),
+            history: History::default(),
+        }
+    }
+
+    /// Find all drive temperature sensors from the nvme and drivetemp hwmon drivers,
+    /// or those of the trace while replaying
+    pub fn find_drive_sensors() -> io::Result<Vec<DriveTemp>> {
+        if let Some(drives) = trace::replayed_drives() {
+            return Ok(drives
+                .into_iter()
+                .map(|d| DriveTemp::new(d.device, d.model, d.crit_temp, PathBuf::new()))
+                .collect());
+        }
+
+        info!("Find drive temperature sensors");
+        let hwmon_base = Path::new("/sys/class/hwmon");
+        let mut drives = Vec::new();
+
+        for entry in read_dir(hwmon_base)? {
+            let hwmon = entry?.path();
+
+            let Ok(name) = fs::read_to_string(hwmon.join("name")) else {
+                continue;
+            };
+            let name = name.trim();
+            if name != "nvme" && name != "drivetemp" {
+                continue;
+            }
+
+            let input_path = hwmon.join("temp1_input");
+            if !input_path.exists() {
+                continue;
+            }
+
+            let Some(device) = DriveTemp::block_device(&hwmon.join("device")) else {
+                info!("  {hwmon:?}: no block device found for {name}");
+                continue;
+            };
+
+            let crit_temp = ["temp1_crit", "temp1_max"]
+                .iter()
+                .filter_map(|f| fs::read_to_string(hwmon.join(f)).ok())
+                .filter_map(|v| v.trim().parse::<f64>().ok())
+                .map(|v| v / 1000.0)
+                .find(|v| *v > 0.0)
+                .unwrap_or(DEFAULT_CRIT_TEMP);
+
+            let model =
+                fs::read_to_string(Path::new("/sys/block").join(&device).join("device/model"))
+                    .map(|m| m.trim().to_string())
+                    .unwrap_or_default();
+
+            info!("  found {name} sensor {input_path:?} for {device} {model}");
+            drives.push(DriveTemp::new(device, model, crit_temp, input_path));
+        }
+
+        drives.sort_by(|a, b| a.device.cmp(&b.device));
+        for drive in &drives {
+            trace::declare_drive(&DriveInfo {
+                device: drive.device.clone(),
+                model: drive.model.clone(),
+                crit_temp: drive.crit_temp,
+            });
+        }
+        Ok(drives)
+    }
+
+    /// Map the hwmon parent device back to a block device name.
+    /// drivetemp hangs off the SCSI device which has a block/ directory,
+    /// nvme hangs off the controller (or its PCI device) with the namespaces as children.
+    fn block_device(device: &Path) -> Option<String> {
+        if let Some(name) = DriveTemp::first_entry(&device.join("block"), |_| true) {
+            return Some(name);
+        }
+
+        // Older kernels register the nvme hwmon on the PCI device
+        let controller = match DriveTemp::first_entry(&device.join("nvme"), |_| true) {
+            Some(ctrl) => device.join("nvme").join(ctrl),
+            None => device.to_path_buf(),
+        };
+
+        let ctrl_name = controller
+            .canonicalize()
+            .ok()?
+            .file_name()?
+            .to_string_lossy()
+            .to_string();
+        let prefix = format!("{ctrl_name}n");
+        DriveTemp::first_entry(&controller, |n| n.starts_with(&prefix)).or(Some(ctrl_name))
+    }
+
+    fn first_entry(dir: &Path, filter: impl Fn(&str) -> bool) -> Option<String> {
+        let mut names: Vec<String> = read_dir(dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|n| filter(n))
+            .collect();
+        names.sort();
+        names.into_iter().next()
+    }
+
+    pub fn read_temp(&self) -> io::Result<f64> {
+        trace::input(
+            &format!("disktemp:{}", self.device),
+            || -> io::Result<f64> {
+                let raw = fs::read_to_string(&self.input_path)?;
+                let millideg: i32 = raw.trim().parse().map_err(|e| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("Parse error: {e}"))
+                })?;
+                Ok(f64::from(millideg) / 1000.0)
+            },
+        )
+    }
+
+    pub fn latest_sample(&self) -> f64 {
+        *self.samples.back().unwrap_or(&0f64)
+    }
+}
+
+#[derive(Debug)]
+pub struct DiskTemp {
+    drives: Vec<DriveTemp>,
+    graph_options: Vec<&'static str>,
+    unit_options: Vec<&'static str>,
+    /// colors cached so we don't need to convert to string every time
+    svg_colors: SvgColors,
+    config: DiskTempConfig,
+}
+
+impl DemoGraph for DiskTemp {
+    fn demo(&self) -> String {
+        match self.config.chart {
+            ChartKind::Ring => crate::svg_graph::ring("38", 45, None, &self.svg_colors),
+            ChartKind::Line => crate::svg_graph::line(
+                &std::collections::VecDeque::from(DEMO_SAMPLES),
+                DEFAULT_CRIT_TEMP,
+                &self.svg_colors,
+            ),
+            ChartKind::Heat => crate::svg_graph::heat(
+                &std::collections::VecDeque::from(DEMO_SAMPLES),
+                DEFAULT_CRIT_TEMP as u64,
+                &self.svg_colors,
+                &self.config.color_stops,
+            ),
+            ChartKind::StackedBars => {
+                log::error!("StackedBars not supported for DiskTemp");
+                INVALID_IMG.to_string()
+            }
+        }
+    }
+
+    fn colors(&self) -> &ChartColors {
+        self.config.colors()
+    }
+
+    fn set_colors(&mut self, colors: &ChartColors) {
+        *self.config.colors_mut() = *colors;
+        self.svg_colors.set_colors(colors);
+    }
+
+    fn color_choices(&self) -> Vec<(&'static str, ColorVariant)> {
+        match self.config.chart {
+            ChartKind::Line => (*super::COLOR_CHOICES_LINE).into(),
+            ChartKind::Ring => (*super::COLOR_CHOICES_RING).into(),
+            ChartKind::Heat => (*super::COLOR_CHOICES_HEAT).into(),
+            ChartKind::StackedBars => panic!("StackedBars not supported for DiskTemp"),
+        }
+    }
+
+    fn id(&self) -> Option<String> {
+        None
+    }
+
+    fn kind(&self) -> ChartKind {
+        self.config.chart
+    }
+}
+
+impl Sensor for DiskTemp {
+    fn update_config(&mut self, config: &dyn Any, _refresh_rate: u32) {
+        if let Some(cfg) = config.downcast_ref::<DiskTempConfig>() {
+            self.config = cfg.clone();
+            self.svg_colors.set_colors(cfg.colors());
+        }
+    }
+
+    fn graph_kind(&self) -> ChartKind {
+        self.config.chart
+    }
+
+    fn set_graph_kind(&mut self, kind: ChartKind) {
+        assert!(kind == ChartKind::Line || kind == ChartKind::Ring || kind == ChartKind::Heat);
+        self.config.chart = kind;
+    }
+
+    fn update(&mut self) {
+        for drive in &mut self.drives {
+            if self.config.hidden.contains(&drive.device) {
+                continue;
+            }
+            match drive.read_temp() {
+                Ok(temp) => {
+                    drive.samples.push_back(temp);
+                    drive.history.push_now(temp);
+                }
+                Err(e) => info!("Error reading {} temp data {e:?}", drive.device),
+            }
+        }
+    }
+
+    fn demo_graph(&self) -> Box<dyn DemoGraph> {
+        let mut dmo = DiskTemp {
+            drives: Vec::new(),
+            graph_options: self.graph_options.clone(),
+            unit_options: self.unit_options.clone(),
+            svg_colors: SvgColors::new(&ChartColors::default()),
+            config: DiskTempConfig::default(),
+        };
+        dmo.update_config(&self.config, 0);
+        Box::new(dmo)
+    }
+
+    fn chart(
+        &'_ self,
+        _height_hint: u16,
+        _width_hint: u16,
+    ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
+        self.drive_chart(self.visible_drives().next().unwrap_or(0))
+    }
+
+    fn settings_ui(&'_ self) -> Element<'_, crate::app::Message> {
+        let theme = cosmic::theme::active();
+        let cosmic = theme.cosmic();
+
+        let mut temp_elements = Vec::new();
+
+        let mut charts = Vec::new();
+        for (idx, drive) in self.drives.iter().enumerate() {
+            if self.config.hidden.contains(&drive.device) {
+                continue;
+            }
+            charts.push(Element::from(
+                column!(
+                    Container::new(self.drive_chart(idx).width(60).height(60))
+                        .width(90)
+                        .align_x(Alignment::Center),
+                    cosmic::widget::text::body(drive.device.clone())
+                        .width(90)
+                        .align_x(Alignment::Center),
+                    cosmic::widget::text::body(self.drive_label(idx))
+                        .width(90)
+                        .align_x(Alignment::Center)
+                )
+                .padding(5)
+                .align_x(Alignment::Center),
+            ));
+        }
+        temp_elements.push(Element::from(Column::with_children(charts)));
+
+        let selected_graph: Option<usize> = Some(self.graph_kind().into());
+        let selected_unit: Option<usize> = Some(self.config.unit.into());
+
+        let config = &self.config;
+        let temp_kind = self.graph_kind();
+        temp_elements.push(Element::from(
+            column!(
+                settings::item(
+                    fl!("enable-chart"),
+                    toggler(config.chart_visible())
+                        .on_toggle(|value| { Message::ToggleDiskTempChart(value) }),
+                ),
+                settings::item(
+                    fl!("enable-label"),
+                    toggler(config.label_visible())
+                        .on_toggle(|value| { Message::ToggleDiskTempLabel(value) }),
+                ),
+                settings::item(
+                    fl!("temperature-unit"),
+                    widget::dropdown(&self.unit_options, selected_unit, |m| {
+                        Message::SelectDiskTempUnit(m.into())
+                    },)
+                ),
+                row!(
+                    widget::text::body(fl!("chart-type")),
+                    widget::dropdown(&self.graph_options, selected_graph, |m| {
+                        Message::SelectGraphType(DeviceKind::DiskTemp, m.into())
+                    },)
+                    .width(70),
+                    widget::horizontal_space(),
+                    widget::button::standard(fl!("change-colors")).on_press(
+                        Message::ColorPickerOpen(DeviceKind::DiskTemp, temp_kind, None)
+                    ),
+                )
+                .align_y(Center),
+                alerts::settings_ui(
+                    DeviceKind::DiskTemp,
+                    None,
+                    &config.alerts,
+                    self.alert_unit()
+                ),
+                color_stops::settings_ui(
+                    DeviceKind::DiskTemp,
+                    None,
+                    &config.color_stops,
+                    self.alert_unit()
+                ),
+            )
+            .push_maybe(
+                (config.chart != ChartKind::Ring)
+                    .then(|| history::window_ui(DeviceKind::DiskTemp, None, config.window)),
+            )
+            .spacing(cosmic.space_xs()),
+        ));
+
+        let mut drive_items = Vec::new();
+        for drive in &self.drives {
+            let shown = !config.hidden.contains(&drive.device);
+            let device = drive.device.clone();
+            let label = if drive.model.is_empty() {
+                drive.device.clone()
+            } else {
+                format!("{} ({})", drive.device, drive.model)
+            };
+            drive_items.push(
+                settings::item(
+                    label,
+                    widget::checkbox("", shown).on_toggle(move |value| {
+                        Message::ToggleDiskTempDrive(device.clone(), value)
+                    }),
+                )
+                .into(),
+            );
+        }
+
+        column!(
+            widget::text::heading(fl!("disk-temperature-title")),
+            Row::with_children(temp_elements).align_y(Alignment::Center),
+            widget::text::heading(fl!("disk-temperature-drives")),
+            Column::with_children(drive_items).spacing(cosmic.space_xxs())
+        )
+        .spacing(cosmic::theme::spacing().space_xs)
+        .into()
+    }
+
+    // The hottest of the shown drives
+    fn alert_value(&self) -> Option<f64> {
+        self.drives
+            .iter()
+            .filter(|d| !self.config.hidden.contains(&d.device))
+            .map(DriveTemp::latest_sample)
+            .max_by(f64::total_cmp)
+    }
+
+    fn alert_unit(&self) -> &'static str {
+        "°C"
+    }
+
+    fn histories_mut(&mut self) -> Vec<Series<'_>> {
+        self.drives
+            .iter_mut()
+            .map(|d| Series::new(d.device.clone(), "°C", &mut d.history))
+            .collect()
+    }
+}
+
+impl Default for DiskTemp {
+    fn default() -> Self {
+        let drives = match DriveTemp::find_drive_sensors() {
+            Ok(drives) => {
+                if drives.is_empty() {
+                    info!("DiskTemp:detect: No drive temperature sensors found.");
+                }
+                drives
+            }
+            Err(e) => {
+                info!("DiskTemp:detect: No drive temperature sensors found. {e:?}");
+                Vec::new()
+            }
+        };
+
+        let mut disktemp = DiskTemp {
+            drives,
+            graph_options: super::GRAPH_OPTIONS_RING_LINE_HEAT.to_vec(),
+            unit_options: super::UNIT_OPTIONS.to_vec(),
+            svg_colors: SvgColors::new(&ChartColors::default()),
+            config: DiskTempConfig::default(),
+        };
+        disktemp.set_colors(&ChartColors::default());
+        disktemp
+    }
+}
+
+impl DiskTemp {
+    // true if at least one drive temperature sensor was found
+    pub fn is_found(&self) -> bool {
+        !self.drives.is_empty()
+    }
+
+    pub fn drives(&self) -> &[DriveTemp] {
+        &self.drives
+    }
+
+    /// Indices of the drives the user hasn't hidden
+    pub fn visible_drives(&self) -> impl Iterator<Item = usize> + '_ {
+        self.drives
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| !self.config.hidden.contains(&d.device))
+            .map(|(idx, _)| idx)
+    }
+
+    pub fn drive_chart(
+        &'_ self,
+        idx: usize,
+    ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
+        let svg = if let Some(drive) = self.drives.get(idx) {
+            let colors = self
+                .svg_colors
+                .with_stops(&self.config.color_stops, Some(drive.latest_sample()));
+            let samples =
+                history::chart_samples(&drive.samples, &drive.history, self.config.window);
+            match self.config.chart {
+                ChartKind::Ring => {
+                    let latest = drive.latest_sample();
+                    let mut value = self.format_temp(latest);
+
+                    // remove the C/F/K unit if there's not enough space
+                    if value.len() > 3 {
+                        let _ = value.pop();
+                    }
+
+                    let percentage = (latest / drive.crit_temp * 100.0).round().clamp(0.0, 100.0);
+                    crate::svg_graph::ring(&value, percentage as u8, None, &colors)
+                }
+                ChartKind::Line => crate::svg_graph::line(&samples, drive.crit_temp, &colors),
+                ChartKind::Heat => crate::svg_graph::heat(
+                    &samples,
+                    drive.crit_temp as u64,
+                    &colors,
+                    &self.config.color_stops,
+                ),
+                ChartKind::StackedBars => {
+                    log::error!("StackedBars not supported for DiskTemp");
+                    INVALID_IMG.to_string()
+                }
+            }
+        } else {
+            self.demo()
+        };
+
+        let icon = cosmic::widget::icon::from_svg_bytes(svg.into_bytes());
+        widget::Container::new(
+            icon.icon()
+                .height(cosmic::iced::Length::Fill)
+                .width(cosmic::iced::Length::Fill),
+        )
+    }
+
+    pub fn prometheus(&self, metrics: &mut Metrics) {
+        for idx in self.visible_drives() {
+            let drive = &self.drives[idx];
+            metrics.gauge(
+                "disk_temperature_celsius",
+                "Drive temperature",
+                &[("device", &drive.device)],
+                drive.latest_sample(),
+            );
+        }
+    }
+
+    pub fn drive_label(&self, idx: usize) -> String {
+        self.drives.get(idx).map_or_else(
+            || String::from("--C"),
+            |d| self.format_temp(d.latest_sample()),
+        )
+    }
+
+    fn format_temp(&self, celcius: f64) -> String {
+        match self.config.unit {
+            TempUnit::Celcius => format!("{}C", celcius.trunc()),
+            TempUnit::Farenheit => format!("{}F", (celcius * 9.0 / 5.0 + 32.0).trunc()),
+            TempUnit::Kelvin => format!("{}K", (celcius + 273.15).trunc()),
+            TempUnit::Rankine => format!("{}R", (celcius * 9.0 / 5.0 + 491.67).trunc()),
+        }
+    }
+}
+
+const DEMO_SAMPLES: [f64; 21] = [
+    33.0, 33.0, 34.0, 34.0, 35.0, 37.0, 40.0, 44.0, 47.0, 49.0, 50.0, 50.0, 49.0, 46.0, 43.0, 41.0,
+    39.0, 38.0, 37.0, 36.0, 36.0,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("minimon-disktemp-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn block_device_of_drivetemp() {
+        // The SCSI device, like /sys/bus/scsi/devices/0:0:0:0
+        let device = temp_dir("scsi");
+        fs::create_dir_all(device.join("block/sda")).unwrap();
+        assert_eq!(DriveTemp::block_device(&device).as_deref(), Some("sda"));
+        fs::remove_dir_all(&device).unwrap();
+    }
+
+    #[test]
+    fn block_device_of_nvme() {
+        let base = temp_dir("nvme");
+
+        // The controller with its namespaces
+        let controller = base.join("nvme0");
+        fs::create_dir_all(controller.join("hwmon1")).unwrap();
+        fs::create_dir_all(controller.join("nvme0n2")).unwrap();
+        fs::create_dir_all(controller.join("nvme0n1")).unwrap();
+        assert_eq!(
+            DriveTemp::block_device(&controller).as_deref(),
+            Some("nvme0n1")
+        );
+
+        // Older kernels, the PCI device with the controller below it
+        let pci = base.join("0000:01:00.0");
+        fs::create_dir_all(pci.join("nvme/nvme1/nvme1n1")).unwrap();
+        assert_eq!(DriveTemp::block_device(&pci).as_deref(), Some("nvme1n1"));
+
+        // No namespace, fall back to the controller
+        let empty = base.join("nvme2");
+        fs::create_dir_all(&empty).unwrap();
+        assert_eq!(DriveTemp::block_device(&empty).as_deref(), Some("nvme2"));
+
+        assert_eq!(DriveTemp::block_device(&base.join("missing")), None);
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
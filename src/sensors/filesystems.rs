@@ -0,0 +1,470 @@
+use bounded_vec_deque::BoundedVecDeque;
+
+use sysinfo::{DiskRefreshKind, Disks as DisksInfo};
+
+use crate::{
+    alerts, color_stops,
+    colorpicker::DemoGraph,
+    config::{ChartColors, ChartKind, ColorVariant, DeviceKind, FilesystemsConfig},
+    fl,
+    svg_graph::SvgColors,
+};
+
+use cosmic::{Element, widget::Container};
+
+use cosmic::widget;
+use cosmic::widget::{settings, toggler};
+
+use cosmic::{
+    iced::{
+        Alignment,
+        widget::{column, row},
+    },
+    iced_widget::{Column, Row},
+};
+
+use crate::app::Message;
+use std::any::Any;
+use std::time::Instant;
+
+use super::Sensor;
+
+// Enough samples to cover a few minutes of fill rate at the default refresh rate
+const FILL_SAMPLES: usize = 300;
+// Don't guess the fill rate from less than this many seconds of data
+const MIN_FILL_WINDOW_SECS: f64 = 30.0;
+// Decimal like the drive sizes, a 1 TB drive shows 1000 GB and not 931
+const GB: f64 = 1_000_000_000.0;
+
+// Pseudo and image filesystems that never fill up in a meaningful way
+const IGNORED_FILESYSTEMS: [&str; 6] = [
+    "squashfs", "overlay", "tmpfs", "devtmpfs", "efivarfs", "ramfs",
+];
+
+#[derive(Debug, Clone)]
+pub struct MountUsage {
+    pub mount_point: String,
+    pub total: u64,
+    pub available: u64,
+    fill: BoundedVecDeque<(Instant, u64)>,
+}
+
+impl MountUsage {
+    fn new(mount_point: &str) -> Self {
+        MountUsage {
+            mount_point: mount_point.to_owned(),
+            total: 0,
+            available: 0,
+            fill: BoundedVecDeque::new(FILL_SAMPLES),
+        }
+    }
+
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            (self.used() as f64 / self.total as f64) * 100.0
+        }
+    }
+
+    /// Seconds until the filesystem is full at the recent fill rate, None if not filling up.
+    pub fn time_until_full(&self) -> Option<u64> {
+        let (t_first, used_first) = self.fill.front()?;
+        let (t_last, used_last) = self.fill.back()?;
+
+        let elapsed = t_last.duration_since(*t_first).as_secs_f64();
+        if elapsed < MIN_FILL_WINDOW_SECS || used_last <= used_first {
+            return None;
+        }
+
+        let rate = (used_last - used_first) as f64 / elapsed;
+        Some((self.available as f64 / rate) as u64)
+    }
+
+    /// Label like "412/1000 GB"
+    pub fn label(&self) -> String {
+        let used = self.used() as f64 / GB;
+        let total = self.total as f64 / GB;
+        if total < 10.0 {
+            format!("{used:.1}/{total:.1} GB")
+        } else {
+            format!("{}/{} GB", used.round(), total.round())
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Filesystems {
+    disks: DisksInfo,
+    mounts: Vec<MountUsage>,
+    svg_colors: SvgColors,
+    config: FilesystemsConfig,
+}
+
+impl DemoGraph for Filesystems {
+    fn demo(&self) -> String {
+        crate::svg_graph::ring("44", 44, None, &self.svg_colors)
+    }
+
+    fn colors(&self) -> &ChartColors {
+        self.config.colors()
+    }
+
+    fn set_colors(&mut self, colors: &ChartColors) {
+        *self.config.colors_mut() = *colors;
+        self.svg_colors.set_colors(colors);
+    }
+
+    fn color_choices(&self) -> Vec<(&'static str, ColorVariant)> {
+        (*super::COLOR_CHOICES_RING).into()
+    }
+
+    fn id(&self) -> Option<String> {
+        None
+    }
+
+    fn kind(&self) -> ChartKind {
+        ChartKind::Ring
+    }
+}
+
+impl Sensor for Filesystems {
+    fn update_config(&mut self, config: &dyn Any, _refresh_rate: u32) {
+        if let Some(cfg) = config.downcast_ref::<FilesystemsConfig>() {
+            self.config = cfg.clone();
+            self.svg_colors.set_colors(cfg.colors());
+
+            // Keep the fill history of mounts that are still selected
+            self.mounts.retain(|m| cfg.mounts.contains(&m.mount_point));
+            for mount in &cfg.mounts {
+                if !self.mounts.iter().any(|m| &m.mount_point == mount) {
+                    self.mounts.push(MountUsage::new(mount));
+                }
+            }
+            self.mounts.sort_by_key(|m| {
+                cfg.mounts
+                    .iter()
+                    .position(|p| p == &m.mount_point)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+    }
+
+    fn graph_kind(&self) -> ChartKind {
+        ChartKind::Ring
+    }
+
+    fn set_graph_kind(&mut self, kind: ChartKind) {
+        assert!(kind == ChartKind::Ring);
+    }
+
+    fn update(&mut self) {
+        let r = DiskRefreshKind::nothing().with_storage();
+        self.disks.refresh_specifics(true, r);
+        let now = Instant::now();
+
+        for mount in &mut self.mounts {
+            if let Some(disk) = self
+                .disks
+                .list()
+                .iter()
+                .find(|d| d.mount_point().to_string_lossy() == mount.mount_point)
+            {
+                mount.total = disk.total_space();
+                mount.available = disk.available_space();
+                let used = mount.used();
+                mount.fill.push_back((now, used));
+            } else {
+                mount.total = 0;
+                mount.available = 0;
+                mount.fill.clear();
+            }
+        }
+    }
+
+    fn demo_graph(&self) -> Box<dyn DemoGraph> {
+        let mut dmo = Filesystems::default();
+        dmo.update_config(&self.config, 0);
+        Box::new(dmo)
+    }
+
+    fn chart(
+        &'_ self,
+        _height_hint: u16,
+        _width_hint: u16,
+    ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
+        self.mount_chart(self.fullest_mount().unwrap_or(0))
+    }
+
+    fn settings_ui(&'_ self) -> Element<'_, crate::app::Message> {
+        let theme = cosmic::theme::active();
+        let cosmic = theme.cosmic();
+
+        let config = &self.config;
+
+        let mut fs_elements = Vec::new();
+
+        let mut charts = Vec::new();
+        for (idx, mount) in self.mounts.iter().enumerate() {
+            charts.push(Element::from(
+                column!(
+                    Container::new(self.mount_chart(idx).width(60).height(60))
+                        .width(90)
+                        .align_x(Alignment::Center),
+                    cosmic::widget::text::body(mount.mount_point.clone())
+                        .width(90)
+                        .align_x(Alignment::Center),
+                    cosmic::widget::text::body(self.mount_label(idx))
+                        .width(90)
+                        .align_x(Alignment::Center)
+                )
+                .padding(5)
+                .align_x(Alignment::Center),
+            ));
+        }
+        fs_elements.push(Element::from(Column::with_children(charts)));
+
+        fs_elements.push(Element::from(
+            column!(
+                settings::item(
+                    fl!("enable-chart"),
+                    toggler(config.chart_visible())
+                        .on_toggle(|value| { Message::ToggleFilesystemsChart(value) }),
+                ),
+                settings::item(
+                    fl!("enable-label"),
+                    toggler(config.label_visible())
+                        .on_toggle(|value| { Message::ToggleFilesystemsLabel(value) }),
+                ),
+                settings::item(
+                    fl!("filesystems-predict-full"),
+                    toggler(config.predict_full).on_toggle(Message::ToggleFilesystemsPredict),
+                ),
+                row!(
+                    widget::horizontal_space(),
+                    widget::button::standard(fl!("change-colors")).on_press(
+                        Message::ColorPickerOpen(DeviceKind::Filesystems, ChartKind::Ring, None)
+                    ),
+                    widget::horizontal_space()
+                ),
+                alerts::settings_ui(
+                    DeviceKind::Filesystems,
+                    None,
+                    &config.alerts,
+                    self.alert_unit()
+                ),
+                color_stops::settings_ui(
+                    DeviceKind::Filesystems,
+                    None,
+                    &config.color_stops,
+                    self.alert_unit(),
+                ),
+            )
+            .spacing(cosmic.space_xs()),
+        ));
+
+        let mut mount_items = Vec::new();
+        for (mount_point, fs) in self.available_mounts() {
+            let selected = config.mounts.contains(&mount_point);
+            let label = format!("{mount_point} ({fs})");
+            mount_items.push(
+                settings::item(
+                    label,
+                    widget::checkbox("", selected).on_toggle(move |value| {
+                        Message::ToggleFilesystemMount(mount_point.clone(), value)
+                    }),
+                )
+                .into(),
+            );
+        }
+
+        column!(
+            widget::text::heading(fl!("filesystems-title")),
+            Row::with_children(fs_elements).align_y(Alignment::Center),
+            widget::text::heading(fl!("filesystems-mounts")),
+            Column::with_children(mount_items).spacing(cosmic.space_xxs())
+        )
+        .spacing(cosmic::theme::spacing().space_xs)
+        .into()
+    }
+
+    // The fullest of the monitored filesystems
+    fn alert_value(&self) -> Option<f64> {
+        self.fullest_mount()
+            .map(|idx| self.mounts[idx].percentage())
+    }
+
+    fn alert_unit(&self) -> &'static str {
+        "%"
+    }
+}
+
+impl Default for Filesystems {
+    fn default() -> Self {
+        let disks =
+            DisksInfo::new_with_refreshed_list_specifics(DiskRefreshKind::nothing().with_storage());
+        let mut filesystems = Filesystems {
+            disks,
+            mounts: Vec::new(),
+            svg_colors: SvgColors::new(&ChartColors::default()),
+            config: FilesystemsConfig::default(),
+        };
+        let config = filesystems.config.clone();
+        filesystems.update_config(&config, 0);
+        filesystems
+    }
+}
+
+impl Filesystems {
+    pub fn mounts(&self) -> &[MountUsage] {
+        &self.mounts
+    }
+
+    /// Index of the fullest mounted filesystem, None if none of them is mounted
+    fn fullest_mount(&self) -> Option<usize> {
+        self.mounts
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.total > 0)
+            .max_by(|(_, a), (_, b)| a.percentage().total_cmp(&b.percentage()))
+            .map(|(idx, _)| idx)
+    }
+
+    /// Mount points that can be selected for monitoring, with their filesystem type
+    pub fn available_mounts(&self) -> Vec<(String, String)> {
+        let mut mounts: Vec<(String, String)> = self
+            .disks
+            .list()
+            .iter()
+            .filter(|d| d.total_space() > 0)
+            .filter_map(|d| {
+                let fs = d.file_system().to_string_lossy().to_string();
+                if IGNORED_FILESYSTEMS.contains(&fs.as_str()) {
+                    None
+                } else {
+                    Some((d.mount_point().to_string_lossy().to_string(), fs))
+                }
+            })
+            .collect();
+        mounts.sort();
+        mounts.dedup_by(|a, b| a.0 == b.0);
+        mounts
+    }
+
+    pub fn mount_chart(
+        &'_ self,
+        idx: usize,
+    ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
+        let pct = self.mounts.get(idx).map_or(0.0, MountUsage::percentage);
+        let value = format!("{}", pct.round());
+        let colors = self
+            .svg_colors
+            .with_stops(&self.config.color_stops, Some(pct));
+        let svg =
+            crate::svg_graph::ring(&value, pct.round().clamp(0.0, 100.0) as u8, None, &colors);
+
+        let icon = cosmic::widget::icon::from_svg_bytes(svg.into_bytes());
+        widget::Container::new(
+            icon.icon()
+                .height(cosmic::iced::Length::Fill)
+                .width(cosmic::iced::Length::Fill),
+        )
+    }
+
+    /// Used/total label, with the predicted time until full if enabled
+    pub fn mount_label(&self, idx: usize) -> String {
+        let Some(mount) = self.mounts.get(idx) else {
+            return String::from("-/- GB");
+        };
+
+        let mut label = mount.label();
+        if self.config.predict_full
+            && let Some(secs) = mount.time_until_full()
+        {
+            label.push_str(" (");
+            label.push_str(&Filesystems::format_duration(secs));
+            label.push(')');
+        }
+        label
+    }
+
+    fn format_duration(secs: u64) -> String {
+        let minutes = secs / 60;
+        let hours = minutes / 60;
+        let days = hours / 24;
+
+        if days > 0 {
+            format!("{days}d {}h", hours % 24)
+        } else if hours > 0 {
+            format!("{hours}h {}m", minutes % 60)
+        } else {
+            format!("{minutes}m")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn mount(total: u64, fill: &[(u64, u64)]) -> MountUsage {
+        let start = Instant::now();
+        let mut mount = MountUsage::new("/");
+        mount.total = total;
+        for (secs, used) in fill {
+            mount.available = total - used;
+            mount
+                .fill
+                .push_back((start + Duration::from_secs(*secs), *used));
+        }
+        mount
+    }
+
+    #[test]
+    fn time_until_full() {
+        let gb = GB as u64;
+        // 1 GB in 60 s with 10 GB left
+        let filling = mount(100 * gb, &[(0, 89 * gb), (30, 89 * gb), (60, 90 * gb)]);
+        assert_eq!(filling.time_until_full(), Some(600));
+
+        // Too short to tell
+        assert_eq!(
+            mount(100 * gb, &[(0, 89 * gb), (20, 90 * gb)]).time_until_full(),
+            None
+        );
+        // Steady or freed space
+        assert_eq!(
+            mount(100 * gb, &[(0, 90 * gb), (60, 90 * gb)]).time_until_full(),
+            None
+        );
+        assert_eq!(
+            mount(100 * gb, &[(0, 91 * gb), (60, 90 * gb)]).time_until_full(),
+            None
+        );
+        assert_eq!(mount(100 * gb, &[]).time_until_full(), None);
+    }
+
+    #[test]
+    fn format_duration() {
+        assert_eq!(Filesystems::format_duration(59), "0m");
+        assert_eq!(Filesystems::format_duration(45 * 60), "45m");
+        assert_eq!(Filesystems::format_duration(3 * 3600 + 5 * 60), "3h 5m");
+        assert_eq!(
+            Filesystems::format_duration(2 * 86400 + 7 * 3600 + 59),
+            "2d 7h"
+        );
+    }
+
+    #[test]
+    fn label() {
+        let gb = GB as u64;
+        assert_eq!(mount(1000 * gb, &[(0, 412 * gb)]).label(), "412/1000 GB");
+        assert_eq!(mount(8 * gb, &[(0, 2_500_000_000)]).label(), "2.5/8.0 GB");
+        assert_eq!(mount(0, &[]).label(), "0.0/0.0 GB");
+    }
+}
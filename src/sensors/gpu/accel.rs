@@ -0,0 +1,231 @@
+//! Compute accelerators under /sys/class/accel, like the Intel NPU (intel_vpu) and
+//! AMD XDNA (amdxdna). They have no temperature sensor or memory of their own,
+//! their buffers are allocated from system memory.
+
+use anyhow::{Result, anyhow};
+use log::{debug, info};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use super::fdinfo::{DrmUsage, GpuProcess};
+use super::pci_ids;
+use crate::sensors::gpus::Gpu;
+
+// Memory regions the accel drivers report in fdinfo
+const MEMORY_REGIONS: [&str; 2] = ["memory", "system"];
+
+pub struct AccelGpu {
+    name: String,
+    id: String,
+    slot: String,
+    // npu_busy_time_us of intel_vpu, other drivers only have fdinfo
+    busy_time_path: Option<PathBuf>,
+    busy_time: RefCell<Option<(Instant, u64)>>,
+    memory_total: u64,
+    usage: RefCell<DrmUsage>,
+    paused: bool,
+}
+
+impl AccelGpu {
+    pub fn new(name: String, id: String, device_path: &Path, slot: &str) -> Self {
+        let busy_time_path = Some(device_path.join("npu_busy_time_us")).filter(|p| p.exists());
+
+        AccelGpu {
+            name,
+            id,
+            slot: slot.to_string(),
+            busy_time_path,
+            busy_time: RefCell::new(None),
+            memory_total: AccelGpu::get_memory_total(),
+            usage: RefCell::new(DrmUsage::new(slot)),
+            paused: false,
+        }
+    }
+
+    fn read_u64<P: AsRef<Path>>(path: P) -> Option<u64> {
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    // Shared with the CPU, so the system memory is the upper bound
+    fn get_memory_total() -> u64 {
+        fs::read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|meminfo| {
+                meminfo.lines().find_map(|line| {
+                    line.strip_prefix("MemTotal:")?
+                        .trim()
+                        .trim_end_matches("kB")
+                        .trim()
+                        .parse::<u64>()
+                        .ok()
+                })
+            })
+            .map_or(0, |kb| kb * 1024)
+    }
+
+    // accel0, accel1... with their driver
+    fn get_accel_devices() -> Vec<(String, String)> {
+        let mut devices = Vec::new();
+        let Ok(entries) = fs::read_dir("/sys/class/accel/") else {
+            return devices;
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with("accel") {
+                continue;
+            }
+            let driver = fs::read_link(entry.path().join("device/driver"))
+                .ok()
+                .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_default();
+            devices.push((name, driver));
+        }
+        devices.sort();
+        devices
+    }
+
+    fn get_name(slot: &str, driver: &str) -> String {
+        if let Some(names) = pci_ids::lookup(slot) {
+            return pci_ids::clean_gpu_name(&names.vendor, &names.device);
+        }
+        match driver {
+            "intel_vpu" => "Intel NPU".to_string(),
+            "amdxdna" => "AMD XDNA NPU".to_string(),
+            _ => format!("NPU ({driver})"),
+        }
+    }
+
+    pub fn get_gpus(known: &HashSet<String>) -> Vec<Gpu> {
+        debug!("AccelGpu::get_gpus().");
+        let mut gpus = Vec::new();
+
+        for (accel, driver) in AccelGpu::get_accel_devices() {
+            let device_path = PathBuf::from(format!("/sys/class/accel/{accel}/device"));
+            let (Some(id), Some(slot)) = (
+                super::generate_device_id(&device_path),
+                super::device_pci_slot(&device_path),
+            ) else {
+                continue;
+            };
+            if known.contains(&slot) {
+                continue;
+            }
+            let name = AccelGpu::get_name(&slot, &driver);
+            info!("Found accelerator {name} ({accel}, {driver}, {slot})");
+            gpus.push(Gpu::new(Box::new(AccelGpu::new(
+                name,
+                id,
+                &device_path,
+                &slot,
+            ))));
+        }
+        gpus
+    }
+
+    // Share of the time busy since the previous sample, in percent
+    fn busy_time_usage(&self, path: &Path) -> Result<f64> {
+        let busy_us = AccelGpu::read_u64(path).ok_or_else(|| anyhow!("No NPU busy time"))?;
+        let now = Instant::now();
+        let previous = self.busy_time.borrow_mut().replace((now, busy_us));
+
+        match previous {
+            Some((time, prev)) if busy_us >= prev => {
+                let elapsed_us = now.duration_since(time).as_micros() as u64;
+                if elapsed_us == 0 {
+                    return Err(anyhow!("NPU busy time sampled too quickly"));
+                }
+                Ok(((busy_us - prev) as f64 / elapsed_us as f64 * 100.0).min(100.0))
+            }
+            _ => Ok(0.0),
+        }
+    }
+}
+
+impl super::GpuIf for AccelGpu {
+    fn restart(&mut self) {
+        debug!("AccelGpu::restart({}).", self.name);
+        self.paused = false;
+    }
+
+    fn stop(&mut self) {
+        debug!("AccelGpu::stop({}).", self.name);
+        self.paused = true;
+        self.usage.borrow_mut().reset();
+        *self.busy_time.borrow_mut() = None;
+    }
+
+    fn is_active(&self) -> bool {
+        !self.paused
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    // The clients are rescanned on every update either way, they're needed for the memory
+    fn usage(&self) -> Result<u32> {
+        if !self.is_active() {
+            return Err(anyhow!("Accelerator paused"));
+        }
+        let fdinfo_usage = self.usage.borrow_mut().refresh();
+        let usage = match &self.busy_time_path {
+            Some(path) => self.busy_time_usage(path)?,
+            None => fdinfo_usage,
+        };
+        Ok(usage.round() as u32)
+    }
+
+    fn temperature(&self) -> Result<u32> {
+        Err(anyhow!("Accelerators have no temperature sensor"))
+    }
+
+    fn has_temperature(&self) -> bool {
+        false
+    }
+
+    fn is_accelerator(&self) -> bool {
+        true
+    }
+
+    fn vram_total(&self) -> u64 {
+        self.memory_total
+    }
+
+    // Buffers of all visible clients
+    fn vram_used(&self) -> Result<u64> {
+        if !self.is_active() {
+            return Err(anyhow!("Accelerator paused"));
+        }
+        Ok(self.usage.borrow().memory(&MEMORY_REGIONS))
+    }
+
+    fn pci_slot(&self) -> Option<String> {
+        Some(self.slot.clone())
+    }
+
+    // usage() already rescans the clients on every update
+    fn processes(&self) -> Vec<GpuProcess> {
+        if !self.is_active() {
+            return Vec::new();
+        }
+        self.usage.borrow().processes(&MEMORY_REGIONS)
+    }
+}
+
+impl std::fmt::Debug for AccelGpu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "AccelGpu {{ name: {}, id: {}, slot: {}, paused: {} }}",
+            self.name, self.id, self.slot, self.paused
+        )
+    }
+}
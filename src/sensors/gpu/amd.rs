@@ -1,14 +1,15 @@
 use anyhow::{Context, Result, anyhow};
-use hex;
 use log::{debug, info};
-use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use std::sync::LazyLock;
 
+use super::fdinfo::{DrmUsage, GpuProcess};
+use super::pci_ids;
+use crate::config::GpuMetric;
 use crate::sensors::gpus::Gpu;
 //use log::{debug, warn};
 
@@ -20,6 +21,12 @@ pub struct AmdGpu {
     power_status_path: String,
     temp_input_path: Option<String>,
     vram_total: u64,
+    device_path: PathBuf,
+    hwmon_path: Option<PathBuf>,
+    // Metrics found when the GPU was detected, with the file each one is read from
+    metric_paths: Vec<(GpuMetric, PathBuf)>,
+    slot: Option<String>,
+    clients: RefCell<DrmUsage>,
     paused: bool,
 }
 
@@ -27,6 +34,10 @@ impl AmdGpu {
     pub fn new(name: &str, card: &str, id: &str, vram_total: u64) -> Self {
         let base = format!("/sys/class/drm/{card}/device");
         let temp_input_path = AmdGpu::find_temp_input_path(card);
+        let device_path = PathBuf::from(&base);
+        let hwmon_path = AmdGpu::find_hwmon_path(&device_path);
+        let metric_paths = AmdGpu::find_metric_paths(&device_path, hwmon_path.as_deref());
+        let slot = super::pci_slot(card);
         Self {
             name: name.to_string(),
             id: id.to_string(),
@@ -35,6 +46,11 @@ impl AmdGpu {
             power_status_path: format!("{base}/power/runtime_status"),
             temp_input_path,
             vram_total,
+            device_path,
+            hwmon_path,
+            metric_paths,
+            slot: slot.clone(),
+            clients: RefCell::new(DrmUsage::new(&slot.unwrap_or_default())),
             paused: false,
         }
     }
@@ -101,64 +117,101 @@ impl AmdGpu {
         None
     }
 
-    fn get_vram_total(card: &str) -> Option<u64> {
-        let path = format!("/sys/class/drm/{card}/device/mem_info_vram_total");
-        Self::parse_u64_file(&path)
+    fn find_hwmon_path(device_path: &Path) -> Option<PathBuf> {
+        fs::read_dir(device_path.join("hwmon"))
+            .ok()?
+            .flatten()
+            .next()
+            .map(|e| e.path())
     }
 
-    fn get_pci_slot(card: &str) -> Option<String> {
-        let path = format!("/sys/class/drm/{card}/device/uevent");
-        Self::read_file_to_string(path)
-            .ok()?
-            .lines()
-            .find_map(|line| {
-                line.strip_prefix("PCI_SLOT_NAME=")
-                    .map(|s| s.to_lowercase().to_string())
+    // Find the temperature input with the given label, amdgpu names them edge, junction and mem
+    fn find_temp_by_label(hwmon: &Path, label: &str) -> Option<PathBuf> {
+        (1..=3)
+            .find(|i| {
+                Self::read_file_to_string(hwmon.join(format!("temp{i}_label")))
+                    .is_ok_and(|l| l == label)
             })
+            .map(|i| hwmon.join(format!("temp{i}_input")))
     }
 
-    fn get_lspci_gpu_names() -> Vec<(String, String)> {
-        fn clean_gpu_name(model: &str) -> String {
-            let (_, truncated) = model.split_once("]:").unwrap_or((model, model));
-            let truncated = truncated.split("[1002:").next().unwrap_or(model);
-            truncated
-                .replace("Corporation", "")
-                .replace("[AMD/ATI]", "")
-                .replace("compatible controller", "")
-                .replace("controller", "")
-                .replace("VGA", "")
-                .replace("3D", "")
-                .replace("Display", "")
-                .replace(':', "")
-                .replace("  ", " ")
-                .replace('[', "(")
-                .replace(']', ")")
-                .trim()
-                .to_string()
+    fn find_metric_paths(device_path: &Path, hwmon: Option<&Path>) -> Vec<(GpuMetric, PathBuf)> {
+        let mut paths = Vec::new();
+
+        if let Some(hwmon) = hwmon {
+            // Older kernels have power1_average, APUs and newer dGPUs power1_input
+            if let Some(power) = ["power1_average", "power1_input"]
+                .iter()
+                .map(|f| hwmon.join(f))
+                .find(|p| p.exists())
+            {
+                paths.push((GpuMetric::Power, power));
+            }
+            if hwmon.join("fan1_input").exists() {
+                paths.push((GpuMetric::Fan, hwmon.join("fan1_input")));
+            } else if hwmon.join("pwm1").exists() {
+                paths.push((GpuMetric::Fan, hwmon.join("pwm1")));
+            }
+            if let Some(path) = Self::find_temp_by_label(hwmon, "junction") {
+                paths.push((GpuMetric::TempJunction, path));
+            }
+            if let Some(path) = Self::find_temp_by_label(hwmon, "mem") {
+                paths.push((GpuMetric::TempMemory, path));
+            }
         }
 
-        let mut map = Vec::new();
-        let output = Command::new("lspci").arg("-nn").output();
-        let Ok(output) = output else {
-            return map;
-        };
-        let Ok(stdout) = String::from_utf8(output.stdout) else {
-            return map;
-        };
+        for (metric, file) in [
+            (GpuMetric::CoreClock, "pp_dpm_sclk"),
+            (GpuMetric::MemoryClock, "pp_dpm_mclk"),
+            (GpuMetric::MemoryBusy, "mem_busy_percent"),
+            (GpuMetric::Gtt, "mem_info_gtt_used"),
+        ] {
+            let path = device_path.join(file);
+            if path.exists() {
+                paths.push((metric, path));
+            }
+        }
 
-        for line in stdout.lines() {
-            if (line.contains("VGA") || line.contains("Display") || line.contains("3D"))
-                && let Some((slot, rest)) = line.split_once(' ')
-            {
-                let model = rest.trim();
-                let name = clean_gpu_name(model);
-                map.push((slot.to_lowercase().to_string(), name));
+        info!(
+            "AMD metrics found: {:?}",
+            paths.iter().map(|(m, _)| m).collect::<Vec<_>>()
+        );
+        paths
+    }
+
+    // pp_dpm_sclk lists the DPM levels like "1: 1200Mhz *", the current one is marked
+    fn parse_dpm_levels(content: &str) -> (Option<f64>, Option<f64>) {
+        let mut current = None;
+        let mut max: Option<f64> = None;
+        for line in content.lines() {
+            let Some(mhz) = line
+                .split_whitespace()
+                .nth(1)
+                .and_then(|f| f.to_lowercase().strip_suffix("mhz")?.parse::<f64>().ok())
+            else {
+                continue;
+            };
+            if line.trim_end().ends_with('*') {
+                current = Some(mhz);
             }
+            max = Some(max.map_or(mhz, |m| m.max(mhz)));
         }
-        map
+        (current, max)
+    }
+
+    fn metric_path(&self, metric: GpuMetric) -> Option<&Path> {
+        self.metric_paths
+            .iter()
+            .find(|(m, _)| *m == metric)
+            .map(|(_, p)| p.as_path())
     }
 
-    fn get_gpu_name(card: &str, lspci_map: &Vec<(String, String)>) -> String {
+    fn get_vram_total(card: &str) -> Option<u64> {
+        let path = format!("/sys/class/drm/{card}/device/mem_info_vram_total");
+        Self::parse_u64_file(&path)
+    }
+
+    fn get_gpu_name(card: &str) -> String {
         info!("Resolving GPU name for card: {card}");
 
         // Use static lookup table first, with nice names
@@ -174,58 +227,46 @@ impl AmdGpu {
             debug!("Failed to read device ID from path: {device_id_path}");
         }
 
-        // Fallback: Get PCI slot and look for it in the lspci list
-        if let Some(slot) = &AmdGpu::get_pci_slot(card) {
+        // Fallback: Get PCI slot and look it up in pci.ids
+        if let Some(slot) = &super::pci_slot(card) {
             info!("Resolved PCI slot for card {card}: {slot:?}");
-            for (p, n) in lspci_map {
-                if slot.contains(p) {
-                    info!("Found name in lspci_map: {n}");
-                    return n.clone();
-                }
+            if let Some(names) = pci_ids::lookup(slot) {
+                // A device entry often covers several models, the board may tell which one it is
+                let device = match names.subsystem {
+                    Some(board) if names.device.contains('/') => {
+                        let chip = names.device.split(" [").next().unwrap_or(&names.device);
+                        format!("{chip} [{board}]")
+                    }
+                    _ => names.device,
+                };
+                let name = pci_ids::clean_gpu_name(&names.vendor, &device);
+                info!("Found name in pci.ids: {name}");
+                return name;
             }
-            debug!("No entry in lspci_map for slot: {slot}");
+            debug!("No entry in pci.ids for slot: {slot}");
         }
 
         debug!("Falling back to unknown GPU name");
         "Unknown AMD GPU".to_string()
     }
 
-    fn generate_gpu_id(card: &str) -> Option<String> {
-        let device_path = PathBuf::from(format!("/sys/class/drm/{card}/device"));
-        let pci_address = device_path.canonicalize().ok()?;
-        let subsystem_vendor =
-            Self::read_file_to_string(device_path.join("subsystem_vendor")).ok()?;
-        let subsystem_device =
-            Self::read_file_to_string(device_path.join("subsystem_device")).ok()?;
-
-        let mut hasher = Sha256::new();
-        hasher.update(pci_address.to_string_lossy().as_bytes());
-        hasher.update(subsystem_vendor.as_bytes());
-        hasher.update(subsystem_device.as_bytes());
-
-        Some(hex::encode(hasher.finalize()))
-    }
-
-    pub fn get_gpus() -> Vec<Gpu> {
+    pub fn get_gpus(known: &HashSet<String>) -> Vec<Gpu> {
         debug!("AmdGpu::get_gpus().");
 
         let mut gpus = Vec::new();
 
-        let lspci_map = AmdGpu::get_lspci_gpu_names();
-        debug!("Available lspci_map entries:");
-        for (k, v) in &lspci_map {
-            debug!("  {k} -> {v}");
-        }
-
         let cards = AmdGpu::get_amd_cards();
 
         for card in cards {
             debug!("                    Found card {card}");
+            if super::pci_slot(&card).is_some_and(|slot| known.contains(&slot)) {
+                continue;
+            }
             if let Some(vram_total) = AmdGpu::get_vram_total(&card) {
                 debug!("                    total vram {vram_total}");
-                if let Some(id) = AmdGpu::generate_gpu_id(&card) {
+                if let Some(id) = super::generate_gpu_id(&card) {
                     debug!("                    id {id}");
-                    let name = AmdGpu::get_gpu_name(&card, &lspci_map);
+                    let name = AmdGpu::get_gpu_name(&card);
                     debug!("                    name {name}");
                     gpus.push(Gpu::new(Box::new(AmdGpu::new(
                         &name, &card, &id, vram_total,
@@ -246,6 +287,7 @@ impl super::GpuIf for AmdGpu {
     fn stop(&mut self) {
         debug!("AmdGpu::stop({}).", self.name);
         self.paused = true;
+        self.clients.borrow_mut().reset();
     }
 
     fn is_active(&self) -> bool {
@@ -305,6 +347,89 @@ impl super::GpuIf for AmdGpu {
         }
         Ok(Self::parse_u64_file(&self.vram_used_path).unwrap_or(0))
     }
+
+    fn pci_slot(&self) -> Option<String> {
+        self.slot.clone()
+    }
+
+    // Unlike usage this doesn't need the GPU awake, fdinfo is kept by the kernel
+    fn processes(&self) -> Vec<GpuProcess> {
+        if !self.is_active() {
+            return Vec::new();
+        }
+        let mut clients = self.clients.borrow_mut();
+        clients.refresh();
+        clients.processes(&["vram"])
+    }
+
+    fn metrics(&self) -> Vec<GpuMetric> {
+        self.metric_paths.iter().map(|(m, _)| *m).collect()
+    }
+
+    fn metric(&self, metric: GpuMetric) -> Result<f64> {
+        if !self.is_active() {
+            return Err(anyhow!("AMD device paused"));
+        }
+        // Reading the DPM tables or sensors would wake up a sleeping GPU
+        if !self.powered_on() {
+            return Ok(0.0);
+        }
+        let path = self
+            .metric_path(metric)
+            .with_context(|| format!("{metric:?} not available"))?;
+        let content = Self::read_file_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let value = match metric {
+            GpuMetric::CoreClock | GpuMetric::MemoryClock => AmdGpu::parse_dpm_levels(&content)
+                .0
+                .context("No active DPM level")?,
+            _ => content.parse::<f64>().context("Failed to parse metric")?,
+        };
+
+        Ok(match metric {
+            // microwatts
+            GpuMetric::Power => value / 1_000_000.0,
+            GpuMetric::Fan => {
+                let hwmon = self.hwmon_path.as_ref().context("No AMD hwmon")?;
+                if path.ends_with("pwm1") {
+                    value / 255.0 * 100.0
+                } else {
+                    let max = Self::parse_u32_file(&hwmon.join("fan1_max").to_string_lossy())
+                        .context("No maximum fan speed")?;
+                    (value / f64::from(max.max(1)) * 100.0).min(100.0)
+                }
+            }
+            GpuMetric::Gtt => value / 1_073_741_824.0,
+            // millidegrees
+            GpuMetric::TempJunction | GpuMetric::TempMemory => value / 1000.0,
+            _ => value,
+        })
+    }
+
+    fn metric_max(&self, metric: GpuMetric) -> Option<f64> {
+        match metric {
+            GpuMetric::Power => {
+                let cap = self.hwmon_path.as_ref()?.join("power1_cap");
+                Some(Self::parse_u64_file(&cap.to_string_lossy())? as f64 / 1_000_000.0)
+            }
+            GpuMetric::CoreClock | GpuMetric::MemoryClock => {
+                let content = Self::read_file_to_string(self.metric_path(metric)?).ok()?;
+                AmdGpu::parse_dpm_levels(&content).1
+            }
+            GpuMetric::Gtt => {
+                let total = self.device_path.join("mem_info_gtt_total");
+                Some(Self::parse_u64_file(&total.to_string_lossy())? as f64 / 1_073_741_824.0)
+            }
+            // temp2_input has its limit in temp2_crit, in millidegrees
+            GpuMetric::TempJunction | GpuMetric::TempMemory => {
+                let input = self.metric_path(metric)?.to_string_lossy().to_string();
+                let crit = input.strip_suffix("_input")?.to_owned() + "_crit";
+                Some(Self::parse_u64_file(&crit)? as f64 / 1000.0)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Debug for AmdGpu {
@@ -383,3 +508,24 @@ pub static AMD_GPU_DEVICE_IDS: LazyLock<HashMap<&'static str, &'static str>> =
 
         m
     });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dpm_levels() {
+        let sclk = "0: 500Mhz\n1: 1200Mhz *\n2: 2410Mhz\n";
+        assert_eq!(AmdGpu::parse_dpm_levels(sclk), (Some(1200.0), Some(2410.0)));
+
+        // Some cards report the deep sleep level as S and use MHz
+        let sclk = "S: 19Mhz *\n0: 500MHz\n1: 2700MHz\n";
+        assert_eq!(AmdGpu::parse_dpm_levels(sclk), (Some(19.0), Some(2700.0)));
+
+        // Idle between levels: no level is current
+        let mclk = "0: 96Mhz\n1: 1000Mhz\n";
+        assert_eq!(AmdGpu::parse_dpm_levels(mclk), (None, Some(1000.0)));
+
+        assert_eq!(AmdGpu::parse_dpm_levels(""), (None, None));
+    }
+}
@@ -0,0 +1,490 @@
+//! GPU usage from the DRM fdinfo interface, see the kernel's
+//! Documentation/gpu/drm-usage-stats.rst. The keys are common to amdgpu, i915, xe and nouveau,
+//! and the accel drivers intel_vpu and amdxdna.
+//! Only processes the user may inspect are visible.
+
+use log::debug;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::time::Instant;
+
+/// One DRM client (an open render node context) as reported in /proc/<pid>/fdinfo/<fd>
+#[derive(Debug, Default, Clone)]
+pub struct DrmClient {
+    pub pid: u32,
+    pub driver: String,
+    pub pdev: String,
+    pub client_id: u64,
+    /// drm-engine-<class>: busy time in nanoseconds
+    pub engines: HashMap<String, u64>,
+    /// drm-engine-capacity-<class>: number of engines of that class
+    pub capacity: HashMap<String, u64>,
+    /// drm-cycles-<class> and drm-total-cycles-<class>
+    pub cycles: HashMap<String, (u64, u64)>,
+    /// Resident memory per region in bytes
+    pub memory: HashMap<String, u64>,
+}
+
+impl DrmClient {
+    fn parse(pid: u32, content: &str) -> Option<DrmClient> {
+        let mut client = DrmClient {
+            pid,
+            ..Default::default()
+        };
+        let mut resident = HashMap::new();
+        let mut legacy = HashMap::new();
+        let mut has_id = false;
+
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            if key == "drm-driver" {
+                client.driver = value.to_string();
+            } else if key == "drm-pdev" {
+                client.pdev = value.to_lowercase();
+            } else if key == "drm-client-id" {
+                client.client_id = value.parse().ok()?;
+                has_id = true;
+            } else if let Some(class) = key.strip_prefix("drm-engine-capacity-") {
+                client
+                    .capacity
+                    .insert(class.to_string(), parse_number(value));
+            } else if let Some(class) = key.strip_prefix("drm-engine-") {
+                client
+                    .engines
+                    .insert(class.to_string(), parse_number(value));
+            } else if let Some(class) = key.strip_prefix("drm-total-cycles-") {
+                client.cycles.entry(class.to_string()).or_default().1 = parse_number(value);
+            } else if let Some(class) = key.strip_prefix("drm-cycles-") {
+                client.cycles.entry(class.to_string()).or_default().0 = parse_number(value);
+            } else if let Some(region) = key.strip_prefix("drm-resident-") {
+                resident.insert(region.to_string(), parse_bytes(value));
+            } else if let Some(region) = key.strip_prefix("drm-memory-") {
+                legacy.insert(region.to_string(), parse_bytes(value));
+            }
+        }
+
+        // drm-memory-<region> is the legacy name of drm-resident-<region>
+        client.memory = if resident.is_empty() {
+            legacy
+        } else {
+            resident
+        };
+
+        has_id.then_some(client)
+    }
+
+    /// Utilisation per engine class since the previous sample of the same client, in percent
+    pub fn utilization(
+        &self,
+        previous: Option<&DrmClient>,
+        elapsed_ns: u64,
+    ) -> HashMap<String, f64> {
+        let mut util = HashMap::new();
+
+        for (class, busy) in &self.engines {
+            if elapsed_ns == 0 {
+                break;
+            }
+            let prev = previous
+                .and_then(|p| p.engines.get(class))
+                .copied()
+                .unwrap_or(0);
+            let capacity = self.capacity.get(class).copied().unwrap_or(1).max(1);
+            let pct = busy.saturating_sub(prev) as f64 / (elapsed_ns * capacity) as f64 * 100.0;
+            util.insert(class.clone(), pct);
+        }
+
+        for (class, (cycles, total)) in &self.cycles {
+            let (prev_cycles, prev_total) = previous
+                .and_then(|p| p.cycles.get(class))
+                .copied()
+                .unwrap_or((0, 0));
+            let total_delta = total.saturating_sub(prev_total);
+            if total_delta == 0 {
+                continue;
+            }
+            let capacity = self.capacity.get(class).copied().unwrap_or(1).max(1);
+            let pct =
+                cycles.saturating_sub(prev_cycles) as f64 / (total_delta * capacity) as f64 * 100.0;
+            util.insert(class.clone(), pct);
+        }
+
+        util
+    }
+}
+
+// "123 ns" or "123"
+fn parse_number(value: &str) -> u64 {
+    value
+        .split_whitespace()
+        .next()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
+
+// "123 KiB", "4 MiB" or plain bytes
+fn parse_bytes(value: &str) -> u64 {
+    let mut parts = value.split_whitespace();
+    let number: u64 = parts.next().and_then(|v| v.parse().ok()).unwrap_or(0);
+    match parts.next() {
+        Some("KiB") => number * 1024,
+        Some("MiB") => number * 1024 * 1024,
+        Some("GiB") => number * 1024 * 1024 * 1024,
+        _ => number,
+    }
+}
+
+/// All DRM clients visible in /proc, optionally only those on one PCI device.
+/// A client shared through several fds (dup, fork) is only reported once.
+pub fn scan_clients(pdev: Option<&str>) -> Vec<DrmClient> {
+    let mut clients = Vec::new();
+    let mut seen = HashSet::new();
+
+    let Ok(procs) = fs::read_dir("/proc") else {
+        return clients;
+    };
+
+    for proc in procs.flatten() {
+        let Some(pid) = proc
+            .file_name()
+            .to_str()
+            .and_then(|p| p.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(proc.path().join("fd")) else {
+            continue;
+        };
+
+        for fd in fds.flatten() {
+            let Ok(target) = fs::read_link(fd.path()) else {
+                continue;
+            };
+            // Compute accelerators (NPUs) use the same fdinfo keys
+            if !target.starts_with("/dev/dri/") && !target.starts_with("/dev/accel/") {
+                continue;
+            }
+
+            let fdinfo = proc.path().join("fdinfo").join(fd.file_name());
+            let Ok(content) = fs::read_to_string(&fdinfo) else {
+                continue;
+            };
+            let Some(client) = DrmClient::parse(pid, &content) else {
+                continue;
+            };
+
+            if pdev.is_some_and(|p| p != client.pdev) {
+                continue;
+            }
+            if seen.insert((client.pdev.clone(), client.client_id)) {
+                clients.push(client);
+            }
+        }
+    }
+
+    clients
+}
+
+/// GPU usage of one process, summed over its DRM clients
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuProcess {
+    pub pid: u32,
+    pub name: String,
+    /// Busiest engine class in percent
+    pub usage: f64,
+    /// Resident memory in bytes
+    pub memory: u64,
+}
+
+/// Tracks the fdinfo counters of one GPU between updates
+#[derive(Debug)]
+pub struct DrmUsage {
+    pdev: String,
+    clients: HashMap<u64, DrmClient>,
+    // Busiest engine class per client, in percent
+    client_usage: HashMap<u64, f64>,
+    time: Option<Instant>,
+}
+
+impl DrmUsage {
+    pub fn new(pdev: &str) -> Self {
+        DrmUsage {
+            pdev: pdev.to_lowercase(),
+            clients: HashMap::new(),
+            client_usage: HashMap::new(),
+            time: None,
+        }
+    }
+
+    /// Rescan the clients, returns the utilisation of the busiest engine class in percent.
+    /// The first call has nothing to compare with and returns 0.
+    pub fn refresh(&mut self) -> f64 {
+        let now = Instant::now();
+        let current = scan_clients(Some(&self.pdev));
+
+        let mut per_class: HashMap<String, f64> = HashMap::new();
+        self.client_usage.clear();
+
+        if let Some(time) = self.time {
+            let elapsed_ns = now.duration_since(time).as_nanos() as u64;
+            for client in &current {
+                let util = client.utilization(self.clients.get(&client.client_id), elapsed_ns);
+                let busiest = util.values().copied().fold(0.0, f64::max);
+                self.client_usage
+                    .insert(client.client_id, busiest.min(100.0));
+                for (class, pct) in util {
+                    *per_class.entry(class).or_default() += pct;
+                }
+            }
+        }
+
+        debug!("DrmUsage::refresh({}) {per_class:?}", self.pdev);
+
+        self.clients = current.into_iter().map(|c| (c.client_id, c)).collect();
+        self.time = Some(now);
+
+        per_class.values().copied().fold(0.0, f64::max).min(100.0)
+    }
+
+    /// Resident memory of all clients in the regions starting with any of the prefixes
+    pub fn memory(&self, region_prefixes: &[&str]) -> u64 {
+        self.clients
+            .values()
+            .flat_map(|c| c.memory.iter())
+            .filter(|(region, _)| region_prefixes.iter().any(|p| region.starts_with(p)))
+            .map(|(_, bytes)| bytes)
+            .sum()
+    }
+
+    /// Busiest engine class of a client during the last interval, in percent
+    pub fn client_usage(&self, client_id: u64) -> f64 {
+        self.client_usage.get(&client_id).copied().unwrap_or(0.0)
+    }
+
+    /// Processes using the GPU, busiest first. Memory counts the regions starting with any of the prefixes.
+    pub fn processes(&self, region_prefixes: &[&str]) -> Vec<GpuProcess> {
+        let mut by_pid: HashMap<u32, GpuProcess> = HashMap::new();
+
+        for client in self.clients.values() {
+            let process = by_pid.entry(client.pid).or_insert_with(|| GpuProcess {
+                pid: client.pid,
+                name: fs::read_to_string(format!("/proc/{}/comm", client.pid))
+                    .map(|n| n.trim().to_string())
+                    .unwrap_or_else(|_| client.pid.to_string()),
+                usage: 0.0,
+                memory: 0,
+            });
+            process.usage = (process.usage + self.client_usage(client.client_id)).min(100.0);
+            process.memory += client
+                .memory
+                .iter()
+                .filter(|(region, _)| region_prefixes.iter().any(|p| region.starts_with(p)))
+                .map(|(_, bytes)| bytes)
+                .sum::<u64>();
+        }
+
+        let mut processes: Vec<GpuProcess> = by_pid.into_values().collect();
+        processes.sort_by(|a, b| {
+            b.usage
+                .total_cmp(&a.usage)
+                .then(b.memory.cmp(&a.memory))
+                .then(a.pid.cmp(&b.pid))
+        });
+        processes
+    }
+
+    /// Forget previous counters, so a resumed GPU doesn't report the paused time as idle
+    pub fn reset(&mut self) {
+        self.clients.clear();
+        self.client_usage.clear();
+        self.time = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Older amdgpu kernels still use the legacy drm-memory- keys
+    const AMDGPU: &str = "\
+pos:\t0
+flags:\t02100002
+mnt_id:\t24
+drm-driver:\tamdgpu
+drm-pdev:\t0000:03:00.0
+drm-client-id:\t42
+drm-memory-vram:\t524288 KiB
+drm-memory-gtt:\t2048 KiB
+drm-memory-cpu:\t0 KiB
+drm-engine-gfx:\t1500000000 ns
+drm-engine-compute:\t0 ns
+";
+
+    const I915: &str = "\
+drm-driver:\ti915
+drm-pdev:\t0000:00:02.0
+drm-client-id:\t7
+drm-total-local0:\t0
+drm-resident-system0:\t16 MiB
+drm-engine-render:\t2000000000 ns
+drm-engine-video:\t1000000000 ns
+drm-engine-capacity-video:\t2
+drm-engine-video-enhance:\t0 ns
+";
+
+    // xe counts GPU cycles instead of busy time
+    const XE: &str = "\
+drm-driver:\txe
+drm-pdev:\t0000:03:00.0
+drm-client-id:\t12
+drm-resident-system:\t8 MiB
+drm-resident-vram0:\t256 MiB
+drm-cycles-rcs:\t1100
+drm-total-cycles-rcs:\t2400
+drm-cycles-ccs:\t800
+drm-total-cycles-ccs:\t1400
+drm-engine-capacity-ccs:\t4
+";
+
+    // nouveau has no engine busy time on older kernels
+    const NOUVEAU: &str = "\
+drm-driver:\tnouveau
+drm-pdev:\t0000:01:00.0
+drm-client-id:\t3
+drm-total-vram:\t64 MiB
+drm-resident-vram:\t64 MiB
+drm-resident-gtt:\t4 MiB
+";
+
+    fn client(pid: u32, content: &str) -> DrmClient {
+        DrmClient::parse(pid, content).unwrap()
+    }
+
+    #[test]
+    fn parse_drivers() {
+        let amd = client(100, AMDGPU);
+        assert_eq!(
+            (amd.driver.as_str(), amd.pdev.as_str(), amd.client_id),
+            ("amdgpu", "0000:03:00.0", 42)
+        );
+        assert_eq!(amd.engines["gfx"], 1_500_000_000);
+        assert_eq!(amd.memory["vram"], 512 * 1024 * 1024);
+        assert_eq!(amd.memory["gtt"], 2 * 1024 * 1024);
+
+        let intel = client(100, I915);
+        assert_eq!(intel.capacity["video"], 2);
+        assert!(!intel.engines.contains_key("capacity-video"));
+        assert_eq!(intel.engines["video-enhance"], 0);
+        // drm-total- is allocated, not resident memory
+        assert_eq!(intel.memory.len(), 1);
+        assert_eq!(intel.memory["system0"], 16 * 1024 * 1024);
+
+        let xe = client(100, XE);
+        assert!(xe.engines.is_empty());
+        assert_eq!(xe.cycles["rcs"], (1100, 2400));
+        assert_eq!(xe.memory["vram0"], 256 * 1024 * 1024);
+
+        let nouveau = client(100, NOUVEAU);
+        assert!(nouveau.engines.is_empty() && nouveau.cycles.is_empty());
+        assert_eq!(nouveau.memory["gtt"], 4 * 1024 * 1024);
+
+        // Not a DRM fd, or no usable client id
+        assert!(DrmClient::parse(100, "pos:\t0\nflags:\t02\n").is_none());
+        assert!(DrmClient::parse(100, "drm-driver:\ti915\ndrm-client-id:\tx\n").is_none());
+    }
+
+    #[test]
+    fn engine_time_utilization() {
+        let previous = client(100, &AMDGPU.replace("1500000000", "1000000000"));
+        let util = client(100, AMDGPU).utilization(Some(&previous), 1_000_000_000);
+        assert_eq!(util["gfx"], 50.0);
+        assert_eq!(util["compute"], 0.0);
+        assert!(
+            client(100, AMDGPU)
+                .utilization(Some(&previous), 0)
+                .is_empty()
+        );
+
+        // Busy time is summed over the engines of a class
+        let previous = client(100, &I915.replace("1000000000", "0"));
+        let util = client(100, I915).utilization(Some(&previous), 1_000_000_000);
+        assert_eq!(util["video"], 50.0);
+    }
+
+    #[test]
+    fn cycle_utilization() {
+        let previous = client(
+            100,
+            &XE.replace("\t1100", "\t1000")
+                .replace("\t2400", "\t2000")
+                .replace("\t800", "\t400")
+                .replace("\t1400", "\t1000"),
+        );
+        let util = client(100, XE).utilization(Some(&previous), 1_000_000_000);
+        assert_eq!(util["rcs"], 25.0);
+        // 400 cycles of 4 engines in 400 total
+        assert_eq!(util["ccs"], 25.0);
+
+        // No cycles elapsed
+        assert!(
+            client(100, XE)
+                .utilization(Some(&client(100, XE)), 1)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn memory_and_processes() {
+        // pids without a /proc entry are named by their pid
+        let (a, b) = (u32::MAX - 1, u32::MAX);
+        let mut first = client(a, I915);
+        first.memory.insert(String::from("local0"), 100);
+        let second = client(a, &I915.replace("client-id:\t7", "client-id:\t8"));
+        let other = client(b, &I915.replace("client-id:\t7", "client-id:\t9"));
+
+        let mut usage = DrmUsage::new("0000:00:02.0");
+        usage.clients = [first, second, other]
+            .into_iter()
+            .map(|c| (c.client_id, c))
+            .collect();
+        usage.client_usage = HashMap::from([(7, 70.0), (8, 40.0), (9, 20.0)]);
+
+        assert_eq!(usage.memory(&["local"]), 100);
+        assert_eq!(
+            usage.memory(&["system", "local"]),
+            3 * 16 * 1024 * 1024 + 100
+        );
+
+        let processes = usage.processes(&["local"]);
+        assert_eq!(
+            processes,
+            [
+                GpuProcess {
+                    pid: a,
+                    name: a.to_string(),
+                    usage: 100.0,
+                    memory: 100,
+                },
+                GpuProcess {
+                    pid: b,
+                    name: b.to_string(),
+                    usage: 20.0,
+                    memory: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn byte_units() {
+        assert_eq!(parse_bytes("123"), 123);
+        assert_eq!(parse_bytes("2 KiB"), 2048);
+        assert_eq!(parse_bytes("3 MiB"), 3 * 1024 * 1024);
+        assert_eq!(parse_bytes("1 GiB"), 1024 * 1024 * 1024);
+        assert_eq!(parse_bytes("junk"), 0);
+    }
+}
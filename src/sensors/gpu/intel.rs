@@ -1,31 +1,188 @@
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
+use log::{debug, info};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
+use super::fdinfo::{DrmUsage, GpuProcess};
+use super::pci_ids;
+use crate::config::GpuMetric;
 use crate::sensors::gpus::Gpu;
-//use log::{debug, warn};
+
+// Requested frequency, i915 on older and newer kernels, then xe
+const FREQ_FILES: [&str; 3] = [
+    "gt_cur_freq_mhz",
+    "gt/gt0/rps_cur_freq_mhz",
+    "device/tile0/gt0/freq0/cur_freq",
+];
+
+// Local memory size of i915 discrete cards, in the card directory
+const LMEM_TOTAL_FILES: [&str; 2] = ["lmem_total_bytes", "prelim_lmem_total_bytes"];
 
 pub struct IntelGpu {
     pub name: String,
     pub id: String,
+    card: String,
+    slot: String,
+    power_status_path: PathBuf,
+    hwmon_path: Option<PathBuf>,
+    vram_total: u64,
+    usage: RefCell<DrmUsage>,
+    // Previous energy counter in microjoules, for cards without power1_input
+    energy: RefCell<Option<(Instant, u64)>>,
+    paused: bool,
 }
 
-#[allow(dead_code)]
 impl IntelGpu {
-    pub fn new(name: String, id: String) -> Self {
-        IntelGpu { name, id }
+    pub fn new(name: String, id: String, card: &str, pdev: &str) -> Self {
+        let base = PathBuf::from(format!("/sys/class/drm/{card}/device"));
+        let hwmon_path = IntelGpu::find_hwmon_path(&base);
+
+        IntelGpu {
+            name,
+            id,
+            card: card.to_string(),
+            slot: pdev.to_lowercase(),
+            power_status_path: base.join("power/runtime_status"),
+            hwmon_path,
+            vram_total: IntelGpu::get_vram_total(&base),
+            usage: RefCell::new(DrmUsage::new(pdev)),
+            energy: RefCell::new(None),
+            paused: false,
+        }
+    }
+
+    fn powered_on(&self) -> bool {
+        fs::read_to_string(&self.power_status_path).map_or(true, |s| s.trim() != "suspended")
+    }
+
+    fn read_u64<P: AsRef<Path>>(path: P) -> Option<u64> {
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    // Integrated GPUs have no hwmon, Arc cards have one from the i915 or xe driver
+    fn find_hwmon_path(base: &Path) -> Option<PathBuf> {
+        let entries = fs::read_dir(base.join("hwmon")).ok()?;
+        let path = entries.flatten().next()?.path();
+        info!("Intel hwmon found at {path:?}");
+        Some(path)
+    }
+
+    // Size of the local memory, integrated GPUs have none. xe reports it per tile,
+    // i915 for the whole card, as prelim_ on the backported DG2 driver.
+    fn get_vram_total(base: &Path) -> u64 {
+        let mut total = 0;
+        for tile in 0..4 {
+            match IntelGpu::read_u64(base.join(format!("tile{tile}/physical_vram_size_bytes"))) {
+                Some(bytes) => total += bytes,
+                None => break,
+            }
+        }
+        if total > 0 {
+            return total;
+        }
+
+        let Some(card) = base.parent() else {
+            return 0;
+        };
+        LMEM_TOTAL_FILES
+            .iter()
+            .find_map(|f| IntelGpu::read_u64(card.join(f)))
+            .unwrap_or(0)
+    }
+
+    fn get_intel_cards() -> Vec<(String, String)> {
+        debug!("IntelGpu::get_intel_cards().");
+        let mut cards = Vec::new();
+        let Ok(entries) = fs::read_dir("/sys/class/drm/") else {
+            return cards;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(card) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            // Skip connectors like card0-eDP-1
+            if !card.starts_with("card") || card.contains('-') {
+                continue;
+            }
+
+            let vendor = fs::read_to_string(path.join("device/vendor")).unwrap_or_default();
+            if vendor.trim() != "0x8086" {
+                continue;
+            }
+
+            let driver = fs::read_link(path.join("device/driver"))
+                .ok()
+                .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_default();
+            debug!("                    Intel card {card}, driver {driver}");
+            if driver == "i915" || driver == "xe" {
+                cards.push((card.to_string(), driver));
+            }
+        }
+        cards.sort();
+        cards
+    }
+
+    fn get_gpu_name(slot: &str) -> String {
+        // pci.ids: "Alder Lake-P GT2 [Iris Xe Graphics]"
+        let Some(names) = pci_ids::lookup(slot) else {
+            return "Intel Graphics".to_string();
+        };
+        let model = names.device;
+
+        // Prefer the marketing name in brackets if there is one
+        let name = match (model.rfind('['), model.rfind(']')) {
+            (Some(start), Some(end)) if start < end => model[start + 1..end].to_string(),
+            _ => model.replace("Intel Corporation", "").trim().to_string(),
+        };
+
+        if name.starts_with("Intel") {
+            name
+        } else {
+            format!("Intel {name}")
+        }
+    }
+
+    pub fn get_gpus(known: &HashSet<String>) -> Vec<Gpu> {
+        debug!("IntelGpu::get_gpus().");
+        let mut gpus = Vec::new();
+
+        for (card, driver) in IntelGpu::get_intel_cards() {
+            let (Some(id), Some(slot)) = (super::generate_gpu_id(&card), super::pci_slot(&card))
+            else {
+                continue;
+            };
+            if known.contains(&slot) {
+                continue;
+            }
+            let name = IntelGpu::get_gpu_name(&slot);
+            info!("Found Intel GPU {name} ({card}, {driver}, {slot})");
+            gpus.push(Gpu::new(Box::new(IntelGpu::new(name, id, &card, &slot))));
+        }
+        gpus
     }
 }
 
 impl super::GpuIf for IntelGpu {
     fn restart(&mut self) {
-        todo!();
+        debug!("IntelGpu::restart({}).", self.name);
+        self.paused = false;
     }
 
     fn stop(&mut self) {
-        todo!();
+        debug!("IntelGpu::stop({}).", self.name);
+        self.paused = true;
+        self.usage.borrow_mut().reset();
+        *self.energy.borrow_mut() = None;
     }
 
     fn is_active(&self) -> bool {
-        todo!();
+        !self.paused
     }
 
     fn name(&self) -> String {
@@ -36,25 +193,129 @@ impl super::GpuIf for IntelGpu {
         self.id.clone()
     }
 
+    // Reading fdinfo doesn't wake the GPU, so this also runs while it's suspended
     fn usage(&self) -> Result<u32> {
-        todo!();
+        if !self.is_active() {
+            return Err(anyhow!("Intel device paused"));
+        }
+        let usage = self.usage.borrow_mut().refresh();
+        Ok(usage.round() as u32)
     }
 
     fn temperature(&self) -> Result<u32> {
-        todo!();
+        if !self.powered_on() {
+            return Ok(0);
+        }
+        let hwmon = self.hwmon_path.as_ref().context("No Intel hwmon")?;
+
+        // xe puts the package temperature in temp2
+        (1..=4)
+            .filter_map(|i| IntelGpu::read_u64(hwmon.join(format!("temp{i}_input"))))
+            .next()
+            .map(|t| t as u32)
+            .context("No Intel temperature sensor")
+    }
+
+    // Integrated GPUs have no sensor of their own
+    fn has_temperature(&self) -> bool {
+        self.hwmon_path.is_some()
     }
 
     fn vram_total(&self) -> u64 {
-        todo!();
+        self.vram_total
     }
 
+    // Memory in the local (i915) or vram (xe) regions, summed over all visible clients
     fn vram_used(&self) -> Result<u64> {
-        todo!();
+        if !self.is_active() {
+            return Err(anyhow!("Intel device paused"));
+        }
+        if self.vram_total == 0 {
+            return Ok(0);
+        }
+        Ok(self.usage.borrow().memory(&["local", "vram"]))
+    }
+
+    fn pci_slot(&self) -> Option<String> {
+        Some(self.slot.clone())
+    }
+
+    // usage() already rescans the clients on every update
+    fn processes(&self) -> Vec<GpuProcess> {
+        if !self.is_active() {
+            return Vec::new();
+        }
+        self.usage.borrow().processes(&["local", "vram", "system"])
+    }
+
+    fn metrics(&self) -> Vec<GpuMetric> {
+        let mut metrics = vec![GpuMetric::CoreClock];
+        if self.hwmon_path.is_some() {
+            metrics.push(GpuMetric::Power);
+        }
+        metrics
+    }
+
+    fn metric(&self, metric: GpuMetric) -> Result<f64> {
+        if !self.is_active() {
+            return Err(anyhow!("Intel device paused"));
+        }
+        match metric {
+            GpuMetric::CoreClock => self.core_clock(),
+            GpuMetric::Power => self.power(),
+            _ => Err(anyhow!("{metric:?} not supported by Intel GPUs")),
+        }
     }
 }
 
 impl IntelGpu {
-    pub fn get_gpus() -> Vec<Gpu> {
-        Vec::new()
+    // Current requested frequency in MHz
+    fn core_clock(&self) -> Result<f64> {
+        if !self.powered_on() {
+            return Ok(0.0);
+        }
+        let base = PathBuf::from(format!("/sys/class/drm/{}", self.card));
+        FREQ_FILES
+            .iter()
+            .find_map(|f| IntelGpu::read_u64(base.join(f)))
+            .map(|mhz| mhz as f64)
+            .context("No Intel frequency file")
+    }
+
+    // Board or package power in W
+    fn power(&self) -> Result<f64> {
+        let hwmon = self.hwmon_path.as_ref().context("No Intel hwmon")?;
+
+        if let Some(microwatts) = IntelGpu::read_u64(hwmon.join("power1_input")) {
+            return Ok(microwatts as f64 / 1_000_000.0);
+        }
+
+        // i915 and xe only have an energy counter, derive the average power from it
+        let energy = IntelGpu::read_u64(hwmon.join("energy1_input"))
+            .context("No Intel power or energy counter")?;
+        let now = Instant::now();
+        let previous = self.energy.borrow_mut().replace((now, energy));
+
+        match previous {
+            Some((time, prev)) if energy >= prev => {
+                let elapsed_us = now.duration_since(time).as_micros() as u64;
+                if elapsed_us == 0 {
+                    return Err(anyhow!("Intel power sampled too quickly"));
+                }
+                // uJ / us = W
+                Ok((energy - prev) as f64 / elapsed_us as f64)
+            }
+            _ => Err(anyhow!("Intel power needs two samples")),
+        }
+    }
+}
+
+impl std::fmt::Debug for IntelGpu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "IntelGpu {{ name: {}, id: {}, card: {}, paused: {} }}",
+            self.name, self.id, self.card, self.paused
+        )
     }
 }
@@ -0,0 +1,160 @@
+//! Scriptable in-memory GPU for tests. Each reading is taken from a queue,
+//! the last entry repeats once the queue is down to one.
+
+use anyhow::{Result, anyhow};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use super::ThrottleReason;
+use super::fdinfo::GpuProcess;
+use crate::config::GpuMetric;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reading<T> {
+    Value(T),
+    Error,
+    // Runtime suspended, real backends report 0 rather than waking the GPU
+    Suspended,
+}
+
+#[derive(Debug, Default)]
+pub struct MockScript {
+    pub usage: VecDeque<Reading<u32>>,
+    pub temperature: VecDeque<Reading<u32>>,
+    pub vram_used: VecDeque<Reading<u64>>,
+    pub metrics: HashMap<GpuMetric, VecDeque<Reading<f64>>>,
+    pub metric_max: HashMap<GpuMetric, f64>,
+    pub throttle_reasons: Option<Vec<ThrottleReason>>,
+    pub processes: Vec<GpuProcess>,
+    pub accelerator: bool,
+    // Number of readings taken, to check nothing is polled while stopped
+    pub reads: usize,
+}
+
+impl MockScript {
+    fn next<T: Copy + Default>(queue: &mut VecDeque<Reading<T>>, reads: &mut usize) -> Result<T> {
+        *reads += 1;
+        let reading = if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().copied()
+        };
+        match reading {
+            Some(Reading::Value(value)) => Ok(value),
+            Some(Reading::Suspended) => Ok(T::default()),
+            Some(Reading::Error) => Err(anyhow!("Mock read error")),
+            None => Err(anyhow!("Mock script empty")),
+        }
+    }
+}
+
+pub struct MockGpu {
+    name: String,
+    id: String,
+    vram_total: u64,
+    script: Rc<RefCell<MockScript>>,
+    paused: bool,
+}
+
+impl MockGpu {
+    /// The returned script stays with the test, to feed readings after the Gpu took the mock
+    pub fn new(id: &str, vram_total: u64) -> (Self, Rc<RefCell<MockScript>>) {
+        let script = Rc::new(RefCell::new(MockScript::default()));
+        (
+            MockGpu {
+                name: format!("Mock GPU {id}"),
+                id: id.to_string(),
+                vram_total,
+                script: script.clone(),
+                paused: false,
+            },
+            script,
+        )
+    }
+}
+
+impl super::GpuIf for MockGpu {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn usage(&self) -> Result<u32> {
+        let script = &mut *self.script.borrow_mut();
+        MockScript::next(&mut script.usage, &mut script.reads)
+    }
+
+    fn temperature(&self) -> Result<u32> {
+        let script = &mut *self.script.borrow_mut();
+        MockScript::next(&mut script.temperature, &mut script.reads)
+    }
+
+    // Like accelerators, a mock without scripted temperatures has no sensor
+    fn has_temperature(&self) -> bool {
+        !self.script.borrow().temperature.is_empty()
+    }
+
+    fn vram_total(&self) -> u64 {
+        self.vram_total
+    }
+
+    fn vram_used(&self) -> Result<u64> {
+        let script = &mut *self.script.borrow_mut();
+        MockScript::next(&mut script.vram_used, &mut script.reads)
+    }
+
+    fn metrics(&self) -> Vec<GpuMetric> {
+        let mut metrics: Vec<GpuMetric> = self.script.borrow().metrics.keys().copied().collect();
+        metrics.sort();
+        metrics
+    }
+
+    fn metric(&self, metric: GpuMetric) -> Result<f64> {
+        let script = &mut *self.script.borrow_mut();
+        let queue = script
+            .metrics
+            .get_mut(&metric)
+            .ok_or_else(|| anyhow!("{metric:?} not scripted"))?;
+        MockScript::next(queue, &mut script.reads)
+    }
+
+    fn metric_max(&self, metric: GpuMetric) -> Option<f64> {
+        self.script.borrow().metric_max.get(&metric).copied()
+    }
+
+    fn has_throttle_reasons(&self) -> bool {
+        self.script.borrow().throttle_reasons.is_some()
+    }
+
+    fn throttle_reasons(&self) -> Result<Vec<ThrottleReason>> {
+        self.script
+            .borrow()
+            .throttle_reasons
+            .clone()
+            .ok_or_else(|| anyhow!("Throttle reasons not scripted"))
+    }
+
+    fn processes(&self) -> Vec<GpuProcess> {
+        self.script.borrow().processes.clone()
+    }
+
+    fn is_accelerator(&self) -> bool {
+        self.script.borrow().accelerator
+    }
+
+    fn stop(&mut self) {
+        self.paused = true;
+    }
+
+    fn restart(&mut self) {
+        self.paused = false;
+    }
+
+    fn is_active(&self) -> bool {
+        !self.paused
+    }
+}
@@ -1,8 +1,43 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
 
+use crate::config::GpuMetric;
+use crate::fl;
+use fdinfo::GpuProcess;
+
+pub mod accel;
 pub mod amd;
+pub mod fdinfo;
 pub mod intel;
+#[cfg(test)]
+pub mod mock;
+pub mod nouveau;
 pub mod nvidia;
+pub mod pci_ids;
+pub mod power;
+pub mod replay;
+
+/// Why the GPU is running below its requested clocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleReason {
+    PowerCap,
+    Thermal,
+    HwSlowdown,
+    PowerBrake,
+}
+
+impl std::fmt::Display for ThrottleReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ThrottleReason::PowerCap => write!(f, "{}", fl!("gpu-throttle-power")),
+            ThrottleReason::Thermal => write!(f, "{}", fl!("gpu-throttle-thermal")),
+            ThrottleReason::HwSlowdown => write!(f, "{}", fl!("gpu-throttle-hw-slowdown")),
+            ThrottleReason::PowerBrake => write!(f, "{}", fl!("gpu-throttle-power-brake")),
+        }
+    }
+}
 
 pub trait GpuIf {
     fn name(&self) -> String;
@@ -14,6 +49,51 @@ pub trait GpuIf {
     fn vram_total(&self) -> u64;
     fn vram_used(&self) -> Result<u64>;
 
+    // Does the device have a temperature sensor at all?
+    fn has_temperature(&self) -> bool {
+        true
+    }
+
+    // NPUs and other compute accelerators, they are listed like GPUs but aren't graphics cards
+    fn is_accelerator(&self) -> bool {
+        false
+    }
+
+    // Additional metrics this GPU can report
+    fn metrics(&self) -> Vec<GpuMetric> {
+        Vec::new()
+    }
+
+    // Current value of a metric, in the unit given by GpuMetric::unit()
+    fn metric(&self, metric: GpuMetric) -> Result<f64> {
+        Err(anyhow!("{metric:?} not supported"))
+    }
+
+    // Upper bound of a metric for scaling the charts, if the GPU reports one
+    fn metric_max(&self, _metric: GpuMetric) -> Option<f64> {
+        None
+    }
+
+    // Does the GPU report throttle reasons at all?
+    fn has_throttle_reasons(&self) -> bool {
+        false
+    }
+
+    // Active reasons for reduced clocks, idle and user clock settings are not included
+    fn throttle_reasons(&self) -> Result<Vec<ThrottleReason>> {
+        Err(anyhow!("Throttle reasons not supported"))
+    }
+
+    // Processes using the GPU, busiest first. Only called while the popup is open.
+    fn processes(&self) -> Vec<GpuProcess> {
+        Vec::new()
+    }
+
+    // PCI slot like 0000:01:00.0, to look up the runtime power state and device nodes
+    fn pci_slot(&self) -> Option<String> {
+        None
+    }
+
     // Stop polling, to allow it to sleep
     fn stop(&mut self);
     // Resume active polling
@@ -21,3 +101,38 @@ pub trait GpuIf {
     // Stopped or active for polling?
     fn is_active(&self) -> bool;
 }
+
+/// PCI slot of a DRM card, like 0000:03:00.0
+pub fn pci_slot(card: &str) -> Option<String> {
+    device_pci_slot(Path::new(&format!("/sys/class/drm/{card}/device")))
+}
+
+/// PCI slot of a sysfs device directory
+pub fn device_pci_slot(device_path: &Path) -> Option<String> {
+    fs::read_to_string(device_path.join("uevent"))
+        .ok()?
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("PCI_SLOT_NAME=")
+                .map(|s| s.trim().to_lowercase())
+        })
+}
+
+/// Stable id of a DRM card, hashed from its PCI address and subsystem ids
+pub fn generate_gpu_id(card: &str) -> Option<String> {
+    generate_device_id(Path::new(&format!("/sys/class/drm/{card}/device")))
+}
+
+/// Stable id of a sysfs device directory, hashed from its PCI address and subsystem ids
+pub fn generate_device_id(device_path: &Path) -> Option<String> {
+    let pci_address = device_path.canonicalize().ok()?;
+    let subsystem_vendor = fs::read_to_string(device_path.join("subsystem_vendor")).ok()?;
+    let subsystem_device = fs::read_to_string(device_path.join("subsystem_device")).ok()?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(pci_address.to_string_lossy().as_bytes());
+    hasher.update(subsystem_vendor.trim().as_bytes());
+    hasher.update(subsystem_device.trim().as_bytes());
+
+    Some(hex::encode(hasher.finalize()))
+}
@@ -0,0 +1,247 @@
+use anyhow::{Context, Result, anyhow};
+use log::{debug, info};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::fdinfo::{DrmUsage, GpuProcess};
+use super::pci_ids;
+use crate::sensors::gpus::Gpu;
+
+/// NVIDIA cards on the open nouveau driver, NVML only works with the proprietary one.
+/// Usage and processes come from DRM fdinfo, the temperature from nouveau's hwmon.
+pub struct NouveauGpu {
+    pub name: String,
+    pub id: String,
+    card: String,
+    slot: String,
+    power_status_path: PathBuf,
+    hwmon_path: Option<PathBuf>,
+    usage: RefCell<DrmUsage>,
+    paused: bool,
+}
+
+impl NouveauGpu {
+    pub fn new(name: String, id: String, card: &str, pdev: &str) -> Self {
+        let base = PathBuf::from(format!("/sys/class/drm/{card}/device"));
+        let hwmon_path = NouveauGpu::find_hwmon_path(&base);
+
+        NouveauGpu {
+            name,
+            id,
+            card: card.to_string(),
+            slot: pdev.to_lowercase(),
+            power_status_path: base.join("power/runtime_status"),
+            hwmon_path,
+            usage: RefCell::new(DrmUsage::new(pdev)),
+            paused: false,
+        }
+    }
+
+    fn powered_on(&self) -> bool {
+        fs::read_to_string(&self.power_status_path).map_or(true, |s| s.trim() != "suspended")
+    }
+
+    fn find_hwmon_path(base: &Path) -> Option<PathBuf> {
+        let entries = fs::read_dir(base.join("hwmon")).ok()?;
+        let path = entries.flatten().next()?.path();
+        info!("nouveau hwmon found at {path:?}");
+        Some(path)
+    }
+
+    // Cards in `drm`, like /sys/class/drm, bound to nouveau
+    fn get_nouveau_cards(drm: &Path) -> Vec<String> {
+        debug!("NouveauGpu::get_nouveau_cards().");
+        let mut cards = Vec::new();
+        let Ok(entries) = fs::read_dir(drm) else {
+            return cards;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(card) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            // Skip connectors like card0-DP-1
+            if !card.starts_with("card") || card.contains('-') {
+                continue;
+            }
+
+            let driver = fs::read_link(path.join("device/driver"))
+                .ok()
+                .and_then(|d| d.file_name().map(|n| n.to_string_lossy().to_string()))
+                .unwrap_or_default();
+            if driver == "nouveau" {
+                cards.push(card.to_string());
+            }
+        }
+        cards.sort();
+        cards
+    }
+
+    fn read_temperature(hwmon: &Path) -> Result<u32> {
+        let millidegrees = fs::read_to_string(hwmon.join("temp1_input"))?
+            .trim()
+            .parse()?;
+        Ok(millidegrees)
+    }
+
+    fn get_gpu_name(slot: &str) -> String {
+        pci_ids::lookup(slot).map_or_else(
+            || "NVIDIA Graphics".to_string(),
+            |names| pci_ids::clean_gpu_name(&names.vendor, &names.device),
+        )
+    }
+
+    pub fn get_gpus(known: &HashSet<String>) -> Vec<Gpu> {
+        debug!("NouveauGpu::get_gpus().");
+        let mut gpus = Vec::new();
+
+        for card in NouveauGpu::get_nouveau_cards(Path::new("/sys/class/drm")) {
+            let (Some(id), Some(slot)) = (super::generate_gpu_id(&card), super::pci_slot(&card))
+            else {
+                continue;
+            };
+            if known.contains(&slot) {
+                continue;
+            }
+            let name = NouveauGpu::get_gpu_name(&slot);
+            info!("Found nouveau GPU {name} ({card}, {slot})");
+            gpus.push(Gpu::new(Box::new(NouveauGpu::new(name, id, &card, &slot))));
+        }
+        gpus
+    }
+}
+
+impl super::GpuIf for NouveauGpu {
+    fn restart(&mut self) {
+        debug!("NouveauGpu::restart({}).", self.name);
+        self.paused = false;
+    }
+
+    fn stop(&mut self) {
+        debug!("NouveauGpu::stop({}).", self.name);
+        self.paused = true;
+        self.usage.borrow_mut().reset();
+    }
+
+    fn is_active(&self) -> bool {
+        !self.paused
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    // Kernels before engine fdinfo support report no busy time, the usage stays 0
+    fn usage(&self) -> Result<u32> {
+        if !self.is_active() {
+            return Err(anyhow!("nouveau device paused"));
+        }
+        let usage = self.usage.borrow_mut().refresh();
+        Ok(usage.round() as u32)
+    }
+
+    fn temperature(&self) -> Result<u32> {
+        if !self.powered_on() {
+            return Ok(0);
+        }
+        let hwmon = self.hwmon_path.as_ref().context("No nouveau hwmon")?;
+        NouveauGpu::read_temperature(hwmon)
+    }
+
+    fn has_temperature(&self) -> bool {
+        self.hwmon_path.is_some()
+    }
+
+    // nouveau doesn't report the size of the VRAM
+    fn vram_total(&self) -> u64 {
+        0
+    }
+
+    fn vram_used(&self) -> Result<u64> {
+        if !self.is_active() {
+            return Err(anyhow!("nouveau device paused"));
+        }
+        Ok(0)
+    }
+
+    fn pci_slot(&self) -> Option<String> {
+        Some(self.slot.clone())
+    }
+
+    // usage() already rescans the clients on every update
+    fn processes(&self) -> Vec<GpuProcess> {
+        if !self.is_active() {
+            return Vec::new();
+        }
+        self.usage.borrow().processes(&["vram", "gtt"])
+    }
+}
+
+impl std::fmt::Debug for NouveauGpu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "NouveauGpu {{ name: {}, id: {}, card: {}, paused: {} }}",
+            self.name, self.id, self.card, self.paused
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("minimon-nouveau-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn add_card(drm: &Path, card: &str, driver: &str) {
+        let device = drm.join(card).join("device");
+        fs::create_dir_all(&device).unwrap();
+        symlink(
+            format!("../../../bus/pci/drivers/{driver}"),
+            device.join("driver"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn finds_nouveau_cards() {
+        let drm = temp_dir("drm");
+        add_card(&drm, "card1", "nouveau");
+        add_card(&drm, "card0", "i915");
+        add_card(&drm, "card1-DP-1", "nouveau");
+        add_card(&drm, "card2", "nouveau");
+        fs::create_dir_all(drm.join("renderD128")).unwrap();
+
+        assert_eq!(NouveauGpu::get_nouveau_cards(&drm), vec!["card1", "card2"]);
+        assert!(NouveauGpu::get_nouveau_cards(&drm.join("missing")).is_empty());
+        fs::remove_dir_all(&drm).unwrap();
+    }
+
+    #[test]
+    fn reads_hwmon_temperature() {
+        let device = temp_dir("device");
+        let hwmon = device.join("hwmon/hwmon3");
+        fs::create_dir_all(&hwmon).unwrap();
+        fs::write(hwmon.join("temp1_input"), "52000\n").unwrap();
+
+        assert_eq!(NouveauGpu::find_hwmon_path(&device), Some(hwmon.clone()));
+        assert_eq!(NouveauGpu::read_temperature(&hwmon).unwrap(), 52000);
+        fs::write(hwmon.join("temp1_input"), "\n").unwrap();
+        assert!(NouveauGpu::read_temperature(&hwmon).is_err());
+        fs::remove_dir_all(&device).unwrap();
+    }
+}
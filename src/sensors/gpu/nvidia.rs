@@ -1,21 +1,38 @@
 use anyhow::{Context, Result, anyhow};
 use log::{debug, info, warn};
-use nvml_wrapper::{Device, Nvml, error::NvmlError};
+use nvml_wrapper::bitmasks::device::ThrottleReasons;
+use nvml_wrapper::enum_wrappers::device::{Clock, PcieUtilCounter, TemperatureSensor};
+use nvml_wrapper::{Device, Nvml};
 
-use std::sync::LazyLock;
+use std::collections::HashSet;
+use std::fs;
+use std::sync::OnceLock;
 
+use super::ThrottleReason;
+use super::pci_ids;
+use crate::config::GpuMetric;
 use crate::sensors::gpus::Gpu;
 
-pub static NVML: LazyLock<Result<Nvml, NvmlError>> = LazyLock::new(|| {
-    let nvml = Nvml::init();
+static NVML: OnceLock<Nvml> = OnceLock::new();
 
-    if let Err(error) = nvml.as_ref() {
-        warn!("Connection to NVML failed, reason: {error}");
-    } else {
-        debug!("Successfully connected to NVML");
+/// Connection to NVML. A failed connection is retried on the next call,
+/// as the driver may be loaded after the applet started.
+pub fn nvml() -> Result<&'static Nvml> {
+    if let Some(nvml) = NVML.get() {
+        return Ok(nvml);
     }
-    nvml
-});
+
+    match Nvml::init() {
+        Ok(nvml) => {
+            debug!("Successfully connected to NVML");
+            Ok(NVML.get_or_init(|| nvml))
+        }
+        Err(error) => {
+            warn!("Connection to NVML failed, reason: {error}");
+            Err(anyhow!(error))
+        }
+    }
+}
 
 pub struct NvidiaGpu<'a> {
     // Index returned by NVML
@@ -23,6 +40,11 @@ pub struct NvidiaGpu<'a> {
     pub name: String,
     pub uuid: String,
     vram_total: u64,
+    // Metrics the driver answered for when the GPU was detected
+    metrics: Vec<GpuMetric>,
+    has_throttle_reasons: bool,
+    // PCI slot in sysfs format, like 0000:01:00.0
+    slot: Option<String>,
     device: Option<Device<'a>>,
 }
 
@@ -30,12 +52,25 @@ impl NvidiaGpu<'_> {
     pub fn new(index: u32, name: String, uuid: String) -> Self {
         let mut device = None;
         let mut vram = 0;
+        let mut metrics = Vec::new();
+        let mut has_throttle_reasons = false;
+        let mut slot = None;
 
-        if let Ok(nvml) = NVML.as_ref()
+        if let Ok(nvml) = nvml()
             && let Ok(dev) = nvml.device_by_index(index)
             && let Ok(mem) = dev.memory_info()
         {
             vram = mem.total;
+            metrics = NvidiaGpu::METRICS
+                .into_iter()
+                .filter(|m| NvidiaGpu::read_metric(&dev, *m).is_ok())
+                .collect();
+            has_throttle_reasons = dev.current_throttle_reasons().is_ok();
+            slot = dev
+                .pci_info()
+                .ok()
+                .map(|pci| NvidiaGpu::sysfs_slot(&pci.bus_id));
+            info!("Nvidia {name} metrics: {metrics:?}, throttle reasons: {has_throttle_reasons}");
             device = Some(dev);
         }
 
@@ -44,15 +79,59 @@ impl NvidiaGpu<'_> {
             name,
             uuid,
             vram_total: vram,
+            metrics,
+            has_throttle_reasons,
+            slot,
             device,
         }
     }
+
+    // NVML reports an 8 digit domain, "00000000:01:00.0", sysfs uses 4 digits
+    fn sysfs_slot(bus_id: &str) -> String {
+        let bus_id = bus_id.trim_end_matches('\0').to_lowercase();
+        match bus_id.split_once(':') {
+            Some((domain, rest)) if domain.len() > 4 => {
+                format!("{}:{rest}", &domain[domain.len() - 4..])
+            }
+            _ => bus_id,
+        }
+    }
+
+    const METRICS: [GpuMetric; 8] = [
+        GpuMetric::Power,
+        GpuMetric::CoreClock,
+        GpuMetric::MemoryClock,
+        GpuMetric::Fan,
+        GpuMetric::EncoderUsage,
+        GpuMetric::DecoderUsage,
+        GpuMetric::PcieRx,
+        GpuMetric::PcieTx,
+    ];
+
+    fn read_metric(device: &Device, metric: GpuMetric) -> Result<f64> {
+        let value = match metric {
+            // milliwatts
+            GpuMetric::Power => f64::from(device.power_usage()?) / 1000.0,
+            GpuMetric::CoreClock => f64::from(device.clock_info(Clock::SM)?),
+            GpuMetric::MemoryClock => f64::from(device.clock_info(Clock::Memory)?),
+            GpuMetric::Fan => f64::from(device.fan_speed(0)?),
+            GpuMetric::EncoderUsage => f64::from(device.encoder_utilization()?.utilization),
+            GpuMetric::DecoderUsage => f64::from(device.decoder_utilization()?.utilization),
+            // KB/s
+            GpuMetric::PcieRx => {
+                f64::from(device.pcie_throughput(PcieUtilCounter::Receive)?) / 1000.0
+            }
+            GpuMetric::PcieTx => f64::from(device.pcie_throughput(PcieUtilCounter::Send)?) / 1000.0,
+            _ => return Err(anyhow!("{metric:?} not supported by NVML")),
+        };
+        Ok(value)
+    }
 }
 
 impl super::GpuIf for NvidiaGpu<'_> {
     fn restart(&mut self) {
         if self.device.is_none()
-            && let Ok(nvml) = NVML.as_ref()
+            && let Ok(nvml) = nvml()
             && let Ok(dev) = nvml.device_by_index(self.index)
         {
             self.device = Some(dev);
@@ -87,9 +166,7 @@ impl super::GpuIf for NvidiaGpu<'_> {
 
     fn temperature(&self) -> Result<u32> {
         self.with_device(|device_ref| {
-            let temp = device_ref
-                .temperature(nvml_wrapper::enum_wrappers::device::TemperatureSensor::Gpu)?
-                * 1000;
+            let temp = device_ref.temperature(TemperatureSensor::Gpu)? * 1000;
             Ok(temp)
         })
     }
@@ -104,19 +181,81 @@ impl super::GpuIf for NvidiaGpu<'_> {
             Ok(mem.used)
         })
     }
+
+    fn metrics(&self) -> Vec<GpuMetric> {
+        self.metrics.clone()
+    }
+
+    fn metric(&self, metric: GpuMetric) -> Result<f64> {
+        self.with_device(|device_ref| NvidiaGpu::read_metric(device_ref, metric))
+    }
+
+    fn metric_max(&self, metric: GpuMetric) -> Option<f64> {
+        let device_ref = self.device.as_ref()?;
+        match metric {
+            GpuMetric::Power => Some(f64::from(device_ref.enforced_power_limit().ok()?) / 1000.0),
+            GpuMetric::CoreClock => Some(f64::from(device_ref.max_clock_info(Clock::SM).ok()?)),
+            GpuMetric::MemoryClock => {
+                Some(f64::from(device_ref.max_clock_info(Clock::Memory).ok()?))
+            }
+            _ => None,
+        }
+    }
+
+    fn pci_slot(&self) -> Option<String> {
+        self.slot.clone()
+    }
+
+    fn has_throttle_reasons(&self) -> bool {
+        self.has_throttle_reasons
+    }
+
+    fn throttle_reasons(&self) -> Result<Vec<ThrottleReason>> {
+        self.with_device(|device_ref| {
+            let reasons = device_ref.current_throttle_reasons()?;
+            let mut active = Vec::new();
+            if reasons.contains(ThrottleReasons::SW_POWER_CAP) {
+                active.push(ThrottleReason::PowerCap);
+            }
+            if reasons.intersects(
+                ThrottleReasons::SW_THERMAL_SLOWDOWN | ThrottleReasons::HW_THERMAL_SLOWDOWN,
+            ) {
+                active.push(ThrottleReason::Thermal);
+            }
+            if reasons.contains(ThrottleReasons::HW_POWER_BRAKE_SLOWDOWN) {
+                active.push(ThrottleReason::PowerBrake);
+            } else if reasons.contains(ThrottleReasons::HW_SLOWDOWN) {
+                active.push(ThrottleReason::HwSlowdown);
+            }
+            Ok(active)
+        })
+    }
 }
 
 impl NvidiaGpu<'_> {
-    pub fn get_gpus() -> Vec<Gpu> {
+    pub fn get_gpus(known: &HashSet<String>) -> Vec<Gpu> {
         let mut v: Vec<Gpu> = Vec::new();
 
+        // Don't go through NVML on re-detection unless the driver has a new GPU,
+        // probing wakes the runtime suspended ones
+        if !known.is_empty() && NvidiaGpu::driver_slots().iter().all(|s| known.contains(s)) {
+            return v;
+        }
+
         // Nvidia GPUs
         if let Ok(count) = NvidiaGpu::gpus() {
             let nvidia_gpus = (0..count)
                 .filter_map(|i| {
-                    // Try to get both name and UUID, skip this GPU if either fails
-                    let name = NvidiaGpu::name(i).ok()?;
+                    if NvidiaGpu::slot(i).is_some_and(|s| known.contains(&s)) {
+                        return None;
+                    }
+                    // Skip this GPU without a UUID, the name can come from pci.ids
                     let uuid = NvidiaGpu::uuid(i).ok()?;
+                    let name = NvidiaGpu::name(i)
+                        .ok()
+                        .filter(|n| !n.trim().is_empty())
+                        .or_else(|| NvidiaGpu::pci_name(i))
+                        .unwrap_or_else(|| "NVIDIA GPU".to_string());
 
                     Some(Gpu::new(Box::new(NvidiaGpu::new(i, name, uuid))))
                 })
@@ -130,7 +269,7 @@ impl NvidiaGpu<'_> {
     }
 
     pub fn uuid(idx: u32) -> Result<String> {
-        NVML.as_ref()
+        nvml()
             .context("unable to establish NVML connection")
             .and_then(|nvml| {
                 let dev = nvml.device_by_index(idx)?;
@@ -139,7 +278,7 @@ impl NvidiaGpu<'_> {
     }
 
     pub fn name(idx: u32) -> Result<String> {
-        NVML.as_ref()
+        nvml()
             .context("unable to establish NVML connection")
             .and_then(|nvml| {
                 let dev = nvml.device_by_index(idx)?;
@@ -147,8 +286,33 @@ impl NvidiaGpu<'_> {
             })
     }
 
+    // Name from pci.ids, for devices NVML doesn't know the name of
+    fn pci_name(idx: u32) -> Option<String> {
+        let pci = nvml().ok()?.device_by_index(idx).ok()?.pci_info().ok()?;
+        let names = pci_ids::lookup(&NvidiaGpu::sysfs_slot(&pci.bus_id))?;
+        Some(pci_ids::clean_gpu_name(&names.vendor, &names.device))
+    }
+
+    fn slot(idx: u32) -> Option<String> {
+        let nvml = nvml().ok()?;
+        let pci = nvml.device_by_index(idx).ok()?.pci_info().ok()?;
+        Some(NvidiaGpu::sysfs_slot(&pci.bus_id))
+    }
+
+    // PCI slots of the GPUs the driver bound, named like 0000:01:00.0
+    pub fn driver_slots() -> Vec<String> {
+        fs::read_dir("/proc/driver/nvidia/gpus")
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.file_name().to_string_lossy().to_lowercase())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     fn gpus() -> Result<u32> {
-        NVML.as_ref()
+        nvml()
             .context("unable to establish NVML connection")
             .and_then(|nvml| nvml.device_count().context("failed to get GPU count"))
     }
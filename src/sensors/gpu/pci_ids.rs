@@ -0,0 +1,216 @@
+//! Device names from the pci.ids database, the same source lspci uses.
+
+use log::{debug, info};
+use std::fs;
+
+// Distribution locations, and the host's copy when running in a Flatpak sandbox
+const PCI_IDS_PATHS: [&str; 5] = [
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+    "/usr/share/pci.ids",
+    "/run/host/usr/share/hwdata/pci.ids",
+    "/run/host/usr/share/misc/pci.ids",
+];
+
+/// Ids of a PCI device as found in /sys/bus/pci/devices/<slot>, lowercase hex without 0x
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PciIds {
+    pub vendor: String,
+    pub device: String,
+    pub subsystem_vendor: String,
+    pub subsystem_device: String,
+}
+
+impl PciIds {
+    pub fn read(slot: &str) -> Option<PciIds> {
+        let base = format!("/sys/bus/pci/devices/{slot}");
+        let read = |file: &str| -> Option<String> {
+            let value = fs::read_to_string(format!("{base}/{file}")).ok()?;
+            Some(value.trim().trim_start_matches("0x").to_lowercase())
+        };
+
+        Some(PciIds {
+            vendor: read("vendor")?,
+            device: read("device")?,
+            subsystem_vendor: read("subsystem_vendor").unwrap_or_default(),
+            subsystem_device: read("subsystem_device").unwrap_or_default(),
+        })
+    }
+}
+
+/// Names of a PCI device, the subsystem is the board of a specific vendor if listed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PciNames {
+    pub vendor: String,
+    pub device: String,
+    pub subsystem: Option<String>,
+}
+
+/// Look up the ids in the contents of a pci.ids file
+pub fn parse(content: &str, ids: &PciIds) -> Option<PciNames> {
+    let mut names: Option<PciNames> = None;
+    let mut in_vendor = false;
+
+    for line in content.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        let depth = line.chars().take_while(|c| *c == '\t').count();
+        let Some((id, name)) = line.trim_start_matches('\t').split_once("  ") else {
+            continue;
+        };
+
+        match depth {
+            0 => {
+                // Device classes follow the vendors, there's nothing of interest after them
+                if line.starts_with("C ") || names.is_some() {
+                    break;
+                }
+                in_vendor = id == ids.vendor;
+                if in_vendor {
+                    names = Some(PciNames {
+                        vendor: name.trim().to_string(),
+                        ..Default::default()
+                    });
+                }
+            }
+            1 if in_vendor => {
+                let names = names.as_mut()?;
+                if !names.device.is_empty() {
+                    // Past the subsystems of our device
+                    break;
+                }
+                if id == ids.device {
+                    names.device = name.trim().to_string();
+                }
+            }
+            2 if in_vendor => {
+                let Some(names) = names.as_mut().filter(|n| !n.device.is_empty()) else {
+                    continue;
+                };
+                if id == format!("{} {}", ids.subsystem_vendor, ids.subsystem_device) {
+                    names.subsystem = Some(name.trim().to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    names.filter(|n| !n.device.is_empty())
+}
+
+/// Names of the PCI device in a slot like 0000:03:00.0, None if the database or device isn't known
+pub fn lookup(slot: &str) -> Option<PciNames> {
+    let ids = PciIds::read(slot)?;
+    let Some(content) = PCI_IDS_PATHS
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+    else {
+        info!("No pci.ids database found");
+        return None;
+    };
+
+    let names = parse(&content, &ids);
+    debug!("pci_ids::lookup({slot}) {ids:?} -> {names:?}");
+    names
+}
+
+/// Turn "Advanced Micro Devices, Inc. [AMD/ATI] Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]"
+/// into "Advanced Micro Devices, Inc. Navi 21 (Radeon RX 6800/6800 XT / 6900 XT)"
+pub fn clean_gpu_name(vendor: &str, device: &str) -> String {
+    format!("{vendor} {device}")
+        .replace("Corporation", "")
+        .replace("[AMD/ATI]", "")
+        .replace(':', "")
+        .replace("  ", " ")
+        .replace('[', "(")
+        .replace(']', ")")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PCI_IDS: &str = "\
+# Vendors, devices and subsystems.
+#
+1002  Advanced Micro Devices, Inc. [AMD/ATI]
+\t73bf  Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]
+\t\t1002 0e3a  Radeon RX 6900 XT
+\t\t1da2 e438  Radeon RX 6800 XT Nitro+
+\t73df  Navi 22 [Radeon RX 6700/6700 XT/6750 XT / 6800M/6850M XT]
+10de  NVIDIA Corporation
+\t2684  AD102 [GeForce RTX 4090]
+\t\t1043 889d  ROG Strix GeForce RTX 4090
+
+C 03  Display controller
+\t00  VGA compatible controller
+";
+
+    fn ids(vendor: &str, device: &str, subsystem_vendor: &str, subsystem_device: &str) -> PciIds {
+        PciIds {
+            vendor: vendor.to_string(),
+            device: device.to_string(),
+            subsystem_vendor: subsystem_vendor.to_string(),
+            subsystem_device: subsystem_device.to_string(),
+        }
+    }
+
+    #[test]
+    fn device_with_subsystem() {
+        assert_eq!(
+            parse(PCI_IDS, &ids("1002", "73bf", "1da2", "e438")),
+            Some(PciNames {
+                vendor: String::from("Advanced Micro Devices, Inc. [AMD/ATI]"),
+                device: String::from("Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]"),
+                subsystem: Some(String::from("Radeon RX 6800 XT Nitro+")),
+            })
+        );
+    }
+
+    #[test]
+    fn device_without_listed_subsystem() {
+        let names = parse(PCI_IDS, &ids("10de", "2684", "1458", "4104")).unwrap();
+        assert_eq!(names.vendor, "NVIDIA Corporation");
+        assert_eq!(names.device, "AD102 [GeForce RTX 4090]");
+        assert_eq!(names.subsystem, None);
+
+        // A subsystem of another device of the vendor doesn't match
+        let names = parse(PCI_IDS, &ids("1002", "73df", "1002", "0e3a")).unwrap();
+        assert_eq!(names.subsystem, None);
+    }
+
+    #[test]
+    fn unknown_device() {
+        assert_eq!(parse(PCI_IDS, &ids("1002", "ffff", "", "")), None);
+        assert_eq!(parse(PCI_IDS, &ids("8086", "56a0", "", "")), None);
+        // The class section has the same layout, its ids aren't devices
+        assert_eq!(parse(PCI_IDS, &ids("03", "00", "", "")), None);
+    }
+
+    #[test]
+    fn clean_names() {
+        assert_eq!(
+            clean_gpu_name(
+                "Advanced Micro Devices, Inc. [AMD/ATI]",
+                "Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]"
+            ),
+            "Advanced Micro Devices, Inc. Navi 21 (Radeon RX 6800/6800 XT / 6900 XT)"
+        );
+        assert_eq!(
+            clean_gpu_name("NVIDIA Corporation", "AD102 [GeForce RTX 4090]"),
+            "NVIDIA AD102 (GeForce RTX 4090)"
+        );
+        assert_eq!(
+            clean_gpu_name("Intel Corporation", "Meteor Lake NPU"),
+            "Intel Meteor Lake NPU"
+        );
+        assert_eq!(
+            clean_gpu_name("NVIDIA Corporation", "TU117M: GeForce GTX 1650 Mobile"),
+            "NVIDIA TU117M GeForce GTX 1650 Mobile"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Runtime power management state of a PCI GPU, from /sys/bus/pci/devices/<slot>/power.
+//! Reading these files never wakes a suspended device.
+
+use log::debug;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::fl;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    Active,
+    Suspending,
+    Suspended,
+    Resuming,
+    // power/control is "on", the kernel keeps the device powered
+    AlwaysOn,
+}
+
+impl PowerState {
+    /// Parse the contents of power/runtime_status and power/control
+    pub fn parse(status: &str, control: &str) -> Option<PowerState> {
+        match (status.trim(), control.trim()) {
+            ("suspended", _) => Some(PowerState::Suspended),
+            ("suspending", _) => Some(PowerState::Suspending),
+            ("resuming", _) => Some(PowerState::Resuming),
+            ("active", "on") => Some(PowerState::AlwaysOn),
+            ("active", _) => Some(PowerState::Active),
+            _ => None,
+        }
+    }
+
+    /// State of the device in a slot like 0000:01:00.0, None if it has no runtime PM
+    pub fn read(slot: &str) -> Option<PowerState> {
+        let base = format!("/sys/bus/pci/devices/{slot}/power");
+        let status = fs::read_to_string(format!("{base}/runtime_status")).ok()?;
+        let control = fs::read_to_string(format!("{base}/control")).unwrap_or_default();
+        PowerState::parse(&status, &control)
+    }
+
+    pub fn is_awake(self) -> bool {
+        matches!(
+            self,
+            PowerState::Active | PowerState::Resuming | PowerState::AlwaysOn
+        )
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            PowerState::Active | PowerState::AlwaysOn => "power-profile-performance-symbolic",
+            PowerState::Suspended => "power-profile-power-saver-symbolic",
+            PowerState::Suspending | PowerState::Resuming => "power-profile-balanced-symbolic",
+        }
+    }
+}
+
+impl std::fmt::Display for PowerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PowerState::Active => write!(f, "{}", fl!("gpu-power-active")),
+            PowerState::Suspending => write!(f, "{}", fl!("gpu-power-suspending")),
+            PowerState::Suspended => write!(f, "{}", fl!("gpu-power-suspended")),
+            PowerState::Resuming => write!(f, "{}", fl!("gpu-power-resuming")),
+            PowerState::AlwaysOn => write!(f, "{}", fl!("gpu-power-always-on")),
+        }
+    }
+}
+
+/// A process with one of the GPU's device nodes open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceHolder {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// Device nodes of the GPU in a slot: its DRM card and render node, and /dev/nvidiaN
+pub fn device_nodes(slot: &str) -> Vec<PathBuf> {
+    let mut nodes: Vec<PathBuf> = fs::read_dir(format!("/sys/bus/pci/devices/{slot}/drm"))
+        .into_iter()
+        .flat_map(|entries| entries.flatten())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("card") || name.starts_with("renderD"))
+        .map(|name| PathBuf::from(format!("/dev/dri/{name}")))
+        .collect();
+
+    if let Ok(info) = fs::read_to_string(format!("/proc/driver/nvidia/gpus/{slot}/information"))
+        && let Some(minor) = info
+            .lines()
+            .find_map(|line| line.strip_prefix("Device Minor:"))
+    {
+        nodes.push(PathBuf::from(format!("/dev/nvidia{}", minor.trim())));
+    }
+
+    nodes.sort();
+    nodes
+}
+
+/// Processes keeping any of the nodes open, sorted by pid.
+/// Only processes the user may inspect are visible.
+pub fn holders(nodes: &[PathBuf]) -> Vec<DeviceHolder> {
+    let mut holders = Vec::new();
+    if nodes.is_empty() {
+        return holders;
+    }
+
+    let Ok(procs) = fs::read_dir("/proc") else {
+        return holders;
+    };
+
+    for proc in procs.flatten() {
+        let Some(pid) = proc
+            .file_name()
+            .to_str()
+            .and_then(|p| p.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(proc.path().join("fd")) else {
+            continue;
+        };
+
+        let holds = fds
+            .flatten()
+            .filter_map(|fd| fs::read_link(fd.path()).ok())
+            .any(|target| nodes.contains(&target));
+
+        if holds {
+            holders.push(DeviceHolder {
+                pid,
+                name: fs::read_to_string(proc.path().join("comm"))
+                    .map(|n| n.trim().to_string())
+                    .unwrap_or_else(|_| pid.to_string()),
+            });
+        }
+    }
+
+    holders.sort_by_key(|h| h.pid);
+    debug!("power::holders({nodes:?}) {holders:?}");
+    holders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_runtime_status() {
+        assert_eq!(
+            PowerState::parse("suspended\n", "auto\n"),
+            Some(PowerState::Suspended)
+        );
+        assert_eq!(
+            PowerState::parse("suspending\n", "auto\n"),
+            Some(PowerState::Suspending)
+        );
+        assert_eq!(
+            PowerState::parse("resuming\n", "auto\n"),
+            Some(PowerState::Resuming)
+        );
+        assert_eq!(
+            PowerState::parse("active\n", "auto\n"),
+            Some(PowerState::Active)
+        );
+        // Runtime PM turned off, the GPU never sleeps
+        assert_eq!(
+            PowerState::parse("active\n", "on\n"),
+            Some(PowerState::AlwaysOn)
+        );
+        // Missing control file
+        assert_eq!(PowerState::parse("active", ""), Some(PowerState::Active));
+    }
+
+    #[test]
+    fn parse_without_runtime_pm() {
+        assert_eq!(PowerState::parse("unsupported\n", "auto\n"), None);
+        assert_eq!(PowerState::parse("error\n", "on\n"), None);
+        assert_eq!(PowerState::parse("", ""), None);
+    }
+
+    #[test]
+    fn awake_states() {
+        assert!(PowerState::Active.is_awake());
+        assert!(PowerState::AlwaysOn.is_awake());
+        assert!(PowerState::Resuming.is_awake());
+        assert!(!PowerState::Suspended.is_awake());
+        assert!(!PowerState::Suspending.is_awake());
+    }
+}
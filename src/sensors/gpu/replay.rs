@@ -0,0 +1,68 @@
+//! A GPU as recorded in a trace. Gpu::update takes the readings from the trace, this
+//! only stands in for the detected device.
+
+use anyhow::{Result, anyhow};
+
+use crate::config::GpuMetric;
+use crate::trace::GpuInfo;
+
+pub struct ReplayGpu {
+    info: GpuInfo,
+    active: bool,
+}
+
+impl ReplayGpu {
+    pub fn new(info: GpuInfo) -> Self {
+        ReplayGpu { info, active: true }
+    }
+}
+
+impl super::GpuIf for ReplayGpu {
+    fn name(&self) -> String {
+        self.info.name.clone()
+    }
+
+    fn id(&self) -> String {
+        self.info.id.clone()
+    }
+
+    fn usage(&self) -> Result<u32> {
+        Err(anyhow!("Replayed GPU"))
+    }
+
+    fn temperature(&self) -> Result<u32> {
+        Err(anyhow!("Replayed GPU"))
+    }
+
+    fn vram_total(&self) -> u64 {
+        self.info.vram_total
+    }
+
+    fn vram_used(&self) -> Result<u64> {
+        Err(anyhow!("Replayed GPU"))
+    }
+
+    fn has_temperature(&self) -> bool {
+        self.info.has_temperature
+    }
+
+    fn is_accelerator(&self) -> bool {
+        self.info.accelerator
+    }
+
+    fn metrics(&self) -> Vec<GpuMetric> {
+        self.info.metrics.clone()
+    }
+
+    fn stop(&mut self) {
+        self.active = false;
+    }
+
+    fn restart(&mut self) {
+        self.active = true;
+    }
+
+    fn is_active(&self) -> bool {
+        self.active
+    }
+}
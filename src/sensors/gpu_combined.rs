@@ -0,0 +1,522 @@
+use crate::{
+    alerts, color_stops,
+    colorpicker::DemoGraph,
+    config::{
+        ChartColors, ChartKind, ColorVariant, DeviceKind, GpuCombinedConfig, GpuCombinedMode,
+    },
+    fl,
+    history::{self, History, Series},
+    sensors::INVALID_IMG,
+    svg_graph::SvgColors,
+};
+use cosmic::{Element, iced::Alignment::Center, widget::Container};
+
+use cosmic::widget;
+use cosmic::widget::{settings, toggler};
+
+use cosmic::{
+    iced::{
+        Alignment,
+        widget::{column, row},
+    },
+    iced_widget::Row,
+};
+
+use crate::app::Message;
+use std::any::Any;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::LazyLock;
+
+use bounded_vec_deque::BoundedVecDeque;
+
+use super::TempUnit;
+use super::gpus::Gpu;
+
+const MAX_SAMPLES: usize = 21;
+
+static MODE_OPTIONS: LazyLock<[&'static str; 2]> = LazyLock::new(|| {
+    [
+        fl!("gpu-combined-average").leak(),
+        fl!("gpu-combined-max").leak(),
+    ]
+});
+
+static COLOR_CHOICES_RING: LazyLock<[(&'static str, ColorVariant); 5]> = LazyLock::new(|| {
+    [
+        (fl!("graph-gpu-usage").leak(), ColorVariant::Graph1),
+        (fl!("graph-gpu-vram").leak(), ColorVariant::Graph3),
+        (fl!("graph-ring-unused").leak(), ColorVariant::Graph2),
+        (fl!("graph-ring-back").leak(), ColorVariant::Background),
+        (fl!("graph-ring-text").leak(), ColorVariant::Text),
+    ]
+});
+
+static COLOR_CHOICES_LAYERS: LazyLock<[(&'static str, ColorVariant); 5]> = LazyLock::new(|| {
+    [
+        (fl!("graph-gpu-1").leak(), ColorVariant::Graph1),
+        (fl!("graph-gpu-2").leak(), ColorVariant::Graph2),
+        (fl!("graph-gpu-3").leak(), ColorVariant::Graph3),
+        (fl!("graph-line-back").leak(), ColorVariant::Background),
+        (fl!("graph-line-frame").leak(), ColorVariant::Frame),
+    ]
+});
+
+/// One item for all GPUs: combined usage, summed VRAM and the hottest temperature
+pub struct GpuCombined {
+    usage: BoundedVecDeque<f64>,
+    // Share of each card in the combined usage, stacked in the line chart
+    layers: BTreeMap<String, BoundedVecDeque<f64>>,
+    // The same shares for the longer chart windows
+    layer_history: BTreeMap<String, History>,
+    vram_used: f64,
+    vram_total: f64,
+    // Celcius, 0 if no active GPU reports a temperature
+    hottest: f64,
+    cards: usize,
+    graph_options: Vec<&'static str>,
+    svg_colors: SvgColors,
+    config: GpuCombinedConfig,
+}
+
+impl Default for GpuCombined {
+    fn default() -> Self {
+        GpuCombined {
+            usage: BoundedVecDeque::from_iter(std::iter::repeat_n(0.0, MAX_SAMPLES), MAX_SAMPLES),
+            layers: BTreeMap::new(),
+            layer_history: BTreeMap::new(),
+            vram_used: 0.0,
+            vram_total: 0.0,
+            hottest: 0.0,
+            cards: 0,
+            graph_options: super::GRAPH_OPTIONS_RING_LINE.to_vec(),
+            svg_colors: SvgColors::new(&ChartColors::default()),
+            config: GpuCombinedConfig::default(),
+        }
+    }
+}
+
+impl DemoGraph for GpuCombined {
+    fn demo(&self) -> String {
+        match self.config.chart {
+            ChartKind::Ring => crate::svg_graph::ring("40", 40, Some(25), &self.svg_colors),
+            ChartKind::Line => {
+                let layers: Vec<VecDeque<f64>> =
+                    DEMO_SAMPLES.iter().map(|s| VecDeque::from(*s)).collect();
+                crate::svg_graph::line_layers(&layers, 100.0, &self.svg_colors)
+            }
+            _ => {
+                log::error!(
+                    "GpuCombined graph type not supported {:?}",
+                    self.config.chart
+                );
+                INVALID_IMG.to_string()
+            }
+        }
+    }
+
+    fn colors(&self) -> &ChartColors {
+        self.config.colors()
+    }
+
+    fn set_colors(&mut self, colors: &ChartColors) {
+        *self.config.colors_mut() = *colors;
+        self.svg_colors.set_colors(colors);
+    }
+
+    fn color_choices(&self) -> Vec<(&'static str, ColorVariant)> {
+        if self.config.chart == ChartKind::Line {
+            (*COLOR_CHOICES_LAYERS).into()
+        } else {
+            (*COLOR_CHOICES_RING).into()
+        }
+    }
+
+    fn id(&self) -> Option<String> {
+        None
+    }
+
+    fn kind(&self) -> ChartKind {
+        self.config.chart
+    }
+}
+
+impl GpuCombined {
+    pub fn update_config(&mut self, config: &dyn Any, _refresh_rate: u32) {
+        if let Some(cfg) = config.downcast_ref::<GpuCombinedConfig>() {
+            self.config = cfg.clone();
+            self.svg_colors.set_colors(cfg.colors());
+        }
+    }
+
+    pub fn graph_kind(&self) -> ChartKind {
+        self.config.chart
+    }
+
+    pub fn set_graph_kind(&mut self, kind: ChartKind) {
+        assert!(kind == ChartKind::Line || kind == ChartKind::Ring);
+        self.config.chart = kind;
+        self.svg_colors.set_colors(self.config.colors());
+    }
+
+    pub fn demo_graph(&self) -> Box<dyn DemoGraph> {
+        let mut dmo = GpuCombined::default();
+        dmo.update_config(&self.config, 0);
+        Box::new(dmo)
+    }
+
+    /// Combine the latest samples of all GPUs. Paused GPUs count for VRAM only,
+    /// accelerators not at all.
+    pub fn update(&mut self, gpus: &BTreeMap<String, Gpu>) {
+        let gpus: BTreeMap<&String, &Gpu> =
+            gpus.iter().filter(|(_, g)| !g.is_accelerator()).collect();
+        let active: Vec<&Gpu> = gpus.values().copied().filter(|g| g.is_active()).collect();
+        let usages: Vec<f64> = active.iter().map(|g| g.gpu.latest_sample()).collect();
+
+        let combined = match self.config.mode {
+            GpuCombinedMode::Average if !usages.is_empty() => {
+                usages.iter().sum::<f64>() / usages.len() as f64
+            }
+            GpuCombinedMode::Max => usages.iter().copied().fold(0.0, f64::max),
+            GpuCombinedMode::Average => 0.0,
+        };
+        self.usage.push_back(combined);
+
+        // Scale each card so the stacked layers add up to the combined value
+        let sum: f64 = usages.iter().sum();
+        let scale = if sum > 0.0 { combined / sum } else { 0.0 };
+
+        self.layers.retain(|id, _| gpus.contains_key(id));
+        self.layer_history.retain(|id, _| gpus.contains_key(id));
+        for (&id, gpu) in &gpus {
+            let share = if gpu.is_active() {
+                gpu.gpu.latest_sample() * scale
+            } else {
+                0.0
+            };
+            self.layers
+                .entry(id.clone())
+                .or_insert_with(|| {
+                    BoundedVecDeque::from_iter(std::iter::repeat_n(0.0, MAX_SAMPLES), MAX_SAMPLES)
+                })
+                .push_back(share);
+            self.layer_history
+                .entry(id.clone())
+                .or_default()
+                .push_now(share);
+        }
+
+        self.vram_used = gpus.values().map(|g| g.vram.latest_sample()).sum();
+        self.vram_total = gpus.values().map(|g| g.vram.total()).sum();
+        self.hottest = active
+            .iter()
+            .map(|g| g.temp.latest_sample())
+            .fold(0.0, f64::max);
+        self.cards = gpus.len();
+    }
+
+    pub fn latest_sample(&self) -> f64 {
+        *self.usage.back().unwrap_or(&0f64)
+    }
+
+    /// The layer histories of the GPUs to keep across restarts and export, by GPU id
+    pub fn histories_mut(&mut self, ids: &[String]) -> Vec<Series<'_>> {
+        for id in ids {
+            self.layer_history.entry(id.clone()).or_default();
+        }
+        self.layer_history
+            .iter_mut()
+            .map(|(id, history)| Series::new(id.clone(), "%", history))
+            .collect()
+    }
+
+    pub fn usage_label(&self) -> String {
+        let value = self.latest_sample();
+        if value < 10.0 {
+            format!("{:.2}%", (value * 100.0).trunc() / 100.0)
+        } else if value < 100.0 {
+            format!("{:.1}%", (value * 10.0).trunc() / 10.0)
+        } else {
+            format!("{value}%")
+        }
+    }
+
+    pub fn vram_label(&self, vertical_panel: bool) -> String {
+        let unit = if vertical_panel { "GB" } else { " GB" };
+        format!("{:.1}/{:.0}{unit}", self.vram_used, self.vram_total)
+    }
+
+    pub fn temp_label(&self) -> String {
+        let celcius = self.hottest;
+        if celcius <= 0.0 {
+            return match self.config.unit {
+                TempUnit::Celcius => String::from("--C"),
+                TempUnit::Farenheit => String::from("---F"),
+                TempUnit::Kelvin => String::from("---K"),
+                TempUnit::Rankine => String::from("---R"),
+            };
+        }
+        match self.config.unit {
+            TempUnit::Celcius => format!("{}C", celcius.trunc()),
+            TempUnit::Farenheit => format!("{}F", (celcius * 9.0 / 5.0 + 32.0).trunc()),
+            TempUnit::Kelvin => format!("{}K", (celcius + 273.15).trunc()),
+            TempUnit::Rankine => format!("{}R", (celcius * 9.0 / 5.0 + 491.67).trunc()),
+        }
+    }
+
+    /// Overview text, like "35.2% 10.1/32 GB 68C"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.usage_label(),
+            self.vram_label(false),
+            self.temp_label()
+        )
+    }
+
+    pub fn chart(
+        &'_ self,
+    ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
+        let svg = match self.config.chart {
+            ChartKind::Ring => {
+                let latest = self.latest_sample();
+                let value = if latest < 10.0 {
+                    format!("{latest:.1}")
+                } else {
+                    format!("{}", latest.round())
+                };
+                let vram_pct = if self.vram_total > 0.0 {
+                    Some(
+                        (self.vram_used / self.vram_total * 100.0)
+                            .round()
+                            .clamp(0.0, 100.0) as u8,
+                    )
+                } else {
+                    None
+                };
+                crate::svg_graph::ring(
+                    &value,
+                    latest.round().clamp(0.0, 100.0) as u8,
+                    vram_pct,
+                    &self
+                        .svg_colors
+                        .with_stops(&self.config.color_stops, Some(latest)),
+                )
+            }
+            ChartKind::Line => {
+                let empty = History::default();
+                let layers: Vec<VecDeque<f64>> = self
+                    .layers
+                    .iter()
+                    .map(|(id, l)| {
+                        let history = self.layer_history.get(id).unwrap_or(&empty);
+                        history::chart_samples(l, history, self.config.window).into_owned()
+                    })
+                    .collect();
+                crate::svg_graph::line_layers(&layers, 100.0, &self.svg_colors)
+            }
+            _ => {
+                log::error!(
+                    "GpuCombined graph type not supported {:?}",
+                    self.config.chart
+                );
+                INVALID_IMG.to_string()
+            }
+        };
+
+        let icon = cosmic::widget::icon::from_svg_bytes(svg.into_bytes());
+        widget::Container::new(
+            icon.icon()
+                .height(cosmic::iced::Length::Fill)
+                .width(cosmic::iced::Length::Fill),
+        )
+    }
+
+    pub fn settings_ui(&'_ self) -> Element<'_, crate::app::Message> {
+        let theme = cosmic::theme::active();
+        let cosmic = theme.cosmic();
+        let config = &self.config;
+
+        let mut elements = Vec::new();
+        elements.push(Element::from(
+            column!(
+                Container::new(self.chart().width(60).height(60))
+                    .width(90)
+                    .align_x(Alignment::Center),
+                cosmic::widget::text::body(self.usage_label())
+                    .width(90)
+                    .align_x(Alignment::Center)
+            )
+            .padding(5)
+            .align_x(Alignment::Center),
+        ));
+
+        let selected_graph: Option<usize> = Some(self.graph_kind().into());
+        let selected_mode: Option<usize> = Some(config.mode.into());
+        let selected_unit: Option<usize> = Some(config.unit.into());
+        let kind = self.graph_kind();
+
+        elements.push(Element::from(
+            column!(
+                settings::item(
+                    fl!("enable-chart"),
+                    toggler(config.chart_visible()).on_toggle(Message::ToggleGpuCombinedChart),
+                ),
+                settings::item(
+                    fl!("enable-label"),
+                    toggler(config.label_visible()).on_toggle(Message::ToggleGpuCombinedLabel),
+                ),
+                settings::item(
+                    fl!("gpu-combined-mode"),
+                    widget::dropdown(&*MODE_OPTIONS, selected_mode, |m| {
+                        Message::SelectGpuCombinedMode(m.into())
+                    })
+                ),
+                row!(
+                    widget::text::body(fl!("chart-type")),
+                    widget::dropdown(&self.graph_options, selected_graph, |m| {
+                        Message::SelectGraphType(DeviceKind::GpuCombined, m.into())
+                    },)
+                    .width(70),
+                    widget::horizontal_space(),
+                    widget::button::standard(fl!("change-colors")).on_press(
+                        Message::ColorPickerOpen(DeviceKind::GpuCombined, kind, None)
+                    ),
+                )
+                .align_y(Center)
+            )
+            .spacing(cosmic.space_xs()),
+        ));
+
+        column!(
+            widget::text::heading(fl!("gpu-combined-title", count = self.cards)),
+            Row::with_children(elements).align_y(Alignment::Center),
+            settings::item(
+                fl!("gpu-combined-vram"),
+                toggler(config.vram_label).on_toggle(Message::ToggleGpuCombinedVramLabel),
+            ),
+            settings::item(
+                fl!("gpu-combined-temp"),
+                toggler(config.temp_label).on_toggle(Message::ToggleGpuCombinedTempLabel),
+            ),
+            settings::item(
+                fl!("temperature-unit"),
+                widget::dropdown(&*super::UNIT_OPTIONS, selected_unit, |m| {
+                    Message::SelectGpuCombinedTempUnit(m.into())
+                })
+            ),
+            alerts::settings_ui(DeviceKind::GpuCombined, None, &config.alerts, "%"),
+            color_stops::settings_ui(DeviceKind::GpuCombined, None, &config.color_stops, "%"),
+        )
+        .push_maybe(
+            (config.chart == ChartKind::Line)
+                .then(|| history::window_ui(DeviceKind::GpuCombined, None, config.window)),
+        )
+        .spacing(cosmic::theme::spacing().space_xs)
+        .into()
+    }
+}
+
+const DEMO_SAMPLES: [[f64; 21]; 2] = [
+    [
+        10.0, 12.0, 12.0, 14.0, 18.0, 25.0, 30.0, 32.0, 31.0, 28.0, 26.0, 25.0, 27.0, 30.0, 34.0,
+        35.0, 33.0, 30.0, 26.0, 22.0, 20.0,
+    ],
+    [
+        5.0, 5.0, 6.0, 8.0, 10.0, 12.0, 15.0, 20.0, 24.0, 26.0, 25.0, 22.0, 18.0, 15.0, 12.0, 10.0,
+        10.0, 12.0, 14.0, 15.0, 15.0,
+    ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::gpu::mock::{MockGpu, Reading};
+
+    const GB: u64 = 1_073_741_824;
+
+    // A GPU that took one sample, with 1 of its 8 GB VRAM in use
+    fn sampled_gpu(id: &str, usage: u32, celcius: u32, accelerator: bool) -> (String, Gpu) {
+        let (mock, script) = MockGpu::new(id, 8 * GB);
+        {
+            let mut s = script.borrow_mut();
+            s.accelerator = accelerator;
+            s.usage.push_back(Reading::Value(usage));
+            s.temperature.push_back(Reading::Value(celcius * 1000));
+            s.vram_used.push_back(Reading::Value(GB));
+        }
+        let mut gpu = Gpu::new(Box::new(mock));
+        gpu.update();
+        (id.to_string(), gpu)
+    }
+
+    fn combined(mode: GpuCombinedMode, gpus: &BTreeMap<String, Gpu>) -> GpuCombined {
+        let mut combined = GpuCombined::default();
+        let config = GpuCombinedConfig {
+            mode,
+            ..Default::default()
+        };
+        combined.update_config(&config, 1000);
+        combined.update(gpus);
+        combined
+    }
+
+    fn layer(combined: &GpuCombined, id: &str) -> f64 {
+        *combined.layers[id].back().unwrap()
+    }
+
+    #[test]
+    fn average_and_max() {
+        let gpus = BTreeMap::from([
+            sampled_gpu("a", 20, 50, false),
+            sampled_gpu("b", 60, 70, false),
+        ]);
+
+        let average = combined(GpuCombinedMode::Average, &gpus);
+        assert_eq!(average.latest_sample(), 40.0);
+        assert_eq!(average.summary(), "40.0% 2.0/16 GB 70C");
+
+        let max = combined(GpuCombinedMode::Max, &gpus);
+        assert_eq!(max.latest_sample(), 60.0);
+    }
+
+    #[test]
+    fn layers_add_up_to_combined() {
+        let gpus = BTreeMap::from([
+            sampled_gpu("a", 20, 50, false),
+            sampled_gpu("b", 60, 70, false),
+        ]);
+
+        // Scaled by combined / sum, 40 / 80 and 60 / 80
+        let average = combined(GpuCombinedMode::Average, &gpus);
+        assert_eq!(layer(&average, "a"), 10.0);
+        assert_eq!(layer(&average, "b"), 30.0);
+
+        let max = combined(GpuCombinedMode::Max, &gpus);
+        assert_eq!(layer(&max, "a"), 15.0);
+        assert_eq!(layer(&max, "b"), 45.0);
+
+        let idle = BTreeMap::from([sampled_gpu("a", 0, 50, false)]);
+        assert_eq!(layer(&combined(GpuCombinedMode::Average, &idle), "a"), 0.0);
+    }
+
+    #[test]
+    fn paused_gpus_and_accelerators_excluded() {
+        let (id, mut paused) = sampled_gpu("b", 60, 70, false);
+        paused.stop();
+        let gpus = BTreeMap::from([
+            sampled_gpu("a", 20, 50, false),
+            (id, paused),
+            sampled_gpu("npu", 90, 95, true),
+        ]);
+
+        let combined = combined(GpuCombinedMode::Average, &gpus);
+        assert_eq!(combined.latest_sample(), 20.0);
+        assert_eq!(combined.temp_label(), "50C");
+        // The paused GPU keeps its place in the stack and its VRAM total
+        assert_eq!(layer(&combined, "a"), 20.0);
+        assert_eq!(layer(&combined, "b"), 0.0);
+        assert!(!combined.layers.contains_key("npu"));
+        assert_eq!(combined.vram_label(true), "1.0/16GB");
+        assert_eq!(combined.cards, 2);
+    }
+}
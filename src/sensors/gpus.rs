@@ -16,21 +16,42 @@ use cosmic::{
 };
 
 use super::TempUnit;
+use crate::alerts::{self, AlertSample};
 use crate::app::Message;
+use crate::color_stops;
 use crate::colorpicker::DemoGraph;
-use crate::config::DeviceKind;
+use crate::config::{AlertConfig, DeviceKind, GpuMetric, GpuMetricConfig};
+use crate::history::{self, History, Series};
+use crate::prometheus::Metrics;
+use crate::trace::{self, GpuInfo};
 use crate::{
     config::{ChartColors, ChartKind, ColorVariant, GpuTempConfig, GpuUsageConfig, GpuVramConfig},
     fl,
     svg_graph::SvgColors,
 };
 use std::any::Any;
+#[cfg(not(feature = "lyon_charts"))]
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 
+use super::gpu::accel::AccelGpu;
 use super::gpu::amd::AmdGpu;
+use super::gpu::fdinfo::GpuProcess;
 use super::gpu::intel::IntelGpu;
-use super::gpu::{GpuIf, nvidia::NvidiaGpu};
+use super::gpu::nouveau::NouveauGpu;
+use super::gpu::power::{self, DeviceHolder, PowerState};
+use super::gpu::replay::ReplayGpu;
+use super::gpu::{GpuIf, ThrottleReason, nvidia::NvidiaGpu};
 
 const MAX_SAMPLES: usize = 21;
+// Number of processes listed in the popup
+const TOP_PROCESSES: usize = 5;
+// Number of wake-ups from runtime suspend kept for the popup
+const MAX_WAKE_UPS: usize = 10;
+
+// Chart scale of the hot spot temperatures when the GPU has no critical limit
+const DEFAULT_CRITICAL_TEMP: f64 = 110.0;
 
 #[cfg(feature = "lyon_charts")]
 use std::sync::LazyLock;
@@ -45,6 +66,7 @@ static DISABLED_COLORS: LazyLock<ChartColors> = LazyLock::new(|| ChartColors {
 pub struct GpuGraph {
     id: String,
     samples: BoundedVecDeque<f64>,
+    history: History,
     graph_options: Vec<&'static str>,
     svg_colors: SvgColors,
     disabled: bool,
@@ -63,6 +85,7 @@ impl GpuGraph {
         GpuGraph {
             id: id.to_owned(),
             samples: BoundedVecDeque::from_iter(std::iter::repeat_n(0.0, MAX_SAMPLES), MAX_SAMPLES),
+            history: History::default(),
             graph_options: super::GRAPH_OPTIONS_RING_LINE.to_vec(),
             svg_colors: SvgColors::new(&ChartColors::default()),
             disabled: false,
@@ -133,6 +156,12 @@ impl GpuGraph {
     pub fn chart(
         &'_ self,
     ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
+        let colors = if self.disabled {
+            Cow::Borrowed(&self.disabled_colors)
+        } else {
+            self.svg_colors
+                .with_stops(&self.config.color_stops, Some(self.latest_sample()))
+        };
         let svg = if self.config.chart == ChartKind::Ring {
             let latest = self.latest_sample();
             let mut value = String::with_capacity(10);
@@ -151,25 +180,12 @@ impl GpuGraph {
                 percentage = latest.round().clamp(0.0, 100.0) as u8;
             }
 
-            crate::svg_graph::ring(
-                &value,
-                percentage,
-                None,
-                if self.disabled {
-                    &self.disabled_colors
-                } else {
-                    &self.svg_colors
-                },
-            )
+            crate::svg_graph::ring(&value, percentage, None, &colors)
         } else {
             crate::svg_graph::line(
-                &self.samples,
+                &history::chart_samples(&self.samples, &self.history, self.config.window),
                 100.0,
-                if self.disabled {
-                    &self.disabled_colors
-                } else {
-                    &self.svg_colors
-                },
+                &colors,
             )
         };
 
@@ -195,6 +211,7 @@ impl GpuGraph {
 
     pub fn update(&mut self, sample: u32) {
         self.samples.push_back(f64::from(sample));
+        self.history.push_now(f64::from(sample));
     }
 }
 
@@ -265,6 +282,7 @@ impl DemoGraph for GpuGraph {
 pub struct VramGraph {
     id: String,
     samples: BoundedVecDeque<f64>,
+    history: History,
     graph_options: Vec<&'static str>,
     total: f64,
     svg_colors: SvgColors,
@@ -279,6 +297,7 @@ impl VramGraph {
         VramGraph {
             id: id.to_owned(),
             samples: BoundedVecDeque::from_iter(std::iter::repeat_n(0.0, MAX_SAMPLES), MAX_SAMPLES),
+            history: History::default(),
             graph_options: super::GRAPH_OPTIONS_RING_LINE.to_vec(),
             total,
             svg_colors: SvgColors::new(&ChartColors::default()),
@@ -354,6 +373,12 @@ impl VramGraph {
 
     #[cfg(not(feature = "lyon_charts"))]
     pub fn chart(&'_ self) -> cosmic::widget::Container<'_, crate::app::Message, Theme, Renderer> {
+        let colors = if self.disabled {
+            Cow::Borrowed(&self.disabled_colors)
+        } else {
+            self.svg_colors
+                .with_stops(&self.config.color_stops, self.percentage())
+        };
         let svg = if self.config.chart == ChartKind::Ring {
             let latest = self.latest_sample();
             let mut value = String::with_capacity(10);
@@ -371,25 +396,12 @@ impl VramGraph {
                 }
                 percentage = ((latest / self.total) * 100.0).round().clamp(0.0, 100.0) as u8;
             }
-            crate::svg_graph::ring(
-                &value,
-                percentage,
-                None,
-                if self.disabled {
-                    &self.disabled_colors
-                } else {
-                    &self.svg_colors
-                },
-            )
+            crate::svg_graph::ring(&value, percentage, None, &colors)
         } else {
             crate::svg_graph::line(
-                &self.samples,
+                &history::chart_samples(&self.samples, &self.history, self.config.window),
                 self.total,
-                if self.disabled {
-                    &self.disabled_colors
-                } else {
-                    &self.svg_colors
-                },
+                &colors,
             )
         };
         let icon = cosmic::widget::icon::from_svg_bytes(svg.into_bytes());
@@ -431,15 +443,22 @@ impl VramGraph {
         self.total
     }
 
+    /// Used share of the total, None if the total is unknown
+    pub fn percentage(&self) -> Option<f64> {
+        (self.total > 0.0).then(|| self.latest_sample() * 100.0 / self.total)
+    }
+
     pub fn update(&mut self, sample: u64) {
         let new_val: f64 = sample as f64 / 1_073_741_824.0;
         self.samples.push_back(new_val);
+        self.history.push_now(new_val);
     }
 }
 
 pub struct TempGraph {
     id: String,
     samples: BoundedVecDeque<f64>,
+    history: History,
     unit_options: Vec<&'static str>,
     graph_options: Vec<&'static str>,
     max_temp: f64,
@@ -455,6 +474,7 @@ impl TempGraph {
         TempGraph {
             id: id.to_owned(),
             samples: BoundedVecDeque::from_iter(std::iter::repeat_n(0.0, MAX_SAMPLES), MAX_SAMPLES),
+            history: History::default(),
             unit_options: super::UNIT_OPTIONS.to_vec(),
             graph_options: super::GRAPH_OPTIONS_RING_LINE_HEAT.to_vec(),
             max_temp: 100.0,
@@ -542,6 +562,13 @@ impl TempGraph {
 
     #[cfg(not(feature = "lyon_charts"))]
     pub fn chart(&'_ self) -> cosmic::widget::Container<'_, crate::app::Message, Theme, Renderer> {
+        let colors = if self.disabled {
+            Cow::Borrowed(&self.disabled_colors)
+        } else {
+            self.svg_colors
+                .with_stops(&self.config.color_stops, Some(self.latest_sample()))
+        };
+        let samples = history::chart_samples(&self.samples, &self.history, self.config.window);
         let svg = match self.config.chart {
             ChartKind::Ring => {
                 let latest = self.latest_sample();
@@ -553,29 +580,15 @@ impl TempGraph {
                 }
                 let percentage: u8 = latest.round().clamp(0.0, 100.0) as u8;
 
-                crate::svg_graph::ring(
-                    &value,
-                    percentage,
-                    None,
-                    if self.disabled {
-                        &self.disabled_colors
-                    } else {
-                        &self.svg_colors
-                    },
-                )
+                crate::svg_graph::ring(&value, percentage, None, &colors)
             }
-            ChartKind::Line => crate::svg_graph::line(
-                &self.samples,
-                self.max_temp,
-                if self.disabled {
-                    &self.disabled_colors
-                } else {
-                    &self.svg_colors
-                },
+            ChartKind::Line => crate::svg_graph::line(&samples, self.max_temp, &colors),
+            ChartKind::Heat => crate::svg_graph::heat(
+                &samples,
+                self.max_temp as u64,
+                &colors,
+                &self.config.color_stops,
             ),
-            ChartKind::Heat => {
-                crate::svg_graph::heat(&self.samples, self.max_temp as u64, &self.svg_colors)
-            }
             ChartKind::StackedBars => {
                 log::error!("StackedBars not supported for GpuTemp");
                 INVALID_IMG.to_string()
@@ -605,6 +618,7 @@ impl TempGraph {
     pub fn update(&mut self, sample: u32) {
         let new_val = f64::from(sample) / 1000.0;
         self.samples.push_back(new_val);
+        self.history.push_now(new_val);
     }
 }
 
@@ -626,6 +640,7 @@ impl DemoGraph for TempGraph {
                 &std::collections::VecDeque::from(HEAT_DEMO_SAMPLES),
                 100,
                 &self.svg_colors,
+                &self.config.color_stops,
             ),
             ChartKind::StackedBars => {
                 log::error!("StackedBars not supported for GpuTemp");
@@ -731,25 +746,254 @@ impl DemoGraph for VramGraph {
     }
 }
 
+/// Chart of one of the optional GpuMetric values
+pub struct MetricGraph {
+    id: String,
+    metric: GpuMetric,
+    samples: BoundedVecDeque<f64>,
+    history: History,
+    graph_options: Vec<&'static str>,
+    // Reported by the GPU, otherwise the charts scale to the largest sample
+    max: Option<f64>,
+    unit: TempUnit,
+    svg_colors: SvgColors,
+    disabled: bool,
+    disabled_colors: SvgColors,
+    config: GpuMetricConfig,
+}
+
+impl MetricGraph {
+    fn new(id: &str, metric: GpuMetric) -> Self {
+        MetricGraph {
+            id: id.to_owned(),
+            metric,
+            samples: BoundedVecDeque::from_iter(std::iter::repeat_n(0.0, MAX_SAMPLES), MAX_SAMPLES),
+            history: History::default(),
+            graph_options: super::GRAPH_OPTIONS_RING_LINE.to_vec(),
+            max: None,
+            unit: TempUnit::Celcius,
+            svg_colors: SvgColors::new(&ChartColors::default()),
+            disabled: false,
+            disabled_colors: SvgColors {
+                background: String::from("#FFFFFF20"),
+                frame: String::from("#727272FF"),
+                text: String::from("#727272FF"),
+                graph1: String::from("#727272FF"),
+                graph2: String::from("#727272FF"),
+                graph3: String::from("#727272FF"),
+            },
+            config: GpuMetricConfig::default(),
+        }
+    }
+
+    fn update_config(&mut self, config: &dyn Any, _refresh_rate: u32) {
+        if let Some(cfg) = config.downcast_ref::<GpuMetricConfig>() {
+            self.config = cfg.clone();
+            self.svg_colors = SvgColors::new(cfg.colors());
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for sample in &mut self.samples {
+            *sample = 0.0;
+        }
+    }
+
+    pub fn config(&self) -> &GpuMetricConfig {
+        &self.config
+    }
+
+    fn max(&self) -> f64 {
+        match self.metric {
+            GpuMetric::Fan
+            | GpuMetric::MemoryBusy
+            | GpuMetric::EncoderUsage
+            | GpuMetric::DecoderUsage => 100.0,
+            // Scaled to the critical temperature, hot spots run past 100C
+            GpuMetric::TempJunction | GpuMetric::TempMemory => {
+                self.max.unwrap_or(DEFAULT_CRITICAL_TEMP)
+            }
+            _ => self
+                .max
+                .unwrap_or_else(|| self.samples.iter().copied().fold(1.0, f64::max)),
+        }
+    }
+
+    #[cfg(not(feature = "lyon_charts"))]
+    pub fn chart(&'_ self) -> cosmic::widget::Container<'_, crate::app::Message, Theme, Renderer> {
+        let colors = if self.disabled {
+            Cow::Borrowed(&self.disabled_colors)
+        } else {
+            self.svg_colors
+                .with_stops(&self.config.color_stops, Some(self.latest_sample()))
+        };
+        let svg = if self.config.chart == ChartKind::Ring {
+            let latest = self.latest_sample();
+            let mut value = self.value_string();
+            // The unit doesn't fit in the ring
+            value.retain(|c| c.is_ascii_digit() || c == '.' || c == '-');
+            let percentage = (latest / self.max() * 100.0).round().clamp(0.0, 100.0) as u8;
+            crate::svg_graph::ring(&value, percentage, None, &colors)
+        } else {
+            crate::svg_graph::line(
+                &history::chart_samples(&self.samples, &self.history, self.config.window),
+                self.max(),
+                &colors,
+            )
+        };
+
+        Container::new(
+            cosmic::widget::icon::from_svg_bytes(svg.into_bytes())
+                .icon()
+                .height(cosmic::iced::Length::Fill)
+                .width(cosmic::iced::Length::Fill),
+        )
+    }
+
+    pub fn latest_sample(&self) -> f64 {
+        *self.samples.back().unwrap_or(&0f64)
+    }
+
+    pub fn graph_kind(&self) -> crate::config::ChartKind {
+        self.config.chart
+    }
+
+    pub fn set_graph_kind(&mut self, kind: crate::config::ChartKind) {
+        self.config.chart = kind;
+    }
+
+    pub fn update(&mut self, sample: f64, max: Option<f64>) {
+        if max.is_some() {
+            self.max = max;
+        }
+        self.samples.push_back(sample);
+        self.history.push_now(sample);
+    }
+
+    fn value_string(&self) -> String {
+        let value = self.latest_sample();
+        if self.disabled {
+            return format!("---{}", self.metric.unit());
+        }
+        match self.metric {
+            GpuMetric::TempJunction | GpuMetric::TempMemory => match self.unit {
+                TempUnit::Celcius => format!("{}C", value.trunc()),
+                TempUnit::Farenheit => format!("{}F", (value * 9.0 / 5.0 + 32.0).trunc()),
+                TempUnit::Kelvin => format!("{}K", (value + 273.15).trunc()),
+                TempUnit::Rankine => format!("{}R", (value * 9.0 / 5.0 + 491.67).trunc()),
+            },
+            GpuMetric::Power if value < 10.0 => format!("{value:.1}W"),
+            GpuMetric::Gtt => format!("{value:.2}GB"),
+            _ => format!("{}{}", value.round(), self.metric.unit()),
+        }
+    }
+}
+
+impl fmt::Display for MetricGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            // The power limit is the chart's maximum, like "85/220W"
+            Some(limit) if self.metric == GpuMetric::Power && !self.disabled => {
+                let value = self.value_string();
+                write!(f, "{}/{}W", value.trim_end_matches('W'), limit.round())
+            }
+            _ => write!(f, "{}", self.value_string()),
+        }
+    }
+}
+
+impl DemoGraph for MetricGraph {
+    fn demo(&self) -> String {
+        match self.config.chart {
+            ChartKind::Ring => crate::svg_graph::ring("40", 40, None, &self.svg_colors),
+            ChartKind::Line => crate::svg_graph::line(
+                &std::collections::VecDeque::from(DEMO_SAMPLES),
+                32.0,
+                &self.svg_colors,
+            ),
+            _ => {
+                log::error!("MetricGraph type not supported {:?}", self.config.chart);
+                INVALID_IMG.to_string()
+            }
+        }
+    }
+
+    fn colors(&self) -> &ChartColors {
+        self.config.colors()
+    }
+
+    fn set_colors(&mut self, colors: &ChartColors) {
+        *self.config.colors_mut() = *colors;
+        self.svg_colors.set_colors(colors);
+    }
+
+    fn color_choices(&self) -> Vec<(&'static str, ColorVariant)> {
+        if self.config.chart == ChartKind::Line {
+            (*super::COLOR_CHOICES_LINE).into()
+        } else {
+            (*super::COLOR_CHOICES_RING).into()
+        }
+    }
+
+    fn id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+
+    fn kind(&self) -> ChartKind {
+        self.config.chart
+    }
+}
+
 pub struct Gpu {
     gpu_if: Box<dyn GpuIf>,
     pub gpu: GpuGraph,
     pub vram: VramGraph,
     pub temp: TempGraph,
+    pub metrics: BTreeMap<GpuMetric, MetricGraph>,
+    throttle_reasons: Vec<ThrottleReason>,
+    processes: Vec<GpuProcess>,
+    slot: Option<String>,
+    // Device nodes of the GPU, to find the processes keeping it awake
+    nodes: Vec<PathBuf>,
+    power_state: Option<PowerState>,
+    holders: Vec<DeviceHolder>,
+    wake_ups: VecDeque<WakeUp>,
     is_laptop: bool,
     config: GpuConfig,
 }
 
+/// The GPU left runtime suspend, with the processes that had it open at the time
+#[derive(Debug, Clone)]
+pub struct WakeUp {
+    pub time: chrono::DateTime<chrono::Local>,
+    pub holders: Vec<DeviceHolder>,
+}
+
 impl Gpu {
     pub fn new(gpu_if: Box<dyn GpuIf>) -> Self {
         let total = gpu_if.vram_total();
         let id = gpu_if.id();
+        let slot = gpu_if.pci_slot();
+        let nodes = slot.as_deref().map(power::device_nodes).unwrap_or_default();
+        let metrics = gpu_if
+            .metrics()
+            .into_iter()
+            .map(|m| (m, MetricGraph::new(&id, m)))
+            .collect();
 
         Gpu {
             gpu_if,
             gpu: GpuGraph::new(&id),
             vram: VramGraph::new(&id, total as f64 / 1_073_741_824.0),
             temp: TempGraph::new(&id),
+            metrics,
+            throttle_reasons: Vec::new(),
+            processes: Vec::new(),
+            power_state: slot.as_deref().and_then(PowerState::read),
+            slot,
+            nodes,
+            holders: Vec::new(),
+            wake_ups: VecDeque::new(),
             is_laptop: false,
             config: GpuConfig::default(),
         }
@@ -761,6 +1005,13 @@ impl Gpu {
             self.gpu.update_config(&cfg.usage, refresh_rate);
             self.vram.update_config(&cfg.vram, refresh_rate);
             self.temp.update_config(&cfg.temp, refresh_rate);
+            for (metric, graph) in &mut self.metrics {
+                graph.update_config(
+                    &cfg.metric(*metric).cloned().unwrap_or_default(),
+                    refresh_rate,
+                );
+                graph.unit = cfg.temp.unit;
+            }
         }
     }
 
@@ -768,6 +1019,10 @@ impl Gpu {
         self.gpu_if.as_ref().name().clone()
     }
 
+    pub fn pci_slot(&self) -> Option<&str> {
+        self.slot.as_deref()
+    }
+
     pub fn id(&self) -> String {
         self.gpu_if.as_ref().id().clone()
     }
@@ -793,6 +1048,13 @@ impl Gpu {
                 dmo.update_config(&self.temp.config, 0);
                 Box::new(dmo)
             }
+            DeviceKind::GpuMetric(metric) => {
+                let mut dmo = MetricGraph::new(&self.id(), metric);
+                if let Some(graph) = self.metrics.get(&metric) {
+                    dmo.update_config(&graph.config, 0);
+                }
+                Box::new(dmo)
+            }
             _ => {
                 log::error!("Gpu::demo_graph({device:?}) Wrong device kind");
                 panic!("Gpu::demo_graph({device:?}) Wrong device kind")
@@ -802,24 +1064,237 @@ impl Gpu {
 
     pub fn update(&mut self) {
         if self.gpu_if.is_active() {
-            if let Ok(sample) = self.gpu_if.usage() {
+            let id = self.id();
+            let gpu_if = &self.gpu_if;
+            if let Ok(sample) = trace::input(&format!("gpu:{id}:usage"), || gpu_if.usage()) {
                 self.gpu.update(sample);
             }
-            if let Ok(sample) = self.gpu_if.vram_used() {
+            if let Ok(sample) = trace::input(&format!("gpu:{id}:vram"), || gpu_if.vram_used()) {
                 self.vram.update(sample);
             }
-            if let Ok(sample) = self.gpu_if.temperature() {
+            if let Ok(sample) = trace::input(&format!("gpu:{id}:temp"), || gpu_if.temperature()) {
                 self.temp.update(sample);
             }
+            for (metric, graph) in &mut self.metrics {
+                if let Ok(sample) =
+                    trace::input(&format!("gpu:{id}:{metric:?}"), || gpu_if.metric(*metric))
+                {
+                    // The limits rarely change, only look them up until found
+                    let max = if graph.max.is_none() && sample > 0.0 {
+                        trace::optional_input(&format!("gpu:{id}:{metric:?}:max"), || {
+                            gpu_if.metric_max(*metric)
+                        })
+                    } else {
+                        None
+                    };
+                    graph.update(sample, max);
+                }
+            }
+            if let Ok(reasons) = self.gpu_if.throttle_reasons() {
+                self.throttle_reasons = reasons;
+            }
         }
     }
 
+    /// Latest values of the items for alerts, without values while the GPU isn't polled
+    pub fn alert_samples(&self, config: &GpuConfig) -> Vec<AlertSample> {
+        let active = self.gpu_if.is_active();
+        let id = self.id();
+        let name = self.name();
+        let sample =
+            |item: &str, title: String, value: f64, unit, config: AlertConfig| AlertSample {
+                key: format!("gpu:{id}:{item}"),
+                name: format!("{name}: {title}"),
+                value: active.then_some(value),
+                unit,
+                config,
+            };
+
+        let mut samples = vec![sample(
+            "usage",
+            fl!("gpu-title-usage"),
+            self.gpu.latest_sample(),
+            "%",
+            config.usage.alerts.clone(),
+        )];
+        if self.vram.total() > 0.0 {
+            samples.push(sample(
+                "vram",
+                fl!("gpu-title-vram"),
+                self.vram.percentage().unwrap_or_default(),
+                "%",
+                config.vram.alerts.clone(),
+            ));
+        }
+        if self.gpu_if.has_temperature() {
+            samples.push(sample(
+                "temp",
+                fl!("gpu-title-temperature"),
+                self.temp.latest_sample(),
+                "°C",
+                config.temp.alerts.clone(),
+            ));
+        }
+        for (metric, graph) in &self.metrics {
+            if let Some(metric_config) = config.metrics.get(metric) {
+                samples.push(sample(
+                    &format!("{metric:?}"),
+                    metric.to_string(),
+                    graph.latest_sample(),
+                    metric.unit(),
+                    metric_config.alerts.clone(),
+                ));
+            }
+        }
+        samples
+    }
+
+    /// The histories to keep across restarts and export, named like the alert keys
+    pub fn histories_mut(&mut self) -> Vec<Series<'_>> {
+        let mut histories = vec![
+            Series::new("usage", "%", &mut self.gpu.history),
+            Series::new("vram", "GB", &mut self.vram.history),
+            Series::new("temp", "°C", &mut self.temp.history),
+        ];
+        for (metric, graph) in &mut self.metrics {
+            histories.push(Series::new(
+                format!("{metric:?}"),
+                metric.unit(),
+                &mut graph.history,
+            ));
+        }
+        histories
+    }
+
+    /// Gauges labeled with the GPU id and name. A stopped GPU has only `gpu_active`,
+    /// its other readings would be stale.
+    pub fn prometheus(&self, metrics: &mut Metrics) {
+        const GIB: f64 = 1_073_741_824.0;
+        let (id, name) = (self.id(), self.name());
+        let labels = [("id", id.as_str()), ("name", name.as_str())];
+
+        let active = self.gpu_if.is_active();
+        metrics.gauge(
+            "gpu_active",
+            "1 while the GPU is sampled, 0 while it is stopped",
+            &labels,
+            if active { 1.0 } else { 0.0 },
+        );
+        if !active {
+            return;
+        }
+        metrics.gauge(
+            "gpu_usage_percent",
+            "GPU load",
+            &labels,
+            self.gpu.latest_sample(),
+        );
+        metrics.gauge(
+            "gpu_memory_used_bytes",
+            "Used VRAM",
+            &labels,
+            self.vram.latest_sample() * GIB,
+        );
+        metrics.gauge(
+            "gpu_memory_total_bytes",
+            "Total VRAM",
+            &labels,
+            self.vram.total() * GIB,
+        );
+        if self.gpu_if.has_temperature() {
+            metrics.gauge(
+                "gpu_temperature_celsius",
+                "GPU temperature",
+                &labels,
+                self.temp.latest_sample(),
+            );
+        }
+
+        for (metric, graph) in &self.metrics {
+            let (gauge, help, scale) = match metric {
+                GpuMetric::Power => ("gpu_power_watts", "Power draw", 1.0),
+                GpuMetric::CoreClock => ("gpu_core_clock_hertz", "Core clock", 1e6),
+                GpuMetric::MemoryClock => ("gpu_memory_clock_hertz", "Memory clock", 1e6),
+                GpuMetric::Fan => ("gpu_fan_percent", "Fan speed", 1.0),
+                GpuMetric::MemoryBusy => ("gpu_memory_busy_percent", "Memory controller load", 1.0),
+                GpuMetric::Gtt => ("gpu_gtt_used_bytes", "Used GTT memory", GIB),
+                GpuMetric::TempJunction => (
+                    "gpu_junction_temperature_celsius",
+                    "Junction temperature",
+                    1.0,
+                ),
+                GpuMetric::TempMemory => {
+                    ("gpu_memory_temperature_celsius", "Memory temperature", 1.0)
+                }
+                GpuMetric::EncoderUsage => ("gpu_encoder_usage_percent", "Encoder load", 1.0),
+                GpuMetric::DecoderUsage => ("gpu_decoder_usage_percent", "Decoder load", 1.0),
+                GpuMetric::PcieRx => (
+                    "gpu_pcie_receive_bytes_per_second",
+                    "PCIe receive rate",
+                    1e6,
+                ),
+                GpuMetric::PcieTx => (
+                    "gpu_pcie_transmit_bytes_per_second",
+                    "PCIe transmit rate",
+                    1e6,
+                ),
+            };
+            metrics.gauge(gauge, help, &labels, graph.latest_sample() * scale);
+        }
+    }
+
+    /// Scanning all processes is expensive, so this is only done while the popup is open
+    pub fn update_processes(&mut self) {
+        if self.gpu_if.is_active() {
+            self.processes = self.gpu_if.processes();
+            self.processes.truncate(TOP_PROCESSES);
+        }
+    }
+
+    /// Read the runtime power state, which doesn't wake the GPU, and note wake-ups.
+    /// The device holders are only scanned on a wake-up or while the popup is open.
+    pub fn update_power_state(&mut self, popup_open: bool) {
+        let Some(slot) = &self.slot else {
+            return;
+        };
+
+        let state = PowerState::read(slot);
+        let woke = matches!(
+            self.power_state,
+            Some(PowerState::Suspended | PowerState::Suspending)
+        ) && state.is_some_and(PowerState::is_awake);
+
+        if woke || popup_open {
+            self.holders = power::holders(&self.nodes);
+        }
+
+        if woke {
+            info!("{} woke up, held by {:?}", self.name(), self.holders);
+            if self.wake_ups.len() == MAX_WAKE_UPS {
+                self.wake_ups.pop_front();
+            }
+            self.wake_ups.push_back(WakeUp {
+                time: chrono::Local::now(),
+                holders: self.holders.clone(),
+            });
+        }
+
+        self.power_state = state;
+    }
+
+    pub fn power_state(&self) -> Option<PowerState> {
+        self.power_state
+    }
+
     pub fn restart(&mut self) {
         info!("Restarting {}", self.name());
         self.gpu_if.restart();
         self.gpu.disabled = false;
         self.vram.disabled = false;
         self.temp.disabled = false;
+        for graph in self.metrics.values_mut() {
+            graph.disabled = false;
+        }
     }
 
     pub fn stop(&mut self) {
@@ -831,12 +1306,39 @@ impl Gpu {
         self.gpu.disabled = true;
         self.vram.disabled = true;
         self.temp.disabled = true;
+        for graph in self.metrics.values_mut() {
+            graph.clear();
+            graph.disabled = true;
+        }
+        self.throttle_reasons.clear();
+        self.processes.clear();
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        !self.throttle_reasons.is_empty()
+    }
+
+    /// Active throttle reasons, like "Power limit, Thermal limit"
+    pub fn throttle_label(&self) -> String {
+        if self.throttle_reasons.is_empty() {
+            fl!("gpu-throttle-none")
+        } else {
+            self.throttle_reasons
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
     }
 
     pub fn is_active(&self) -> bool {
         self.gpu_if.is_active()
     }
 
+    pub fn is_accelerator(&self) -> bool {
+        self.gpu_if.is_accelerator()
+    }
+
     fn settings_usage_ui(
         &'_ self,
         config: &crate::config::GpuUsageConfig,
@@ -889,6 +1391,17 @@ impl Gpu {
                     )
                 )
                 .align_y(Center),
+                alerts::settings_ui(DeviceKind::Gpu, Some(self.id()), &config.alerts, "%"),
+                color_stops::settings_ui(
+                    DeviceKind::Gpu,
+                    Some(self.id()),
+                    &config.color_stops,
+                    "%"
+                ),
+            )
+            .push_maybe(
+                (config.chart == ChartKind::Line)
+                    .then(|| history::window_ui(DeviceKind::Gpu, Some(self.id()), config.window)),
             )
             .spacing(cosmic.space_xs()),
         ));
@@ -955,6 +1468,17 @@ impl Gpu {
                     )
                 )
                 .align_y(Center),
+                alerts::settings_ui(DeviceKind::Vram, Some(self.id()), &config.alerts, "%"),
+                color_stops::settings_ui(
+                    DeviceKind::Vram,
+                    Some(self.id()),
+                    &config.color_stops,
+                    "%"
+                ),
+            )
+            .push_maybe(
+                (config.chart == ChartKind::Line)
+                    .then(|| history::window_ui(DeviceKind::Vram, Some(self.id()), config.window)),
             )
             .spacing(cosmic.space_xs()),
         ));
@@ -1029,6 +1553,18 @@ impl Gpu {
                     )
                 )
                 .align_y(Center),
+                alerts::settings_ui(DeviceKind::GpuTemp, Some(self.id()), &config.alerts, "°C"),
+                color_stops::settings_ui(
+                    DeviceKind::GpuTemp,
+                    Some(self.id()),
+                    &config.color_stops,
+                    "°C"
+                ),
+            )
+            .push_maybe(
+                (config.chart != ChartKind::Ring).then(|| {
+                    history::window_ui(DeviceKind::GpuTemp, Some(self.id()), config.window)
+                }),
             )
             .spacing(cosmic.space_xs()),
         ));
@@ -1043,6 +1579,145 @@ impl Gpu {
         .into()
     }
 
+    fn settings_processes_ui(&'_ self) -> Option<Element<'_, crate::app::Message>> {
+        if self.processes.is_empty() {
+            return None;
+        }
+
+        let mut rows = Column::new().spacing(cosmic::theme::spacing().space_xxs);
+        for process in &self.processes {
+            rows = rows.push(settings::item(
+                format!("{} ({})", process.name, process.pid),
+                widget::text::body(format!(
+                    "{:.0}%  {:.0} MB",
+                    process.usage,
+                    process.memory as f64 / 1_048_576.0
+                )),
+            ));
+        }
+
+        Some(
+            column![widget::text::heading(fl!("gpu-title-processes")), rows]
+                .spacing(cosmic::theme::spacing().space_xs)
+                .into(),
+        )
+    }
+
+    fn settings_power_ui(
+        &'_ self,
+        config: &crate::config::GpuConfig,
+    ) -> Option<Element<'_, crate::app::Message>> {
+        let state = self.power_state?;
+        let spacing = cosmic::theme::spacing();
+
+        let mut wake_ups = Column::new().spacing(spacing.space_xxs);
+        for wake_up in self.wake_ups.iter().rev() {
+            wake_ups = wake_ups.push(settings::item(
+                wake_up.time.format("%H:%M:%S").to_string(),
+                widget::text::body(holder_names(&wake_up.holders)),
+            ));
+        }
+
+        Some(
+            column!(
+                widget::text::heading(fl!("gpu-title-power")),
+                settings::item(
+                    fl!("gpu-power-state"),
+                    widget::text::body(state.to_string())
+                ),
+                settings::item(
+                    fl!("gpu-power-indicator"),
+                    toggler(config.power_indicator).on_toggle(move |value| {
+                        Message::GpuTogglePowerIndicator(self.id(), value)
+                    }),
+                ),
+                settings::item(
+                    fl!("gpu-power-holders"),
+                    widget::text::body(holder_names(&self.holders))
+                ),
+            )
+            .push_maybe((!self.wake_ups.is_empty()).then(|| {
+                column!(widget::text::body(fl!("gpu-power-wake-ups")), wake_ups)
+                    .spacing(spacing.space_xxs)
+            }))
+            .spacing(spacing.space_xs)
+            .into(),
+        )
+    }
+
+    fn settings_metric_ui(&'_ self, graph: &'_ MetricGraph) -> Element<'_, crate::app::Message> {
+        let theme = cosmic::theme::active();
+        let cosmic = theme.cosmic();
+        let metric = graph.metric;
+        let device = DeviceKind::GpuMetric(metric);
+
+        let mut elements = Vec::new();
+        elements.push(Element::from(
+            column!(
+                Container::new(graph.chart().width(60).height(60))
+                    .width(90)
+                    .align_x(Alignment::Center),
+                cosmic::widget::text::body(graph.to_string())
+                    .width(90)
+                    .align_x(Alignment::Center)
+            )
+            .padding(cosmic::theme::spacing().space_xs)
+            .align_x(Alignment::Center),
+        ));
+
+        let selected: Option<usize> = Some(graph.graph_kind().into());
+        let kind = graph.graph_kind();
+        let id = self.id();
+        elements.push(Element::from(
+            column!(
+                settings::item(
+                    fl!("enable-chart"),
+                    toggler(graph.config.chart_visible()).on_toggle(move |value| {
+                        Message::GpuToggleChart(self.id(), device, value)
+                    }),
+                ),
+                settings::item(
+                    fl!("enable-label"),
+                    toggler(graph.config.label_visible()).on_toggle(move |value| {
+                        Message::GpuToggleLabel(self.id(), device, value)
+                    }),
+                ),
+                row!(
+                    widget::text::body(fl!("chart-type")),
+                    widget::dropdown(&graph.graph_options, selected, move |m| {
+                        Message::GpuSelectGraphType(id.clone(), device, m.into())
+                    },)
+                    .width(70),
+                    widget::horizontal_space(),
+                    widget::button::standard(fl!("change-colors"))
+                        .on_press(Message::ColorPickerOpen(device, kind, Some(self.id()))),
+                )
+                .align_y(Center),
+                alerts::settings_ui(device, Some(self.id()), &graph.config.alerts, metric.unit()),
+                color_stops::settings_ui(
+                    device,
+                    Some(self.id()),
+                    &graph.config.color_stops,
+                    metric.unit()
+                ),
+            )
+            .push_maybe(
+                (graph.config.chart == ChartKind::Line)
+                    .then(|| history::window_ui(device, Some(self.id()), graph.config.window)),
+            )
+            .spacing(cosmic.space_xs()),
+        ));
+
+        column![
+            widget::text::heading(metric.to_string()),
+            Row::with_children(elements)
+                .align_y(Alignment::Center)
+                .spacing(cosmic.space_xs())
+        ]
+        .spacing(cosmic::theme::spacing().space_xs)
+        .into()
+    }
+
     pub fn settings_ui(
         &'_ self,
         config: &crate::config::GpuConfig,
@@ -1065,7 +1740,12 @@ impl Gpu {
         };
 
         let usage = self.settings_usage_ui(&config.usage);
-        let vram = self.settings_vram_ui(&config.vram);
+        // Integrated GPUs without dedicated memory have nothing to show
+        let vram = if self.gpu_if.vram_total() > 0 {
+            Some(self.settings_vram_ui(&config.vram))
+        } else {
+            None
+        };
 
         let stacked = if config.vram.label_visible() && config.usage.label_visible() {
             Some(settings::item(
@@ -1080,28 +1760,147 @@ impl Gpu {
             None
         };
 
-        let temp = self.settings_temp_ui(&config.temp);
+        let temp = if self.gpu_if.has_temperature() {
+            Some(self.settings_temp_ui(&config.temp))
+        } else {
+            None
+        };
 
-        Column::new()
+        let throttle = if self.gpu_if.has_throttle_reasons() {
+            Some(
+                column!(
+                    settings::item(
+                        fl!("gpu-throttle"),
+                        widget::text::body(self.throttle_label())
+                    ),
+                    settings::item(
+                        fl!("gpu-throttle-warning"),
+                        toggler(config.throttle_warning).on_toggle(move |value| {
+                            Message::GpuToggleThrottleWarning(self.id(), value)
+                        }),
+                    ),
+                )
+                .spacing(cosmic::theme::spacing().space_xs),
+            )
+        } else {
+            None
+        };
+
+        let mut column = Column::new()
             .push_maybe(battery_disable)
             .push(usage)
-            .push(temp)
-            .push(vram)
-            .push_maybe(stacked)
-            .spacing(cosmic::theme::spacing().space_xs)
-            .into()
+            .push_maybe(temp)
+            .push_maybe(vram)
+            .push_maybe(stacked);
+        for graph in self.metrics.values() {
+            column = column.push(self.settings_metric_ui(graph));
+        }
+        column = column
+            .push_maybe(throttle)
+            .push_maybe(self.settings_power_ui(config))
+            .push_maybe(self.settings_processes_ui());
+        column.spacing(cosmic::theme::spacing().space_xs).into()
+    }
+}
+
+// "firefox (1234), Xwayland (987)"
+fn holder_names(holders: &[DeviceHolder]) -> String {
+    if holders.is_empty() {
+        return fl!("gpu-power-no-holders");
     }
+    holders
+        .iter()
+        .map(|h| format!("{} ({})", h.name, h.pid))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 pub fn list_gpus() -> Vec<Gpu> {
+    if let Some(gpus) = trace::replayed_gpus() {
+        return gpus
+            .into_iter()
+            .map(|info| Gpu::new(Box::new(ReplayGpu::new(info))))
+            .collect();
+    }
+
+    detect_gpus(&HashSet::new())
+}
+
+/// GPUs on PCI slots not in `known`, so re-detection doesn't probe and wake the GPUs already polled
+pub fn list_new_gpus(known: &HashSet<String>) -> Vec<Gpu> {
+    // A replay has a fixed set of GPUs
+    if trace::is_replaying() {
+        return Vec::new();
+    }
+    detect_gpus(known)
+}
+
+fn detect_gpus(known: &HashSet<String>) -> Vec<Gpu> {
     let mut v: Vec<Gpu> = Vec::new();
 
-    v.extend(IntelGpu::get_gpus());
-    v.extend(NvidiaGpu::get_gpus());
-    v.extend(AmdGpu::get_gpus());
+    v.extend(IntelGpu::get_gpus(known));
+    v.extend(NvidiaGpu::get_gpus(known));
+    v.extend(NouveauGpu::get_gpus(known));
+    v.extend(AmdGpu::get_gpus(known));
+    v.extend(AccelGpu::get_gpus(known));
+
+    if trace::is_recording() {
+        for gpu in &v {
+            trace::declare_gpu(&GpuInfo {
+                id: gpu.id(),
+                name: gpu.name(),
+                vram_total: gpu.gpu_if.vram_total(),
+                has_temperature: gpu.gpu_if.has_temperature(),
+                accelerator: gpu.is_accelerator(),
+                metrics: gpu.gpu_if.metrics(),
+            });
+        }
+    }
     v
 }
 
+/// Make sure every detected GPU has a config and apply it
+pub fn sync_gpu_configs(
+    gpus: &mut BTreeMap<String, Gpu>,
+    configs: &mut HashMap<String, GpuConfig>,
+    refresh_rate: u32,
+) {
+    for (id, gpu) in gpus {
+        let config = configs.entry(id.clone()).or_default();
+        gpu.update_config(config, refresh_rate);
+    }
+}
+
+/// The DRM cards, accelerators and NVIDIA driver devices present, cheap enough to poll for hotplug
+pub fn gpu_devices() -> Vec<String> {
+    let mut devices: Vec<String> = [
+        "/sys/class/drm",
+        "/sys/class/accel",
+        "/proc/driver/nvidia/gpus",
+    ]
+    .iter()
+    .filter_map(|dir| std::fs::read_dir(dir).ok())
+    .flat_map(|entries| entries.flatten())
+    .map(|e| e.file_name().to_string_lossy().to_string())
+    // Ignore connectors like card0-DP-1, they change with monitors
+    .filter(|name| !name.contains('-'))
+    .collect();
+    devices.sort();
+    devices
+}
+
+/// PCI slots of the DRM cards, accelerators and NVIDIA driver devices present
+pub fn gpu_slots() -> HashSet<String> {
+    let mut slots: HashSet<String> = ["/sys/class/drm", "/sys/class/accel"]
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|e| super::gpu::device_pci_slot(&e.path().join("device")))
+        .collect();
+    slots.extend(NvidiaGpu::driver_slots());
+    slots
+}
+
 const DEMO_SAMPLES: [f64; 21] = [
     0.0,
     12.689857482910156,
@@ -1130,3 +1929,233 @@ const HEAT_DEMO_SAMPLES: [f64; 21] = [
     41.0, 42.0, 43.5, 45.0, 48.0, 51.0, 55.0, 57.0, 59.5, 62.0, 64.0, 67.0, 70.0, 74.0, 78.0, 83.0,
     87.0, 90.0, 95.0, 98.0, 100.0,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sensors::gpu::mock::{MockGpu, MockScript, Reading};
+    use crate::sensors::gpu_combined::GpuCombined;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const GB: u64 = 1_073_741_824;
+
+    fn mock_gpu(id: &str, vram_total: u64) -> (Gpu, Rc<RefCell<MockScript>>) {
+        let (mock, script) = MockGpu::new(id, vram_total);
+        (Gpu::new(Box::new(mock)), script)
+    }
+
+    #[test]
+    fn update_pushes_samples() {
+        let (mut gpu, script) = mock_gpu("a", 8 * GB);
+        {
+            let mut s = script.borrow_mut();
+            s.usage.extend([Reading::Value(25), Reading::Value(75)]);
+            s.temperature.push_back(Reading::Value(61_500));
+            s.vram_used.push_back(Reading::Value(2 * GB));
+        }
+
+        gpu.update();
+        assert_eq!(gpu.gpu.latest_sample(), 25.0);
+        assert_eq!(gpu.temp.latest_sample(), 61.5);
+        assert_eq!(gpu.vram.latest_sample(), 2.0);
+        assert_eq!(gpu.vram.total(), 8.0);
+
+        gpu.update();
+        assert_eq!(gpu.gpu.latest_sample(), 75.0);
+        assert_eq!(gpu.gpu.samples.len(), MAX_SAMPLES);
+    }
+
+    #[test]
+    fn accelerators_stay_out_of_combined() {
+        let (mut gpu, script) = mock_gpu("gpu", 8 * GB);
+        {
+            let mut s = script.borrow_mut();
+            s.usage.push_back(Reading::Value(60));
+            s.temperature.push_back(Reading::Value(55_000));
+            s.vram_used.push_back(Reading::Value(2 * GB));
+        }
+        // An idle NPU with all of system RAM as its memory
+        let (mut npu, script) = mock_gpu("npu", 32 * GB);
+        {
+            let mut s = script.borrow_mut();
+            s.accelerator = true;
+            s.usage.push_back(Reading::Value(0));
+            s.vram_used.push_back(Reading::Value(GB));
+        }
+        gpu.update();
+        npu.update();
+
+        let gpus = BTreeMap::from([(gpu.id(), gpu), (npu.id(), npu)]);
+        let mut combined = GpuCombined::default();
+        combined.update(&gpus);
+        assert_eq!(combined.latest_sample(), 60.0);
+        assert_eq!(combined.vram_label(true), "2.0/8GB");
+
+        let mut metrics = Metrics::default();
+        gpus["npu"].prometheus(&mut metrics);
+        assert!(
+            metrics
+                .samples()
+                .all(|s| s.name != "gpu_temperature_celsius")
+        );
+        assert!(metrics.samples().any(|s| s.name == "gpu_usage_percent"));
+    }
+
+    #[test]
+    fn errors_keep_previous_samples() {
+        let (mut gpu, script) = mock_gpu("a", 8 * GB);
+        {
+            let mut s = script.borrow_mut();
+            s.usage.extend([Reading::Value(40), Reading::Error]);
+            s.temperature
+                .extend([Reading::Value(50_000), Reading::Error]);
+        }
+
+        gpu.update();
+        gpu.update();
+        assert_eq!(gpu.gpu.latest_sample(), 40.0);
+        assert_eq!(gpu.temp.latest_sample(), 50.0);
+        // VRAM was never scripted
+        assert_eq!(gpu.vram.latest_sample(), 0.0);
+    }
+
+    #[test]
+    fn suspended_gpu_reports_zero() {
+        let (mut gpu, script) = mock_gpu("a", 8 * GB);
+        {
+            let mut s = script.borrow_mut();
+            s.usage.extend([Reading::Value(90), Reading::Suspended]);
+            s.temperature
+                .extend([Reading::Value(70_000), Reading::Suspended]);
+        }
+
+        gpu.update();
+        gpu.update();
+        assert_eq!(gpu.gpu.latest_sample(), 0.0);
+        assert_eq!(gpu.temp.latest_sample(), 0.0);
+        assert_eq!(gpu.temp.to_string(), "--C");
+    }
+
+    #[test]
+    fn stop_and_restart_pause_polling() {
+        let (mut gpu, script) = mock_gpu("a", 8 * GB);
+        script.borrow_mut().usage.push_back(Reading::Value(50));
+
+        gpu.update();
+        assert_eq!(gpu.gpu.latest_sample(), 50.0);
+
+        gpu.stop();
+        assert!(!gpu.is_active());
+        assert!(gpu.gpu.samples.iter().all(|s| *s == 0.0));
+        assert_eq!(gpu.gpu.to_string(), "---%");
+        assert_eq!(gpu.vram.string(false), "--- GB");
+
+        let reads = script.borrow().reads;
+        gpu.update();
+        assert_eq!(script.borrow().reads, reads);
+
+        gpu.restart();
+        assert!(gpu.is_active());
+        gpu.update();
+        assert_eq!(gpu.gpu.latest_sample(), 50.0);
+        assert_eq!(gpu.gpu.to_string(), "50.0%");
+    }
+
+    #[test]
+    fn vram_graph_formats_gigabytes() {
+        let mut vram = VramGraph::new("a", 16.0);
+        vram.update(GB / 2);
+        assert_eq!(vram.string(false), "0.50 GB");
+        assert_eq!(vram.string(true), "0.50GB");
+
+        vram.update(12 * GB + GB / 4);
+        assert_eq!(vram.string(false), "12.2 GB");
+
+        vram.update(128 * GB);
+        assert_eq!(vram.string(false), "128 GB");
+
+        vram.clear();
+        assert_eq!(vram.latest_sample(), 0.0);
+        assert_eq!(vram.samples.len(), MAX_SAMPLES);
+    }
+
+    #[test]
+    fn temp_graph_converts_units() {
+        let mut temp = TempGraph::new("a");
+        temp.update(45_600);
+        assert_eq!(temp.to_string(), "45C");
+
+        temp.config.unit = TempUnit::Farenheit;
+        assert_eq!(temp.to_string(), "114F");
+
+        temp.config.unit = TempUnit::Kelvin;
+        assert_eq!(temp.to_string(), "318K");
+
+        temp.disabled = true;
+        assert_eq!(temp.to_string(), "---K");
+    }
+
+    #[test]
+    fn metrics_and_throttling() {
+        let (mock, script) = MockGpu::new("a", 0);
+        {
+            let mut s = script.borrow_mut();
+            s.metrics.insert(
+                GpuMetric::Power,
+                [Reading::Value(5.5), Reading::Value(120.0)].into(),
+            );
+            s.metric_max.insert(GpuMetric::Power, 200.0);
+            s.throttle_reasons = Some(vec![ThrottleReason::PowerCap]);
+        }
+        let mut gpu = Gpu::new(Box::new(mock));
+        assert_eq!(gpu.metrics.keys().collect::<Vec<_>>(), [&GpuMetric::Power]);
+
+        gpu.update();
+        let power = &gpu.metrics[&GpuMetric::Power];
+        assert_eq!(power.to_string(), "5.5/200W");
+        assert_eq!(power.max(), 200.0);
+        assert!(gpu.is_throttled());
+
+        gpu.update();
+        assert_eq!(gpu.metrics[&GpuMetric::Power].to_string(), "120/200W");
+
+        gpu.stop();
+        assert!(!gpu.is_throttled());
+        assert_eq!(gpu.metrics[&GpuMetric::Power].to_string(), "---W");
+    }
+
+    #[test]
+    fn sync_adds_missing_and_keeps_absent_configs() {
+        let (gpu, _script) = mock_gpu("present", GB);
+        let mut gpus = BTreeMap::from([("present".to_string(), gpu)]);
+
+        let absent = GpuConfig {
+            stack_labels: false,
+            ..Default::default()
+        };
+        let mut configs = HashMap::from([("absent".to_string(), absent.clone())]);
+
+        sync_gpu_configs(&mut gpus, &mut configs, 1000);
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs["present"], GpuConfig::default());
+        assert_eq!(configs["absent"], absent);
+    }
+
+    #[test]
+    fn sync_applies_config() {
+        let (gpu, _script) = mock_gpu("a", GB);
+        let mut gpus = BTreeMap::from([("a".to_string(), gpu)]);
+
+        let mut configs = HashMap::from([("a".to_string(), GpuConfig::default())]);
+        let config = configs.get_mut("a").unwrap();
+        config.temp.unit = TempUnit::Kelvin;
+        config.usage.chart = ChartKind::Line;
+
+        sync_gpu_configs(&mut gpus, &mut configs, 1000);
+        let gpu = &gpus["a"];
+        assert_eq!(gpu.temp.config.unit, TempUnit::Kelvin);
+        assert_eq!(gpu.gpu.graph_kind(), ChartKind::Line);
+        assert_eq!(gpu.temp.to_string(), "---K");
+    }
+}
@@ -2,11 +2,15 @@ use cosmic::{Element, iced::Alignment::Center, widget::Container};
 use sysinfo::{MemoryRefreshKind, System};
 
 use crate::{
+    alerts, color_stops,
     colorpicker::DemoGraph,
     config::{ChartColors, ChartKind, ColorVariant, DeviceKind, MemoryConfig},
     fl,
+    history::{self, History, Series},
+    prometheus::Metrics,
     sensors::INVALID_IMG,
     svg_graph::SvgColors,
+    trace,
 };
 
 use cosmic::widget;
@@ -25,6 +29,7 @@ use crate::app::Message;
 
 use bounded_vec_deque::BoundedVecDeque;
 use std::fmt::Write;
+use std::io;
 
 use super::Sensor;
 
@@ -55,6 +60,8 @@ pub static COLOR_CHOICES_LINE_STACKED: std::sync::LazyLock<[(&'static str, Color
 pub struct Memory {
     samples_used: BoundedVecDeque<f64>,
     samples_allocated: BoundedVecDeque<f64>,
+    history_used: History,
+    history_allocated: History,
     total_memory: f64,
     system: System,
     graph_options: Vec<&'static str>,
@@ -159,14 +166,33 @@ impl Sensor for Memory {
     }
 
     fn update(&mut self) {
-        let r = MemoryRefreshKind::nothing().with_ram();
+        // "<total> <used> <free>" in bytes
+        let Ok(reading) = trace::input("memory", || -> io::Result<String> {
+            let r = MemoryRefreshKind::nothing().with_ram();
+            self.system.refresh_memory_specifics(r);
+            Ok(format!(
+                "{} {} {}",
+                self.system.total_memory(),
+                self.system.used_memory(),
+                self.system.free_memory()
+            ))
+        }) else {
+            return;
+        };
+        let bytes: Vec<f64> = reading.split(' ').filter_map(|b| b.parse().ok()).collect();
+        let [total, used, free] = bytes[..] else {
+            log::warn!("Invalid memory reading {reading:?}");
+            return;
+        };
 
-        self.system.refresh_memory_specifics(r);
-        let new_val_used: f64 = self.system.used_memory() as f64 / 1_073_741_824.0;
-        let new_val_allocated: f64 =
-            self.total_memory - (self.system.free_memory() as f64 / 1_073_741_824.0);
+        // A replayed trace can come from a machine with more or less memory
+        self.total_memory = total / 1_073_741_824.0;
+        let new_val_used: f64 = used / 1_073_741_824.0;
+        let new_val_allocated: f64 = self.total_memory - (free / 1_073_741_824.0);
         self.samples_used.push_back(new_val_used);
         self.samples_allocated.push_back(new_val_allocated);
+        self.history_used.push_now(new_val_used);
+        self.history_allocated.push_now(new_val_allocated);
     }
 
     fn demo_graph(&self) -> Box<dyn DemoGraph> {
@@ -223,6 +249,9 @@ impl Sensor for Memory {
         _height_hint: u16,
         _width_hint: u16,
     ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
+        let colors = self
+            .svg_colors
+            .with_stops(&self.config.color_stops, self.alert_value());
         let svg = if self.config.chart == ChartKind::Ring {
             let mut latest = self.latest_sample();
             let mut value = String::with_capacity(10);
@@ -251,24 +280,27 @@ impl Sensor for Memory {
                 if pct_allocated > 100 {
                     pct_allocated = 100;
                 }
-                crate::svg_graph::ring(
-                    &value,
-                    pct as u8,
-                    Some(pct_allocated as u8),
-                    &self.svg_colors,
-                )
+                crate::svg_graph::ring(&value, pct as u8, Some(pct_allocated as u8), &colors)
             } else {
-                crate::svg_graph::ring(&value, pct as u8, None, &self.svg_colors)
+                crate::svg_graph::ring(&value, pct as u8, None, &colors)
             }
         } else if self.config.show_allocated {
             crate::svg_graph::line_stacked(
-                &self.samples_used,
-                &self.samples_allocated,
+                &history::chart_samples(&self.samples_used, &self.history_used, self.config.window),
+                &history::chart_samples(
+                    &self.samples_allocated,
+                    &self.history_allocated,
+                    self.config.window,
+                ),
                 self.total_memory,
-                &self.svg_colors,
+                &colors,
             )
         } else {
-            crate::svg_graph::line(&self.samples_used, self.total_memory, &self.svg_colors)
+            crate::svg_graph::line(
+                &history::chart_samples(&self.samples_used, &self.history_used, self.config.window),
+                self.total_memory,
+                &colors,
+            )
         };
 
         let icon = cosmic::widget::icon::from_svg_bytes(svg.into_bytes());
@@ -349,7 +381,18 @@ impl Sensor for Memory {
                     widget::button::standard(fl!("change-colors"))
                         .on_press(Message::ColorPickerOpen(DeviceKind::Memory, mem_kind, None)),
                 )
-                .align_y(Center)
+                .align_y(Center),
+                alerts::settings_ui(DeviceKind::Memory, None, &config.alerts, self.alert_unit()),
+                color_stops::settings_ui(
+                    DeviceKind::Memory,
+                    None,
+                    &config.color_stops,
+                    self.alert_unit()
+                ),
+            )
+            .push_maybe(
+                (config.chart == ChartKind::Line)
+                    .then(|| history::window_ui(DeviceKind::Memory, None, config.window)),
             )
             .spacing(cosmic.space_xs()),
         ));
@@ -359,6 +402,22 @@ impl Sensor for Memory {
             .spacing(0)
             .into()
     }
+
+    // Used memory as a percentage of the total, whatever the label shows
+    fn alert_value(&self) -> Option<f64> {
+        (self.total_memory > 0.0).then(|| self.latest_sample() * 100.0 / self.total_memory)
+    }
+
+    fn alert_unit(&self) -> &'static str {
+        "%"
+    }
+
+    fn histories_mut(&mut self) -> Vec<Series<'_>> {
+        vec![
+            Series::new("used", "GB", &mut self.history_used),
+            Series::new("allocated", "GB", &mut self.history_allocated),
+        ]
+    }
 }
 
 impl Default for Memory {
@@ -389,6 +448,8 @@ impl Default for Memory {
                 std::iter::repeat_n(0.0, MAX_SAMPLES),
                 MAX_SAMPLES,
             ),
+            history_used: History::default(),
+            history_allocated: History::default(),
             total_memory,
             system,
             config: MemoryConfig::default(),
@@ -413,6 +474,28 @@ impl Memory {
         self.total_memory
     }
 
+    pub fn prometheus(&self, metrics: &mut Metrics) {
+        const GIB: f64 = 1_073_741_824.0;
+        metrics.gauge(
+            "memory_used_bytes",
+            "Used memory",
+            &[],
+            self.latest_sample() * GIB,
+        );
+        metrics.gauge(
+            "memory_allocated_bytes",
+            "Allocated memory, including caches",
+            &[],
+            self.latest_sample_allocated() * GIB,
+        );
+        metrics.gauge(
+            "memory_total_bytes",
+            "Total memory",
+            &[],
+            self.total_memory * GIB,
+        );
+    }
+
     pub fn to_string(&self, vertical_panel: bool) -> String {
         let mut current_val = self.latest_sample();
         let unit: &str;
@@ -5,6 +5,7 @@ use std::sync::LazyLock;
 use crate::{
     config::{ColorVariant, GpuConfig},
     fl,
+    history::Series,
 };
 
 const INVALID_IMG: &str = r#"
@@ -98,15 +99,32 @@ pub trait Sensor {
         width_hint: u16,
     ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer>;
     fn settings_ui(&'_ self) -> Element<'_, crate::app::Message>;
+    /// The value alert thresholds are checked against, None while there is no sample
+    fn alert_value(&self) -> Option<f64> {
+        None
+    }
+    /// Unit of alert_value() in settings and notifications
+    fn alert_unit(&self) -> &'static str {
+        ""
+    }
+    /// The histories to keep across restarts and export, by a name unique within the sensor
+    fn histories_mut(&mut self) -> Vec<Series<'_>> {
+        Vec::new()
+    }
 }
 
 pub mod cpu;
 pub mod cputemp;
 pub mod disks;
+pub mod disktemp;
+pub mod filesystems;
 pub mod gpu;
+pub mod gpu_combined;
 pub mod gpus;
 pub mod memory;
 pub mod network;
+pub mod storage_health;
+pub mod thermal;
 
 impl From<usize> for TempUnit {
     fn from(index: usize) -> Self {
@@ -1,14 +1,19 @@
 use bounded_vec_deque::BoundedVecDeque;
 
 use cosmic::{Element, iced_widget::Column, widget::Container};
-use log::info;
+use log::{info, warn};
+use std::io;
 use sysinfo::Networks;
 
 use crate::{
+    alerts, color_stops,
     colorpicker::DemoGraph,
     config::{ChartColors, ChartKind, ColorVariant, DeviceKind, NetworkConfig, NetworkVariant},
     fl,
+    history::{self, History, Series},
+    prometheus::Metrics,
     svg_graph::SvgColors,
+    trace,
 };
 
 use cosmic::widget;
@@ -72,6 +77,9 @@ pub struct Network {
     networks: Networks,
     download: BoundedVecDeque<u64>,
     upload: BoundedVecDeque<u64>,
+    // Bits per second, for the longer chart windows
+    history_download: History,
+    history_upload: History,
     max_y: Option<u64>,
     svg_colors: SvgColors,
     dropdown_options: Vec<&'static str>,
@@ -164,16 +172,33 @@ impl Sensor for Network {
 
     /// Retrieve the amount of data transmitted since last update.
     fn update(&mut self) {
-        self.networks.refresh(true);
-        let mut dl = 0;
-        let mut ul = 0;
+        // "<received> <transmitted>", bytes since the previous update
+        let Ok(reading) = trace::input("network", || -> io::Result<String> {
+            self.networks.refresh(true);
+            let (mut received, mut transmitted) = (0, 0);
+            for (_, network) in &self.networks {
+                received += network.received();
+                transmitted += network.transmitted();
+            }
+            Ok(format!("{received} {transmitted}"))
+        }) else {
+            return;
+        };
+        let Some((received, transmitted)) = reading
+            .split_once(' ')
+            .and_then(|(rx, tx)| Some((rx.parse::<u64>().ok()?, tx.parse::<u64>().ok()?)))
+        else {
+            warn!("Invalid network reading {reading:?}");
+            return;
+        };
+        let dl = received * 8;
+        let ul = transmitted * 8;
 
-        for (_, network) in &self.networks {
-            dl += network.received() * 8;
-            ul += network.transmitted() * 8;
-        }
         self.download.push_back(dl);
         self.upload.push_back(ul);
+        let per_second = 1000.0 / f64::from(self.refresh_rate.max(1));
+        self.history_download.push_now(dl as f64 * per_second);
+        self.history_upload.push_now(ul as f64 * per_second);
     }
 
     fn demo_graph(&self) -> Box<dyn DemoGraph> {
@@ -233,16 +258,32 @@ impl Sensor for Network {
         _height_hint: u16,
         _width_hint: u16,
     ) -> cosmic::widget::Container<'_, crate::app::Message, cosmic::Theme, cosmic::Renderer> {
+        let window = self.config.window;
+        let scale = f64::from(self.refresh_rate) / 1000.0;
+        let download = history::chart_samples_u64(
+            &self.download,
+            &self.history_download,
+            window,
+            GRAPH_SAMPLES,
+            scale,
+        );
+        let upload = history::chart_samples_u64(
+            &self.upload,
+            &self.history_upload,
+            window,
+            GRAPH_SAMPLES,
+            scale,
+        );
         let svg = match self.config.variant {
             NetworkVariant::Combined => crate::svg_graph::double_line(
-                &self.download,
-                &self.upload,
+                &download,
+                &upload,
                 GRAPH_SAMPLES,
                 &self.svg_colors,
                 self.max_y,
             ),
             NetworkVariant::Download => crate::svg_graph::line_adaptive(
-                &self.download,
+                &download,
                 GRAPH_SAMPLES,
                 &self.svg_colors,
                 self.max_y,
@@ -250,7 +291,7 @@ impl Sensor for Network {
             NetworkVariant::Upload => {
                 let mut cols = self.svg_colors.clone();
                 cols.graph1 = cols.graph2.clone();
-                crate::svg_graph::line_adaptive(&self.upload, GRAPH_SAMPLES, &cols, self.max_y)
+                crate::svg_graph::line_adaptive(&upload, GRAPH_SAMPLES, &cols, self.max_y)
             }
         };
         let icon = cosmic::widget::icon::from_svg_bytes(svg.into_bytes());
@@ -366,6 +407,24 @@ impl Sensor for Network {
             );
         }
 
+        net_bandwidth_items.push(alerts::settings_ui(
+            DeviceKind::Network(k),
+            None,
+            &config.alerts,
+            self.alert_unit(),
+        ));
+        net_bandwidth_items.push(color_stops::settings_ui(
+            DeviceKind::Network(k),
+            None,
+            &config.color_stops,
+            self.alert_unit(),
+        ));
+        net_bandwidth_items.push(history::window_ui(
+            DeviceKind::Network(k),
+            None,
+            config.window,
+        ));
+
         net_bandwidth_items.push(
             row!(
                 widget::horizontal_space(),
@@ -401,6 +460,29 @@ impl Sensor for Network {
         .spacing(cosmic::theme::spacing().space_xs)
         .into()
     }
+
+    // Megabits per second over the last second, both directions for the combined item
+    fn alert_value(&self) -> Option<f64> {
+        let download = Network::last_second_bitrate(&self.download, self.refresh_rate);
+        let upload = Network::last_second_bitrate(&self.upload, self.refresh_rate);
+        let bitrate = match self.config.variant {
+            NetworkVariant::Combined => download + upload,
+            NetworkVariant::Download => download,
+            NetworkVariant::Upload => upload,
+        };
+        Some(bitrate as f64 / 1_000_000.0)
+    }
+
+    fn alert_unit(&self) -> &'static str {
+        "Mbps"
+    }
+
+    fn histories_mut(&mut self) -> Vec<Series<'_>> {
+        vec![
+            Series::new("download", "bit/s", &mut self.history_download),
+            Series::new("upload", "bit/s", &mut self.history_upload),
+        ]
+    }
 }
 
 impl Default for Network {
@@ -410,6 +492,8 @@ impl Default for Network {
             networks,
             download: BoundedVecDeque::from_iter(std::iter::repeat_n(0, MAX_SAMPLES), MAX_SAMPLES),
             upload: BoundedVecDeque::from_iter(std::iter::repeat_n(0, MAX_SAMPLES), MAX_SAMPLES),
+            history_download: History::default(),
+            history_upload: History::default(),
             max_y: None,
             dropdown_options: ["b", "Kb", "Mb", "Gb", "Tb"].into(),
             svg_colors: SvgColors::new(&ChartColors::default()),
@@ -510,6 +594,21 @@ impl Network {
         let rate = Network::last_second_bitrate(&self.upload, sample_interval_ms);
         Network::makestr(rate, format, self.config.show_bytes)
     }
+
+    pub fn prometheus(&self, metrics: &mut Metrics) {
+        metrics.gauge(
+            "network_receive_bits_per_second",
+            "Download rate of all interfaces",
+            &[],
+            Network::last_second_bitrate(&self.download, self.refresh_rate) as f64,
+        );
+        metrics.gauge(
+            "network_transmit_bits_per_second",
+            "Upload rate of all interfaces",
+            &[],
+            Network::last_second_bitrate(&self.upload, self.refresh_rate) as f64,
+        );
+    }
 }
 
 const DL_DEMO: [u64; 21] = [
@@ -0,0 +1,537 @@
+use crate::{app::Message, config::StorageHealthConfig, fl};
+
+use cosmic::Element;
+use cosmic::widget;
+use cosmic::widget::{settings, toggler};
+
+use cosmic::{iced::widget::column, iced_widget::Column};
+
+use log::info;
+use std::fs;
+use std::path::Path;
+
+const MDSTAT: &str = "/proc/mdstat";
+const BTRFS_BASE: &str = "/sys/fs/btrfs";
+const ZFS_BASE: &str = "/proc/spl/kstat/zfs";
+
+/// State of a Linux software RAID (md) array from /proc/mdstat
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MdArray {
+    pub name: String,
+    pub level: String,
+    pub active: bool,
+    pub devices_total: u32,
+    pub devices_up: u32,
+    pub failed: Vec<String>,
+    /// Running resync/recovery/reshape/check and its progress in percent
+    pub sync: Option<(String, f32)>,
+}
+
+impl MdArray {
+    pub fn is_degraded(&self) -> bool {
+        !self.active || self.devices_up < self.devices_total || !self.failed.is_empty()
+    }
+
+    /// A scheduled check is routine, anything else rebuilding means redundancy is reduced
+    pub fn is_rebuilding(&self) -> bool {
+        self.sync
+            .as_ref()
+            .is_some_and(|(action, _)| action != "check")
+    }
+
+    pub fn status(&self) -> String {
+        if !self.active {
+            fl!("storage-health-inactive")
+        } else if self.devices_up < self.devices_total {
+            fl!(
+                "storage-health-degraded",
+                up = self.devices_up,
+                total = self.devices_total
+            )
+        } else if !self.failed.is_empty() {
+            fl!("storage-health-failed", devices = self.failed.join(", "))
+        } else if let Some((action, progress)) = &self.sync {
+            format!("{action} {progress:.1}%")
+        } else {
+            fl!("storage-health-ok")
+        }
+    }
+}
+
+/// Parse the contents of /proc/mdstat
+pub fn parse_mdstat(content: &str) -> Vec<MdArray> {
+    let mut arrays: Vec<MdArray> = Vec::new();
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        // "md0 : active raid1 sdb1[1] sda1[0](F)"
+        if let Some((name, rest)) = line.split_once(" : ")
+            && name.starts_with("md")
+            && !line.starts_with(char::is_whitespace)
+        {
+            let mut parts = rest.split_whitespace();
+            let active = parts.next() == Some("active");
+            let mut parts = parts.peekable();
+            // Arrays can be flagged (auto-read-only) before the level
+            while parts.peek().is_some_and(|p| p.starts_with('(')) {
+                parts.next();
+            }
+            let level = if active {
+                parts.next().unwrap_or_default().to_string()
+            } else {
+                String::new()
+            };
+            let failed = parts
+                .filter(|p| p.ends_with("(F)"))
+                .map(|p| p.split('[').next().unwrap_or(p).to_string())
+                .collect();
+
+            arrays.push(MdArray {
+                name: name.trim().to_string(),
+                level,
+                active,
+                failed,
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let Some(array) = arrays.last_mut() else {
+            continue;
+        };
+
+        // "976630464 blocks super 1.2 [2/1] [U_]"
+        if trimmed.contains(" blocks") {
+            if let Some(counts) = trimmed
+                .split_whitespace()
+                .find(|p| p.starts_with('[') && p.contains('/'))
+                && let Some((total, up)) = counts.trim_matches(['[', ']']).split_once('/')
+            {
+                array.devices_total = total.parse().unwrap_or(0);
+                array.devices_up = up.parse().unwrap_or(0);
+            }
+            continue;
+        }
+
+        // "[=>....]  recovery =  8.5% (83457856/976630464) finish=77.2min speed=192818K/sec"
+        for action in ["resync", "recovery", "reshape", "check", "repair"] {
+            if let Some(rest) = trimmed.split(&format!("{action} =")).nth(1) {
+                let progress = rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|p| p.trim_end_matches('%').parse::<f32>().ok())
+                    .unwrap_or(0.0);
+                array.sync = Some((action.to_string(), progress));
+                break;
+            }
+        }
+    }
+
+    arrays
+}
+
+/// Error counters of one device in a btrfs filesystem
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BtrfsDevice {
+    pub filesystem: String,
+    pub devid: String,
+    pub write_errs: u64,
+    pub read_errs: u64,
+    pub flush_errs: u64,
+    pub corruption_errs: u64,
+    pub generation_errs: u64,
+}
+
+impl BtrfsDevice {
+    pub fn errors(&self) -> u64 {
+        self.write_errs
+            + self.read_errs
+            + self.flush_errs
+            + self.corruption_errs
+            + self.generation_errs
+    }
+
+    pub fn status(&self) -> String {
+        if self.errors() == 0 {
+            fl!("storage-health-ok")
+        } else {
+            fl!(
+                "storage-health-btrfs-errors",
+                write = self.write_errs,
+                read = self.read_errs,
+                flush = self.flush_errs,
+                corruption = self.corruption_errs,
+                generation = self.generation_errs
+            )
+        }
+    }
+}
+
+/// Read the device error counters of all mounted btrfs filesystems
+pub fn read_btrfs() -> Vec<BtrfsDevice> {
+    read_btrfs_from(Path::new(BTRFS_BASE))
+}
+
+fn read_btrfs_from(base: &Path) -> Vec<BtrfsDevice> {
+    let mut devices = Vec::new();
+    let Ok(entries) = fs::read_dir(base) else {
+        return devices;
+    };
+
+    for entry in entries.flatten() {
+        let fs_path = entry.path();
+        let Ok(devinfo) = fs::read_dir(fs_path.join("devinfo")) else {
+            continue;
+        };
+
+        let uuid = entry.file_name().to_string_lossy().to_string();
+        let filesystem = fs::read_to_string(fs_path.join("label"))
+            .map(|l| l.trim().to_string())
+            .ok()
+            .filter(|l| !l.is_empty())
+            .unwrap_or_else(|| uuid.chars().take(8).collect());
+
+        for dev in devinfo.flatten() {
+            let Ok(stats) = fs::read_to_string(dev.path().join("error_stats")) else {
+                continue;
+            };
+
+            let mut device = BtrfsDevice {
+                filesystem: filesystem.clone(),
+                devid: dev.file_name().to_string_lossy().to_string(),
+                ..Default::default()
+            };
+            for line in stats.lines() {
+                let Some((key, value)) = line.split_once(char::is_whitespace) else {
+                    continue;
+                };
+                let value = value.trim().parse().unwrap_or(0);
+                match key {
+                    "write_errs" => device.write_errs = value,
+                    "read_errs" => device.read_errs = value,
+                    "flush_errs" => device.flush_errs = value,
+                    "corruption_errs" => device.corruption_errs = value,
+                    "generation_errs" => device.generation_errs = value,
+                    _ => {}
+                }
+            }
+            devices.push(device);
+        }
+    }
+
+    devices.sort_by(|a, b| (&a.filesystem, &a.devid).cmp(&(&b.filesystem, &b.devid)));
+    devices
+}
+
+/// State of the imported zfs pools, if the zfs module exposes it
+pub fn read_zfs() -> Vec<(String, String)> {
+    read_zfs_from(Path::new(ZFS_BASE))
+}
+
+fn read_zfs_from(base: &Path) -> Vec<(String, String)> {
+    let mut pools = Vec::new();
+    let Ok(entries) = fs::read_dir(base) else {
+        return pools;
+    };
+
+    for entry in entries.flatten() {
+        if let Ok(state) = fs::read_to_string(entry.path().join("state")) {
+            pools.push((
+                entry.file_name().to_string_lossy().to_string(),
+                state.trim().to_string(),
+            ));
+        }
+    }
+    pools.sort();
+    pools
+}
+
+#[derive(Debug)]
+pub struct StorageHealth {
+    arrays: Vec<MdArray>,
+    btrfs: Vec<BtrfsDevice>,
+    zfs: Vec<(String, String)>,
+    config: StorageHealthConfig,
+}
+
+impl Default for StorageHealth {
+    fn default() -> Self {
+        let mut health = StorageHealth {
+            arrays: Vec::new(),
+            btrfs: Vec::new(),
+            zfs: Vec::new(),
+            config: StorageHealthConfig::default(),
+        };
+        health.update();
+        health
+    }
+}
+
+impl StorageHealth {
+    pub fn update_config(&mut self, config: &StorageHealthConfig) {
+        self.config = config.clone();
+    }
+
+    pub fn update(&mut self) {
+        let had_problems = self.has_problems();
+
+        self.arrays = fs::read_to_string(MDSTAT)
+            .map(|c| parse_mdstat(&c))
+            .unwrap_or_default();
+        self.btrfs = read_btrfs();
+        self.zfs = read_zfs();
+
+        if self.has_problems() && !had_problems {
+            info!("Storage health problems: {:?}", self.problems());
+        }
+    }
+
+    /// true if there's any RAID array or filesystem to watch
+    pub fn is_found(&self) -> bool {
+        !self.arrays.is_empty() || !self.btrfs.is_empty() || !self.zfs.is_empty()
+    }
+
+    /// Short descriptions of everything that needs attention
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for array in &self.arrays {
+            if array.is_degraded() || array.is_rebuilding() {
+                problems.push(format!("{} {}", array.name, array.status()));
+            }
+        }
+        for dev in &self.btrfs {
+            if dev.errors() > 0 {
+                problems.push(format!(
+                    "{} #{} {}",
+                    dev.filesystem,
+                    dev.devid,
+                    fl!("storage-health-btrfs-short", errors = dev.errors())
+                ));
+            }
+        }
+        for (pool, state) in &self.zfs {
+            if state != "ONLINE" {
+                problems.push(format!("{pool} {state}"));
+            }
+        }
+
+        problems
+    }
+
+    pub fn has_problems(&self) -> bool {
+        self.arrays
+            .iter()
+            .any(|a| a.is_degraded() || a.is_rebuilding())
+            || self.btrfs.iter().any(|d| d.errors() > 0)
+            || self.zfs.iter().any(|(_, state)| state != "ONLINE")
+    }
+
+    /// Label for the settings overview
+    pub fn summary(&self) -> String {
+        let count = self.problems().len();
+        if count == 0 {
+            fl!("storage-health-ok")
+        } else {
+            fl!("storage-health-problems", count = count)
+        }
+    }
+
+    pub fn settings_ui(&'_ self) -> Element<'_, crate::app::Message> {
+        let theme = cosmic::theme::active();
+        let cosmic = theme.cosmic();
+        let config = &self.config;
+
+        let mut details: Vec<Element<Message>> = Vec::new();
+
+        for array in &self.arrays {
+            details.push(
+                settings::item(
+                    format!("{} ({})", array.name, array.level),
+                    widget::text::body(array.status()),
+                )
+                .into(),
+            );
+        }
+        for dev in &self.btrfs {
+            details.push(
+                settings::item(
+                    format!("btrfs {} #{}", dev.filesystem, dev.devid),
+                    widget::text::body(dev.status()),
+                )
+                .into(),
+            );
+        }
+        for (pool, state) in &self.zfs {
+            details.push(
+                settings::item(format!("zfs {pool}"), widget::text::body(state.clone())).into(),
+            );
+        }
+        if details.is_empty() {
+            details.push(widget::text::body(fl!("storage-health-none")).into());
+        }
+
+        column!(
+            widget::text::heading(fl!("storage-health-title")),
+            settings::item(
+                fl!("storage-health-warning"),
+                toggler(config.show_warning).on_toggle(Message::ToggleStorageHealthWarning),
+            ),
+            settings::item(
+                fl!("enable-label"),
+                toggler(config.label_visible).on_toggle(Message::ToggleStorageHealthLabel),
+            ),
+            widget::text::heading(fl!("storage-health-devices")),
+            Column::with_children(details).spacing(cosmic.space_xxs())
+        )
+        .spacing(cosmic::theme::spacing().space_xs)
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const MDSTAT_DEGRADED: &str = "\
+Personalities : [raid1] [raid6] [raid5] [raid4]
+md1 : active raid5 sdd1[3] sdc1[1] sdb1[0]
+      1953260544 blocks super 1.2 level 5, 512k chunk, algorithm 2 [3/3] [UUU]
+      [=>...................]  resync =  8.5% (83457856/976630272) finish=77.2min speed=192818K/sec
+      bitmap: 0/8 pages [0KB], 65536KB chunk
+
+md0 : active raid1 sdb2[1](F) sda2[0]
+      976630464 blocks super 1.2 [2/1] [U_]
+
+unused devices: <none>
+";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("minimon-storage-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn mdstat_degraded_and_resync() {
+        let arrays = parse_mdstat(MDSTAT_DEGRADED);
+        assert_eq!(
+            arrays,
+            [
+                MdArray {
+                    name: String::from("md1"),
+                    level: String::from("raid5"),
+                    active: true,
+                    devices_total: 3,
+                    devices_up: 3,
+                    failed: Vec::new(),
+                    sync: Some((String::from("resync"), 8.5)),
+                },
+                MdArray {
+                    name: String::from("md0"),
+                    level: String::from("raid1"),
+                    active: true,
+                    devices_total: 2,
+                    devices_up: 1,
+                    failed: vec![String::from("sdb2")],
+                    sync: None,
+                },
+            ]
+        );
+        assert!(!arrays[0].is_degraded());
+        assert!(arrays[0].is_rebuilding());
+        assert!(arrays[1].is_degraded());
+    }
+
+    #[test]
+    fn mdstat_recovery_and_check() {
+        let arrays = parse_mdstat(
+            "\
+Personalities : [raid1]
+md0 : active raid1 sdc1[2] sda1[0]
+      976630464 blocks super 1.2 [2/1] [U_]
+      [====>................]  recovery = 21.3% (208123904/976630464) finish=64.1min speed=199744K/sec
+
+md2 : active (auto-read-only) raid1 sde1[1] sdd1[0]
+      488254464 blocks super 1.2 [2/2] [UU]
+      [==>..................]  check = 12.0% (58590535/488254464) finish=40.0min speed=178000K/sec
+
+md3 : inactive sdf1[0](S)
+      976630464 blocks super 1.2
+",
+        );
+        assert_eq!(arrays.len(), 3);
+        assert_eq!(arrays[0].sync, Some((String::from("recovery"), 21.3)));
+        assert!(arrays[0].is_degraded() && arrays[0].is_rebuilding());
+
+        assert_eq!(arrays[1].level, "raid1");
+        assert_eq!(arrays[1].sync, Some((String::from("check"), 12.0)));
+        assert!(!arrays[1].is_degraded() && !arrays[1].is_rebuilding());
+
+        assert!(!arrays[2].active);
+        assert!(arrays[2].is_degraded());
+    }
+
+    #[test]
+    fn btrfs_error_stats() {
+        let base = temp_dir("btrfs");
+        let labeled = base.join("0a1b2c3d-4e5f-6789-abcd-ef0123456789");
+        fs::create_dir_all(labeled.join("devinfo/1")).unwrap();
+        fs::create_dir_all(labeled.join("devinfo/2")).unwrap();
+        fs::write(labeled.join("label"), "data\n").unwrap();
+        fs::write(
+            labeled.join("devinfo/1/error_stats"),
+            "write_errs 0\nread_errs 0\nflush_errs 0\ncorruption_errs 0\ngeneration_errs 0\n",
+        )
+        .unwrap();
+        fs::write(
+            labeled.join("devinfo/2/error_stats"),
+            "write_errs 3\nread_errs 12\nflush_errs 0\ncorruption_errs 1\ngeneration_errs 0\n",
+        )
+        .unwrap();
+        // Without a label the start of the uuid names the filesystem
+        let unlabeled = base.join("fedcba98-7654-3210-fedc-ba9876543210");
+        fs::create_dir_all(unlabeled.join("devinfo/1")).unwrap();
+        fs::write(unlabeled.join("label"), "\n").unwrap();
+        fs::write(unlabeled.join("devinfo/1/error_stats"), "write_errs 0\n").unwrap();
+        // Global entries like features have no devinfo
+        fs::create_dir_all(base.join("features")).unwrap();
+
+        let devices = read_btrfs_from(&base);
+        assert_eq!(
+            devices
+                .iter()
+                .map(|d| (d.filesystem.as_str(), d.devid.as_str(), d.errors()))
+                .collect::<Vec<_>>(),
+            [("data", "1", 0), ("data", "2", 16), ("fedcba98", "1", 0)]
+        );
+        assert_eq!(devices[1].read_errs, 12);
+        assert_eq!(devices[1].corruption_errs, 1);
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn zfs_pool_states() {
+        let base = temp_dir("zfs");
+        fs::create_dir_all(base.join("tank")).unwrap();
+        fs::write(base.join("tank/state"), "DEGRADED\n").unwrap();
+        fs::create_dir_all(base.join("rpool")).unwrap();
+        fs::write(base.join("rpool/state"), "ONLINE\n").unwrap();
+        // Module wide stats are files, not pools
+        fs::write(base.join("arcstats"), "").unwrap();
+
+        assert_eq!(
+            read_zfs_from(&base),
+            [
+                (String::from("rpool"), String::from("ONLINE")),
+                (String::from("tank"), String::from("DEGRADED")),
+            ]
+        );
+        assert!(read_zfs_from(&base.join("missing")).is_empty());
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,300 @@
+//! Thermal zones from /sys/class/thermal, the only temperature source on many ARM
+//! boards and some laptops where no CPU hwmon driver is loaded.
+//! The zones are not panel items of their own, the CPU temperature item lists them
+//! with their trip points and can show any one of them.
+
+use log::info;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Zone types known to measure the CPU, best first
+const CPU_ZONE_TYPES: [&str; 8] = [
+    "x86_pkg_temp",
+    "cpu-thermal",
+    "cpu_thermal",
+    "cpu0-thermal",
+    "cpu-big0-thermal",
+    "soc_thermal",
+    "soc-thermal",
+    "tcpu",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TripPoint {
+    // passive, active, hot or critical
+    pub kind: String,
+    // Celcius
+    pub temp: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThermalZone {
+    // thermal_zone0
+    pub name: String,
+    // Driver supplied, like x86_pkg_temp, acpitz or cpu-thermal
+    pub zone_type: String,
+    // The type, with the occurrence after the first one of the type, like acpitz#1.
+    // Zone numbers depend on the probe order, this is what the settings store.
+    pub key: String,
+    pub trip_points: Vec<TripPoint>,
+    temp_path: PathBuf,
+}
+
+impl ThermalZone {
+    fn read(path: &Path) -> Option<ThermalZone> {
+        let name = path.file_name()?.to_string_lossy().to_string();
+        let zone_type = fs::read_to_string(path.join("type"))
+            .ok()?
+            .trim()
+            .to_string();
+        let temp_path = path.join("temp");
+        if !temp_path.exists() {
+            return None;
+        }
+
+        let mut trip_points = Vec::new();
+        for i in 0.. {
+            let Ok(kind) = fs::read_to_string(path.join(format!("trip_point_{i}_type"))) else {
+                break;
+            };
+            let Some(millideg) = fs::read_to_string(path.join(format!("trip_point_{i}_temp")))
+                .ok()
+                .and_then(|t| t.trim().parse::<i64>().ok())
+            else {
+                continue;
+            };
+            // Disabled trip points are reported with absurd values
+            if millideg <= 0 || millideg >= 200_000 {
+                continue;
+            }
+            trip_points.push(TripPoint {
+                kind: kind.trim().to_string(),
+                temp: millideg as f64 / 1000.0,
+            });
+        }
+
+        Some(ThermalZone {
+            name,
+            key: zone_type.clone(),
+            zone_type,
+            trip_points,
+            temp_path,
+        })
+    }
+
+    /// Current temperature in Celcius
+    pub fn read_temp(&self) -> io::Result<f64> {
+        let raw = fs::read_to_string(&self.temp_path)?;
+        let millideg: i64 = raw
+            .trim()
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Parse error: {e}")))?;
+        Ok(millideg as f64 / 1000.0)
+    }
+
+    /// The critical trip point, where the system shuts down
+    pub fn critical(&self) -> Option<f64> {
+        self.trip_points
+            .iter()
+            .find(|t| t.kind == "critical")
+            .map(|t| t.temp)
+    }
+
+    /// Like "passive 95C, critical 105C"
+    pub fn trip_points_label(&self) -> String {
+        self.trip_points
+            .iter()
+            .map(|t| format!("{} {}C", t.kind, t.temp.trunc()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// All readable zones, ordered by zone number
+pub fn thermal_zones() -> Vec<ThermalZone> {
+    read_zones(Path::new("/sys/class/thermal"))
+}
+
+fn read_zones(base: &Path) -> Vec<ThermalZone> {
+    let Ok(entries) = fs::read_dir(base) else {
+        return Vec::new();
+    };
+
+    let mut zones: Vec<ThermalZone> = entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("thermal_zone"))
+        .filter_map(|e| ThermalZone::read(&e.path()))
+        .collect();
+
+    zones.sort_by_key(|z| {
+        z.name
+            .trim_start_matches("thermal_zone")
+            .parse::<u32>()
+            .unwrap_or(u32::MAX)
+    });
+    for i in 0..zones.len() {
+        let earlier = zones[..i]
+            .iter()
+            .filter(|z| z.zone_type == zones[i].zone_type)
+            .count();
+        if earlier > 0 {
+            zones[i].key = format!("{}#{earlier}", zones[i].zone_type);
+        }
+    }
+    for zone in &zones {
+        info!(
+            "  thermal zone {} type {}, trip points: {:?}",
+            zone.name, zone.zone_type, zone.trip_points
+        );
+    }
+    zones
+}
+
+/// The zone most likely to measure the CPU. ACPI zones are the last resort,
+/// on most laptops they follow the CPU package.
+pub fn find_cpu_zone(zones: &[ThermalZone]) -> Option<usize> {
+    CPU_ZONE_TYPES
+        .iter()
+        .find_map(|t| {
+            zones
+                .iter()
+                .position(|z| z.zone_type.eq_ignore_ascii_case(t))
+        })
+        .or_else(|| {
+            zones
+                .iter()
+                .position(|z| z.zone_type.to_lowercase().contains("cpu"))
+        })
+        .or_else(|| zones.iter().position(|z| z.zone_type == "acpitz"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("minimon-thermal-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Trip points as (type, millidegrees)
+    fn write_zone(base: &Path, number: u32, zone_type: &str, trip_points: &[(&str, &str)]) {
+        let path = base.join(format!("thermal_zone{number}"));
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("type"), format!("{zone_type}\n")).unwrap();
+        fs::write(path.join("temp"), "45000\n").unwrap();
+        for (i, (kind, temp)) in trip_points.iter().enumerate() {
+            fs::write(
+                path.join(format!("trip_point_{i}_type")),
+                format!("{kind}\n"),
+            )
+            .unwrap();
+            fs::write(
+                path.join(format!("trip_point_{i}_temp")),
+                format!("{temp}\n"),
+            )
+            .unwrap();
+        }
+    }
+
+    fn zone(zone_type: &str) -> ThermalZone {
+        ThermalZone {
+            name: String::from("thermal_zone0"),
+            zone_type: zone_type.to_string(),
+            key: zone_type.to_string(),
+            trip_points: Vec::new(),
+            temp_path: PathBuf::new(),
+        }
+    }
+
+    #[test]
+    fn read_trip_points() {
+        let base = temp_dir("trips");
+        write_zone(
+            &base,
+            0,
+            "acpitz",
+            &[
+                ("passive", "95000"),
+                // Disabled trip points
+                ("active", "0"),
+                ("hot", "-273200"),
+                ("active", "255000"),
+                ("critical", "105000"),
+            ],
+        );
+
+        let zone = ThermalZone::read(&base.join("thermal_zone0")).unwrap();
+        assert_eq!(zone.name, "thermal_zone0");
+        assert_eq!(zone.zone_type, "acpitz");
+        assert_eq!(
+            zone.trip_points,
+            [
+                TripPoint {
+                    kind: String::from("passive"),
+                    temp: 95.0,
+                },
+                TripPoint {
+                    kind: String::from("critical"),
+                    temp: 105.0,
+                },
+            ]
+        );
+        assert_eq!(zone.critical(), Some(105.0));
+        assert_eq!(zone.trip_points_label(), "passive 95C, critical 105C");
+        assert_eq!(zone.read_temp().unwrap(), 45.0);
+
+        // Without a temp file the zone can't be read
+        fs::remove_file(base.join("thermal_zone0/temp")).unwrap();
+        assert!(ThermalZone::read(&base.join("thermal_zone0")).is_none());
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn duplicate_types_get_keys() {
+        let base = temp_dir("keys");
+        write_zone(&base, 10, "acpitz", &[]);
+        write_zone(&base, 2, "acpitz", &[]);
+        write_zone(&base, 1, "x86_pkg_temp", &[]);
+        write_zone(&base, 0, "acpitz", &[]);
+        fs::create_dir_all(base.join("cooling_device0")).unwrap();
+
+        let zones = read_zones(&base);
+        assert_eq!(
+            zones
+                .iter()
+                .map(|z| (z.name.as_str(), z.key.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                ("thermal_zone0", "acpitz"),
+                ("thermal_zone1", "x86_pkg_temp"),
+                ("thermal_zone2", "acpitz#1"),
+                ("thermal_zone10", "acpitz#2"),
+            ]
+        );
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn cpu_zone_preference() {
+        let zones = [zone("acpitz"), zone("iwlwifi_1"), zone("x86_pkg_temp")];
+        assert_eq!(find_cpu_zone(&zones), Some(2));
+
+        // Case differs between SoC drivers
+        let zones = [zone("gpu-thermal"), zone("CPU-Thermal")];
+        assert_eq!(find_cpu_zone(&zones), Some(1));
+
+        // Any type naming the CPU before ACPI
+        let zones = [zone("acpitz"), zone("cpu_little")];
+        assert_eq!(find_cpu_zone(&zones), Some(1));
+
+        let zones = [zone("pch_skylake"), zone("acpitz")];
+        assert_eq!(find_cpu_zone(&zones), Some(1));
+
+        assert_eq!(find_cpu_zone(&[zone("pch_skylake")]), None);
+    }
+}
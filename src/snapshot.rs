@@ -0,0 +1,307 @@
+//! `--snapshot` and `--watch`: detect the sensors and print their readings without
+//! starting the applet, as a scriptable diagnostic, e.g. over SSH.
+//!
+//! Rates need two samples, so the first readings are printed one interval after
+//! detection. The readings are the gauges of the Prometheus endpoint, printed as
+//! aligned text or one JSON object per line. `--record` also saves the sensor inputs
+//! to a trace, see crate::trace.
+
+use chrono::{SecondsFormat, Utc};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::config::{DisksConfig, GpuConfig, NetworkConfig};
+use crate::export::{json_number, json_string};
+use crate::prometheus::{Metrics, render_labels};
+use crate::sensors::{
+    Sensor,
+    cpu::Cpu,
+    cputemp::CpuTemp,
+    disks::Disks,
+    disktemp::DiskTemp,
+    gpus::{Gpu, list_gpus},
+    memory::Memory,
+    network::Network,
+};
+use crate::trace;
+
+pub const USAGE: &str = "Usage: cosmic-applet-minimon --snapshot|--watch [--json] [--interval <seconds>] [--record <trace>]";
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+const MIN_INTERVAL_SECS: f64 = 0.1;
+
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    /// Keep printing every interval until interrupted
+    pub watch: bool,
+    pub json: bool,
+    pub interval: Duration,
+    /// Trace file for the sensor inputs
+    pub record: Option<PathBuf>,
+}
+
+impl Options {
+    /// Parse the arguments after the program name
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            watch: false,
+            json: false,
+            interval: DEFAULT_INTERVAL,
+            record: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--snapshot" => {}
+                "--watch" => options.watch = true,
+                "--json" => options.json = true,
+                "--interval" => {
+                    let seconds = args
+                        .next()
+                        .and_then(|s| s.parse::<f64>().ok())
+                        .filter(|s| s.is_finite() && *s >= MIN_INTERVAL_SECS)
+                        .ok_or_else(|| {
+                            format!("--interval needs at least {MIN_INTERVAL_SECS} seconds")
+                        })?;
+                    options.interval = Duration::from_secs_f64(seconds);
+                }
+                "--record" => {
+                    let path = args.next().ok_or("--record needs a file")?;
+                    options.record = Some(PathBuf::from(path));
+                }
+                other => return Err(format!("Unknown argument {other}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// The sensors the applet would show, with default settings
+struct Sensors {
+    cpu: Cpu,
+    cputemp: CpuTemp,
+    memory: Memory,
+    network: Network,
+    disks: Disks,
+    disktemp: DiskTemp,
+    gpus: Vec<Gpu>,
+}
+
+impl Sensors {
+    // The rates are computed for `interval` between updates
+    fn detect(interval: Duration) -> Sensors {
+        let refresh_rate = u32::try_from(interval.as_millis()).unwrap_or(u32::MAX);
+        let mut sensors = Sensors {
+            cpu: Cpu::new(true),
+            cputemp: CpuTemp::default(),
+            memory: Memory::default(),
+            network: Network::default(),
+            disks: Disks::default(),
+            disktemp: DiskTemp::default(),
+            gpus: list_gpus(),
+        };
+        sensors
+            .network
+            .update_config(&NetworkConfig::default(), refresh_rate);
+        sensors
+            .disks
+            .update_config(&DisksConfig::default(), refresh_rate);
+        for gpu in &mut sensors.gpus {
+            gpu.update_config(&GpuConfig::default(), refresh_rate);
+        }
+        sensors
+    }
+
+    fn update(&mut self) {
+        self.cpu.update();
+        self.cputemp.update();
+        self.memory.update();
+        self.network.update();
+        self.disks.update();
+        self.disktemp.update();
+        // A stopped GPU isn't woken up, it only reports gpu_active 0
+        for gpu in &mut self.gpus {
+            gpu.update();
+        }
+    }
+
+    fn metrics(&self) -> Metrics {
+        let mut metrics = Metrics::default();
+        self.cpu.prometheus(&mut metrics);
+        self.cputemp.prometheus(&mut metrics);
+        self.memory.prometheus(&mut metrics);
+        self.network.prometheus(&mut metrics);
+        self.disks.prometheus(&mut metrics);
+        self.disktemp.prometheus(&mut metrics);
+        for gpu in &self.gpus {
+            gpu.prometheus(&mut metrics);
+        }
+        metrics
+    }
+}
+
+/// Print the readings once, or every interval with `--watch`
+pub fn run(options: &Options) -> io::Result<()> {
+    if let Some(path) = &options.record {
+        trace::record(path)?;
+    }
+    let mut sensors = Sensors::detect(options.interval);
+    sensors.update();
+    trace::next_frame();
+
+    let mut out = io::stdout().lock();
+    loop {
+        std::thread::sleep(options.interval);
+        sensors.update();
+        trace::next_frame();
+
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let metrics = sensors.metrics();
+        if options.json {
+            write_json(&mut out, &timestamp, &metrics)?;
+        } else {
+            if options.watch {
+                writeln!(out)?;
+            }
+            write_text(&mut out, &timestamp, &metrics)?;
+        }
+        out.flush()?;
+
+        if !options.watch {
+            return Ok(());
+        }
+    }
+}
+
+// The timestamp, then a line per sample with the values aligned
+fn write_text(out: &mut impl Write, timestamp: &str, metrics: &Metrics) -> io::Result<()> {
+    let rows: Vec<(String, f64)> = metrics
+        .samples()
+        .map(|s| (format!("{}{}", s.name, render_labels(s.labels)), s.value))
+        .collect();
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    writeln!(out, "{timestamp}")?;
+    for (name, value) in rows {
+        writeln!(out, "{name:<width$}  {}", text_value(value))?;
+    }
+    Ok(())
+}
+
+// Enough decimals for latencies in seconds, without trailing zeros
+fn text_value(value: f64) -> String {
+    let rounded = format!("{value:.6}");
+    let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" => String::from("0"),
+        _ => trimmed.to_string(),
+    }
+}
+
+// {"timestamp":...,"metrics":[{"name":...,"labels":{...},"value":...},...]}
+fn write_json(out: &mut impl Write, timestamp: &str, metrics: &Metrics) -> io::Result<()> {
+    let samples: Vec<String> = metrics
+        .samples()
+        .map(|s| {
+            let labels: Vec<String> = s
+                .labels
+                .iter()
+                .map(|(k, v)| format!("{}:{}", json_string(k), json_string(v)))
+                .collect();
+            format!(
+                r#"{{"name":{},"labels":{{{}}},"value":{}}}"#,
+                json_string(s.name),
+                labels.join(","),
+                json_number(s.value)
+            )
+        })
+        .collect();
+    writeln!(
+        out,
+        r#"{{"timestamp":{},"metrics":[{}]}}"#,
+        json_string(timestamp),
+        samples.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn metrics() -> Metrics {
+        let mut metrics = Metrics::default();
+        metrics.gauge("cpu_usage_percent", "CPU load", &[], 12.5);
+        metrics.gauge(
+            "disk_latency_seconds",
+            "Latency",
+            &[("direction", "read")],
+            0.000125,
+        );
+        metrics.gauge("memory_total_bytes", "Total", &[], 16_000_000_000.0);
+        metrics
+    }
+
+    #[test]
+    fn parse_options() {
+        assert_eq!(
+            Options::parse(&args(&["--snapshot"])),
+            Ok(Options {
+                watch: false,
+                json: false,
+                interval: DEFAULT_INTERVAL,
+                record: None,
+            })
+        );
+        assert_eq!(
+            Options::parse(&args(&[
+                "--watch",
+                "--json",
+                "--interval",
+                "2.5",
+                "--record",
+                "a.trace"
+            ])),
+            Ok(Options {
+                watch: true,
+                json: true,
+                interval: Duration::from_millis(2500),
+                record: Some(PathBuf::from("a.trace")),
+            })
+        );
+        assert!(Options::parse(&args(&["--snapshot", "--interval", "0"])).is_err());
+        assert!(Options::parse(&args(&["--snapshot", "--interval"])).is_err());
+        assert!(Options::parse(&args(&["--snapshot", "--record"])).is_err());
+        assert!(Options::parse(&args(&["--snapshot", "--csv"])).is_err());
+    }
+
+    #[test]
+    fn text_output() {
+        let mut out = Vec::new();
+        write_text(&mut out, "2026-01-01T00:00:00.000Z", &metrics()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "2026-01-01T00:00:00.000Z\n\
+             cpu_usage_percent                       12.5\n\
+             disk_latency_seconds{direction=\"read\"}  0.000125\n\
+             memory_total_bytes                      16000000000\n"
+        );
+    }
+
+    #[test]
+    fn json_output() {
+        let mut out = Vec::new();
+        write_json(&mut out, "2026-01-01T00:00:00.000Z", &metrics()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"{"timestamp":"2026-01-01T00:00:00.000Z","metrics":[{"name":"cpu_usage_percent","labels":{},"value":12.5},{"name":"disk_latency_seconds","labels":{"direction":"read"},"value":0.000125},{"name":"memory_total_bytes","labels":{},"value":16000000000}]}"#
+                .to_owned()
+                + "\n"
+        );
+    }
+}
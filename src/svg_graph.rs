@@ -1,8 +1,9 @@
+use std::borrow::Cow;
 use std::collections::VecDeque;
 
 use cosmic::cosmic_theme::palette::Srgba;
 
-use crate::config::ChartColors;
+use crate::config::{ChartColors, ColorStops};
 
 use std::fmt::Write;
 
@@ -16,15 +17,15 @@ pub struct SvgColors {
     pub graph3: String,
 }
 
+fn to_hex(color: Srgba<u8>) -> String {
+    format!(
+        "#{:02X}{:02X}{:02X}{:02X}",
+        color.red, color.green, color.blue, color.alpha
+    )
+}
+
 impl From<ChartColors> for SvgColors {
     fn from(graph_colors: ChartColors) -> Self {
-        fn to_hex(color: Srgba<u8>) -> String {
-            format!(
-                "#{:02X}{:02X}{:02X}{:02X}",
-                color.red, color.green, color.blue, color.alpha
-            )
-        }
-
         SvgColors {
             background: to_hex(graph_colors.background),
             frame: to_hex(graph_colors.frame),
@@ -44,6 +45,23 @@ impl SvgColors {
     pub fn set_colors(&mut self, colors: &ChartColors) {
         *self = (*colors).into();
     }
+
+    /// graph1 in the color of the stop the value reached, keeping the alpha of graph1
+    /// so line charts stay translucent
+    pub fn with_stops(&self, stops: &ColorStops, value: Option<f64>) -> Cow<'_, SvgColors> {
+        match value.and_then(|v| stops.color(v)) {
+            Some(color) => {
+                let alpha = self.graph1.get(7..).unwrap_or("FF");
+                let mut colors = self.clone();
+                colors.graph1 = format!(
+                    "#{:02X}{:02X}{:02X}{alpha}",
+                    color.red, color.green, color.blue
+                );
+                Cow::Owned(colors)
+            }
+            None => Cow::Borrowed(self),
+        }
+    }
 }
 
 fn clip_path_for_ram_fill(percentage: u8) -> String {
@@ -233,6 +251,94 @@ pub fn line_stacked(
     svg
 }
 
+/// Stacked areas, one layer per series drawn on top of the previous ones.
+/// Layers take graph1, graph2 and graph3 in turn, later rounds blend them towards the text color.
+pub fn line_layers(layers: &[VecDeque<f64>], max_y: f64, colors: &SvgColors) -> String {
+    let scaling: f32 = if max_y > 0.0 {
+        40.0 / max_y as f32
+    } else {
+        0.0
+    };
+    let n = layers.iter().map(VecDeque::len).min().unwrap_or(0);
+
+    let mut svg = String::with_capacity(LINE_LEN * (layers.len() + 1));
+    svg.push_str(LINESVG_1);
+    svg.push_str(&colors.background);
+    svg.push_str(LINESVG_2);
+    svg.push_str(&colors.frame);
+    svg.push_str(LINESVG_3);
+
+    let mut bottom = vec![0.0f64; n];
+    for (layer_idx, layer) in layers.iter().enumerate() {
+        let color = &layer_color(colors, layer_idx);
+
+        let mut top_points = String::with_capacity(n * 10);
+        let mut bottom_points = String::with_capacity(n * 10);
+        for (index, value) in layer.iter().take(n).enumerate() {
+            let x = ((index * 2) + 1) as u32;
+            let y_bottom = (41.0 - (scaling * bottom[index] as f32))
+                .round()
+                .clamp(1.0, 41.0);
+            bottom[index] = (bottom[index] + value.max(0.0)).min(max_y);
+            let y_top = (41.0 - (scaling * bottom[index] as f32))
+                .round()
+                .clamp(1.0, 41.0);
+
+            if index > 0 {
+                top_points.push(' ');
+            }
+            let _ = write!(&mut top_points, "{x},{y_top}");
+            let _ = write!(&mut bottom_points, " {x},{y_bottom}");
+        }
+
+        // The band between the previous layers and this one: forward along the top, back along the bottom
+        let reversed: Vec<&str> = bottom_points.split_whitespace().rev().collect();
+        svg.push_str(r#"<polygon fill=""#);
+        svg.push_str(color);
+        svg.push_str(r#"" points=""#);
+        svg.push_str(&top_points);
+        svg.push(' ');
+        svg.push_str(&reversed.join(" "));
+        svg.push_str(r#""/>"#);
+
+        svg.push_str(LINESVG_4);
+        svg.push_str(&color[..color.len() - 2]);
+        svg.push_str(LINESVG_5);
+        svg.push_str(&top_points);
+        svg.push_str(LINESVG_3);
+    }
+
+    svg.push_str(LINESVG_9);
+    svg
+}
+
+// Every round of three layers is a shade further from the graph colors, keeping their alpha
+fn layer_color(colors: &SvgColors, layer_idx: usize) -> String {
+    let base = match layer_idx % 3 {
+        0 => &colors.graph1,
+        1 => &colors.graph2,
+        _ => &colors.graph3,
+    };
+    let round = (layer_idx / 3) as f64;
+    if round == 0.0 {
+        return base.clone();
+    }
+
+    let channel = |hex: &str, i: usize| {
+        hex.get(1 + i * 2..3 + i * 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .unwrap_or(0) as f64
+    };
+    let mix = round / (round + 1.0);
+    let mut color = String::from("#");
+    for i in 0..3 {
+        let value = channel(base, i) + (channel(&colors.text, i) - channel(base, i)) * mix;
+        let _ = write!(&mut color, "{:02X}", value.round() as u8);
+    }
+    color.push_str(base.get(7..).unwrap_or("FF"));
+    color
+}
+
 pub fn double_line(
     samples: &VecDeque<u64>,
     samples2: &VecDeque<u64>,
@@ -363,7 +469,33 @@ pub fn line_adaptive(
     svg
 }
 
-pub fn heat(samples: &VecDeque<f64>, max_y: u64, colors: &SvgColors) -> String {
+// Hard color bands at the stop values, y runs from 41 at zero to 1 at max_y
+fn heat_gradient(stops: &ColorStops, max_y: u64) -> String {
+    let mut gradient = String::with_capacity(256);
+    gradient.push_str(r#"y1="41" x2="0" y2="1" gradientUnits="userSpaceOnUse">"#);
+    let mut previous: Option<String> = None;
+    for stop in stops.sorted() {
+        let offset = (stop.from as f64 * 100.0 / max_y.max(1) as f64).clamp(0.0, 100.0);
+        let color = to_hex(stop.color);
+        if let Some(prev) = &previous {
+            let _ = write!(
+                gradient,
+                r#"<stop offset="{offset:.1}%" stop-color="{}"/>"#,
+                &prev[..7]
+            );
+        }
+        let _ = write!(
+            gradient,
+            r#"<stop offset="{offset:.1}%" stop-color="{}"/>"#,
+            &color[..7]
+        );
+        previous = Some(color);
+    }
+    gradient
+}
+
+/// Heat chart, in the colors of the stops when they're enabled
+pub fn heat(samples: &VecDeque<f64>, max_y: u64, colors: &SvgColors, stops: &ColorStops) -> String {
     // Generate list of coordinates for line
 
     let scaling: f32 = 40.0 / max_y as f32;
@@ -383,6 +515,12 @@ pub fn heat(samples: &VecDeque<f64>, max_y: u64, colors: &SvgColors) -> String {
     );
 
     let mut svg = String::with_capacity(LINE_LEN);
+    svg.push_str(HEATSVG_0);
+    if stops.enabled && !stops.stops.is_empty() {
+        svg.push_str(&heat_gradient(stops, max_y));
+    } else {
+        svg.push_str(HEATSVG_GRADIENT);
+    }
     svg.push_str(HEATSVG_1);
     svg.push_str(&colors.background);
     svg.push_str(HEATSVG_2);
@@ -396,11 +534,13 @@ pub fn heat(samples: &VecDeque<f64>, max_y: u64, colors: &SvgColors) -> String {
     svg
 }
 
-const HEATSVG_1: &str = r#"<svg width="42" height="42" viewBox="0 0 42 42" xmlns="http://www.w3.org/2000/svg">
+const HEATSVG_0: &str = r#"<svg width="42" height="42" viewBox="0 0 42 42" xmlns="http://www.w3.org/2000/svg">
   <defs>
-  <linearGradient id="temp-gradient" x1="0" y1="42" x2="0" y2="0" gradientUnits="userSpaceOnUse">
+  <linearGradient id="temp-gradient" x1="0" "#;
+const HEATSVG_GRADIENT: &str = r#"y1="42" x2="0" y2="0" gradientUnits="userSpaceOnUse">
       <stop offset="0%" stop-color="orange"/>
-      <stop offset="90%" stop-color="red"/>
+      <stop offset="90%" stop-color="red"/>"#;
+const HEATSVG_1: &str = r#"
     </linearGradient>
     <clipPath id="rounded-clip">
       <rect x="0" y="0" width="42" height="42" rx="7" ry="7"/>
@@ -0,0 +1,47 @@
+//! A dbus-daemon of our own for the tests, so they never reach the user's desktop.
+
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use zbus::blocking::{Connection, connection::Builder};
+
+pub struct PrivateBus {
+    daemon: Child,
+    pub address: String,
+}
+
+impl PrivateBus {
+    /// Fails the test if dbus-daemon isn't installed, the tests using it are ignored by default
+    pub fn start() -> PrivateBus {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("dbus-daemon installed");
+
+        let mut address = String::new();
+        let stdout = daemon.stdout.take().expect("dbus-daemon stdout");
+        BufReader::new(stdout)
+            .read_line(&mut address)
+            .expect("dbus-daemon address");
+
+        PrivateBus {
+            daemon,
+            address: address.trim().to_string(),
+        }
+    }
+
+    pub fn connect(&self) -> Connection {
+        Builder::address(self.address.as_str())
+            .expect("valid bus address")
+            .build()
+            .expect("connect to private bus")
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
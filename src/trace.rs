@@ -0,0 +1,484 @@
+//! Recording and replay of the raw sensor inputs, so a bug report can come with a trace
+//! that reproduces the charts and labels for hardware we don't have.
+//!
+//! `--watch --record <file>`, or `--record <file>` for the applet itself, saves the
+//! /proc/stat CPU lines, memory use, network traffic, the CPU and drive temperatures and
+//! the GPU readings and metric ranges of every update. `--replay <file>` runs the applet
+//! with them in place of the hardware, one recorded update per refresh. Disk and
+//! filesystem usage are read live during a replay.
+//!
+//! The replay runs at the applet's refresh rate, the frame times are only kept for
+//! reading the file.
+//!
+//! The file is text, a line per declaration or reading with the fields separated by
+//! tabs (spaces here):
+//!
+//! ```text
+//! minimon-trace 1
+//! gpu <id> <name> <VRAM bytes> <has temperature 0|1> <accelerator 0|1> <metric,...>
+//! drive <device> <model> <critical °C>
+//! frame <ms since the start>
+//! <input> <value, or ! and the error>
+//! ```
+//!
+//! A frame line ends the readings of an update, the first update also has those taken
+//! while detecting the sensors.
+
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+
+use crate::config::GpuMetric;
+
+const MAGIC: &str = "minimon-trace";
+const VERSION: u32 = 1;
+
+/// A GPU as detected while recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuInfo {
+    pub id: String,
+    pub name: String,
+    pub vram_total: u64,
+    pub has_temperature: bool,
+    pub accelerator: bool,
+    pub metrics: Vec<GpuMetric>,
+}
+
+/// A drive temperature sensor as detected while recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriveInfo {
+    pub device: String,
+    pub model: String,
+    pub crit_temp: f64,
+}
+
+/// Writes a trace as the sensors are read
+pub struct Recorder<W: Write> {
+    out: W,
+    start: Instant,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        writeln!(out, "{MAGIC}\t{VERSION}")?;
+        Ok(Recorder {
+            out,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn gpu(&mut self, gpu: &GpuInfo) -> io::Result<()> {
+        let metrics: Vec<String> = gpu.metrics.iter().map(|m| format!("{m:?}")).collect();
+        writeln!(
+            self.out,
+            "gpu\t{}\t{}\t{}\t{}\t{}\t{}",
+            escape(&gpu.id),
+            escape(&gpu.name),
+            gpu.vram_total,
+            u8::from(gpu.has_temperature),
+            u8::from(gpu.accelerator),
+            metrics.join(",")
+        )
+    }
+
+    pub fn drive(&mut self, drive: &DriveInfo) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "drive\t{}\t{}\t{}",
+            escape(&drive.device),
+            escape(&drive.model),
+            drive.crit_temp
+        )
+    }
+
+    pub fn input(&mut self, key: &str, value: Result<&str, &str>) -> io::Result<()> {
+        match value {
+            Ok(value) => writeln!(self.out, "{}\t{}", escape(key), escape(value)),
+            Err(e) => writeln!(self.out, "{}\t!{}", escape(key), escape(e)),
+        }
+    }
+
+    /// End the readings of an update and write them out
+    pub fn frame(&mut self) -> io::Result<()> {
+        writeln!(self.out, "frame\t{}", self.start.elapsed().as_millis())?;
+        self.out.flush()
+    }
+}
+
+// The readings of one update, errors as their message
+type Frame = HashMap<String, Result<String, String>>;
+
+/// A loaded trace and the update being replayed
+#[derive(Debug)]
+pub struct Trace {
+    gpus: Vec<GpuInfo>,
+    drives: Vec<DriveInfo>,
+    frames: Vec<Frame>,
+    current: usize,
+}
+
+impl Trace {
+    pub fn parse(text: &str) -> Result<Trace, String> {
+        let mut lines = text.lines().enumerate();
+        if lines.next().map(|(_, l)| l) != Some(format!("{MAGIC}\t{VERSION}").as_str()) {
+            return Err(format!("Not a {MAGIC} version {VERSION} file"));
+        }
+
+        let mut trace = Trace {
+            gpus: Vec::new(),
+            drives: Vec::new(),
+            frames: vec![Frame::new()],
+            current: 0,
+        };
+        for (index, line) in lines {
+            let invalid = || format!("Line {}: invalid {line:?}", index + 1);
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                [""] => {}
+                ["frame", _] => trace.frames.push(Frame::new()),
+                [
+                    "gpu",
+                    id,
+                    name,
+                    vram_total,
+                    has_temperature,
+                    accelerator,
+                    metrics,
+                ] => {
+                    let metrics = metrics
+                        .split(',')
+                        .filter(|m| !m.is_empty())
+                        .map(|m| {
+                            GpuMetric::ALL
+                                .into_iter()
+                                .find(|metric| format!("{metric:?}") == *m)
+                                .ok_or_else(invalid)
+                        })
+                        .collect::<Result<_, _>>()?;
+                    let gpu = GpuInfo {
+                        id: unescape(id),
+                        name: unescape(name),
+                        vram_total: vram_total.parse().map_err(|_| invalid())?,
+                        has_temperature: *has_temperature == "1",
+                        accelerator: *accelerator == "1",
+                        metrics,
+                    };
+                    // Declared again when the GPUs are detected again
+                    if !trace.gpus.iter().any(|g| g.id == gpu.id) {
+                        trace.gpus.push(gpu);
+                    }
+                }
+                ["drive", device, model, crit_temp] => trace.drives.push(DriveInfo {
+                    device: unescape(device),
+                    model: unescape(model),
+                    crit_temp: crit_temp.parse().map_err(|_| invalid())?,
+                }),
+                ["frame" | "gpu" | "drive", ..] => return Err(invalid()),
+                [key, value] => {
+                    let value = match value.strip_prefix('!') {
+                        Some(e) => Err(unescape(e)),
+                        None => Ok(unescape(value)),
+                    };
+                    if let Some(frame) = trace.frames.last_mut() {
+                        frame.entry(unescape(key)).or_insert(value);
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        if trace.frames.len() > 1 && trace.frames.last().is_some_and(Frame::is_empty) {
+            trace.frames.pop();
+        }
+        Ok(trace)
+    }
+
+    pub fn gpus(&self) -> &[GpuInfo] {
+        &self.gpus
+    }
+
+    pub fn drives(&self) -> &[DriveInfo] {
+        &self.drives
+    }
+
+    /// Was `key` read at all while recording
+    pub fn has_input(&self, key: &str) -> bool {
+        self.frames.iter().any(|f| f.contains_key(key))
+    }
+
+    /// The reading of `key` in the current update, None if it wasn't read
+    pub fn input(&self, key: &str) -> Option<&Result<String, String>> {
+        self.frames.get(self.current)?.get(key)
+    }
+
+    /// Move to the next update, the last one is kept once the trace ended
+    pub fn next_frame(&mut self) {
+        if self.current + 1 < self.frames.len() {
+            self.current += 1;
+            if self.current + 1 == self.frames.len() {
+                info!("Replaying the last update of the trace, it's kept from now on");
+            }
+        }
+    }
+}
+
+enum Mode {
+    Off,
+    Record(Recorder<BufWriter<File>>),
+    Replay(Trace),
+}
+
+static MODE: Mutex<Mode> = Mutex::new(Mode::Off);
+
+fn mode() -> MutexGuard<'static, Mode> {
+    MODE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Recording stops at the first write error, the sensors keep working
+fn record_with(write: impl FnOnce(&mut Recorder<BufWriter<File>>) -> io::Result<()>) {
+    let mut mode = mode();
+    if let Mode::Record(recorder) = &mut *mode
+        && let Err(e) = write(recorder)
+    {
+        warn!("Stopped recording the trace: {e}");
+        *mode = Mode::Off;
+    }
+}
+
+/// Record the sensor inputs to `path` from now on
+pub fn record(path: &Path) -> io::Result<()> {
+    let recorder = Recorder::new(BufWriter::new(File::create(path)?))?;
+    info!("Recording a trace to {}", path.display());
+    *mode() = Mode::Record(recorder);
+    Ok(())
+}
+
+/// Replace the sensor inputs with the trace in `path`
+pub fn replay(path: &Path) -> io::Result<()> {
+    let text = std::fs::read_to_string(path)?;
+    let trace = Trace::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    info!(
+        "Replaying {} updates from {}",
+        trace.frames.len(),
+        path.display()
+    );
+    *mode() = Mode::Replay(trace);
+    Ok(())
+}
+
+pub fn is_recording() -> bool {
+    matches!(*mode(), Mode::Record(_))
+}
+
+pub fn is_replaying() -> bool {
+    matches!(*mode(), Mode::Replay(_))
+}
+
+/// Call between two updates of the sensors
+pub fn next_frame() {
+    if let Mode::Replay(trace) = &mut *mode() {
+        trace.next_frame();
+        return;
+    }
+    record_with(Recorder::frame);
+}
+
+/// Take the reading `key` from `read`, or from the trace while replaying
+pub fn input<T, E>(key: &str, read: impl FnOnce() -> Result<T, E>) -> io::Result<T>
+where
+    T: Display + FromStr,
+    E: Display,
+{
+    if let Mode::Replay(trace) = &*mode() {
+        return match trace.input(key) {
+            Some(Ok(value)) => value.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid {key} in the trace: {value:?}"),
+                )
+            }),
+            Some(Err(e)) => Err(io::Error::other(e.clone())),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No {key} in this update of the trace"),
+            )),
+        };
+    }
+
+    // Not locked while reading, the hardware may be slow
+    let value = read().map_err(|e| e.to_string());
+    record_with(|recorder| match &value {
+        Ok(value) => recorder.input(key, Ok(&value.to_string())),
+        Err(e) => recorder.input(key, Err(e)),
+    });
+    value.map_err(io::Error::other)
+}
+
+/// Like `input` for a reading that may not exist, only the values are recorded
+pub fn optional_input<T>(key: &str, read: impl FnOnce() -> Option<T>) -> Option<T>
+where
+    T: Display + FromStr,
+{
+    if is_replaying() {
+        return input(key, || Err::<T, &str>("Only read from the trace")).ok();
+    }
+    let value = read();
+    if let Some(value) = &value {
+        record_with(|recorder| recorder.input(key, Ok(&value.to_string())));
+    }
+    value
+}
+
+pub fn declare_gpu(gpu: &GpuInfo) {
+    record_with(|recorder| recorder.gpu(gpu));
+}
+
+pub fn declare_drive(drive: &DriveInfo) {
+    record_with(|recorder| recorder.drive(drive));
+}
+
+/// The GPUs of the trace while replaying
+pub fn replayed_gpus() -> Option<Vec<GpuInfo>> {
+    match &*mode() {
+        Mode::Replay(trace) => Some(trace.gpus().to_vec()),
+        _ => None,
+    }
+}
+
+/// The drive temperature sensors of the trace while replaying
+pub fn replayed_drives() -> Option<Vec<DriveInfo>> {
+    match &*mode() {
+        Mode::Replay(trace) => Some(trace.drives().to_vec()),
+        _ => None,
+    }
+}
+
+/// Is `key` in the trace being replayed
+pub fn has_input(key: &str) -> bool {
+    match &*mode() {
+        Mode::Replay(trace) => trace.has_input(key),
+        _ => false,
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(c) => out.push(c),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded() -> String {
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        recorder
+            .gpu(&GpuInfo {
+                id: String::from("0000:01:00.0"),
+                name: String::from("Radeon\tRX"),
+                vram_total: 8_589_934_592,
+                has_temperature: true,
+                accelerator: false,
+                metrics: vec![GpuMetric::Power, GpuMetric::Fan],
+            })
+            .unwrap();
+        recorder
+            .drive(&DriveInfo {
+                device: String::from("nvme0n1"),
+                model: String::from("Samsung SSD 980"),
+                crit_temp: 84.85,
+            })
+            .unwrap();
+        recorder
+            .input("cpu", Ok("cpu0 10 0 5 100 0 0 0 0\ncpu1 20 0 5 90 0 0 0 0"))
+            .unwrap();
+        recorder.frame().unwrap();
+        recorder.input("cputemp", Ok("45.5")).unwrap();
+        recorder.input("cputemp", Ok("99")).unwrap();
+        recorder
+            .input("gpu:0000:01:00.0:Power", Err("not supported"))
+            .unwrap();
+        recorder.frame().unwrap();
+        recorder.input("cputemp", Ok("46.25")).unwrap();
+        recorder.frame().unwrap();
+        String::from_utf8(recorder.out).unwrap()
+    }
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let mut trace = Trace::parse(&recorded()).unwrap();
+        assert_eq!(trace.gpus()[0].name, "Radeon\tRX");
+        assert_eq!(
+            trace.gpus()[0].metrics,
+            vec![GpuMetric::Power, GpuMetric::Fan]
+        );
+        assert_eq!(trace.drives()[0].crit_temp, 84.85);
+        assert!(trace.has_input("cputemp"));
+
+        // Detection and the first update
+        assert_eq!(
+            trace.input("cpu"),
+            Some(&Ok(String::from(
+                "cpu0 10 0 5 100 0 0 0 0\ncpu1 20 0 5 90 0 0 0 0"
+            )))
+        );
+        assert_eq!(trace.input("cputemp"), None);
+
+        // The first reading of an update counts
+        trace.next_frame();
+        assert_eq!(trace.input("cputemp"), Some(&Ok(String::from("45.5"))));
+        assert_eq!(
+            trace.input("gpu:0000:01:00.0:Power"),
+            Some(&Err(String::from("not supported")))
+        );
+
+        // The last update is kept
+        trace.next_frame();
+        trace.next_frame();
+        assert_eq!(trace.input("cputemp"), Some(&Ok(String::from("46.25"))));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Trace::parse("timestamp,instance,sensor\n").is_err());
+        assert!(Trace::parse("minimon-trace\t1\ngpu\tid\n").is_err());
+        assert!(Trace::parse("minimon-trace\t1\ngpu\tid\tname\t1\t1\t0\tWarp\n").is_err());
+    }
+
+    #[test]
+    fn escapes_round_trip() {
+        for s in [
+            "plain",
+            "tab\there",
+            "line\nbreak",
+            "back\\slash\\n",
+            "end\\",
+        ] {
+            assert_eq!(unescape(&escape(s)), s);
+        }
+    }
+}